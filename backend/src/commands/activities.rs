@@ -3,12 +3,13 @@
 use std::collections::HashMap;
 
 use crate::commands::common::AppState;
-use crate::database::Activity;
+use crate::database::{Activity, ActivityFilter, BulkAction, BulkResult, ReapplyOptions, ReapplyProgress};
 use crate::plugin_system::extensions::EntityType;
-use tauri::State;
+use tauri::{State, Window};
 
 /// Get activities for a time range with optional pagination (lazy loading).
 /// If limit is None, returns all activities (backward compatibility).
+/// `filter` narrows the list by idle state, categories and included or excluded tags.
 /// When extension_registry is available, plugin query filters are applied after the database query.
 #[tauri::command]
 pub fn get_activities(
@@ -17,11 +18,12 @@ pub fn get_activities(
     end: i64,
     limit: Option<i64>,
     offset: Option<i64>,
+    filter: Option<ActivityFilter>,
     filter_params: Option<HashMap<String, serde_json::Value>>,
 ) -> Result<Vec<Activity>, String> {
     let activities = state
         .db
-        .get_activities(start, end, limit, offset, None, None)
+        .get_filtered_activities(start, end, limit, offset, &filter.unwrap_or_default())
         .map_err(|e: rusqlite::Error| e.to_string())?;
 
     if let Some(reg) = &state.extension_registry {
@@ -76,8 +78,74 @@ pub fn delete_activity(state: State<'_, AppState>, id: i64) -> Result<(), String
     state.db.delete_activity(id).map_err(|e: rusqlite::Error| e.to_string())
 }
 
-/// Reapply categorization rules to all activities
+/// Activity by id after an edit
+fn edited_activity(state: &AppState, id: i64) -> Result<Activity, String> {
+    state
+        .db
+        .get_activity_by_id(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Activity not found".to_string())
+}
+
+/// Split an activity at `at`; returns the earlier and the later part
+#[tauri::command]
+pub fn split_activity(state: State<'_, AppState>, id: i64, at: i64) -> Result<Vec<Activity>, String> {
+    let earlier = state.db.split_activity(id, at).map_err(|e| e.to_string())?;
+    Ok(vec![edited_activity(&state, earlier)?, edited_activity(&state, id)?])
+}
+
+/// Merge activities of the same app, category and project with nothing tracked in between; returns the merged activity
 #[tauri::command]
-pub fn reapply_categorization_rules(state: State<'_, AppState>) -> Result<(), String> {
-    state.db.reapply_categorization_rules().map_err(|e: rusqlite::Error| e.to_string())
+pub fn merge_activities(state: State<'_, AppState>, ids: Vec<i64>) -> Result<Activity, String> {
+    let id = state.db.merge_activities(&ids).map_err(|e| e.to_string())?;
+    edited_activity(&state, id)
+}
+
+/// Move the start and end of an activity
+#[tauri::command]
+pub fn trim_activity(
+    state: State<'_, AppState>,
+    id: i64,
+    started_at: i64,
+    ended_at: i64,
+) -> Result<Activity, String> {
+    state
+        .db
+        .trim_activity(id, started_at, ended_at)
+        .map_err(|e| e.to_string())?;
+    edited_activity(&state, id)
+}
+
+/// Apply an action to every activity in a time range that matches a filter, in one
+/// transaction. With `dry_run` nothing is saved, but the counts and preview are the same.
+#[tauri::command]
+pub fn bulk_edit_activities(
+    state: State<'_, AppState>,
+    start: i64,
+    end: i64,
+    filter: Option<ActivityFilter>,
+    action: BulkAction,
+    dry_run: Option<bool>,
+) -> Result<BulkResult, String> {
+    state
+        .db
+        .bulk_edit_activities(start, end, &filter.unwrap_or_default(), &action, dry_run.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// Reapply categorization rules, optionally to a time range or a subset of rules.
+/// Categories set by hand are kept unless `force` is set in the options.
+/// Emits `reapply-progress` events to the calling window while it runs, off the main thread.
+#[tauri::command(async)]
+pub fn reapply_categorization_rules(
+    window: Window,
+    state: State<'_, AppState>,
+    options: Option<ReapplyOptions>,
+) -> Result<ReapplyProgress, String> {
+    state
+        .db
+        .reapply_categorization_rules(&options.unwrap_or_default(), |progress| {
+            window.emit("reapply-progress", progress).ok();
+        })
+        .map_err(|e: rusqlite::Error| e.to_string())
 }
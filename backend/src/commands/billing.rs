@@ -0,0 +1,46 @@
+//! Billing commands
+
+use crate::commands::common::AppState;
+use crate::database::CategoryRate;
+use tauri::State;
+
+/// Get the rates of one category, or of all categories, oldest first
+#[tauri::command]
+pub fn get_category_rates(state: State<'_, AppState>, category_id: Option<i64>) -> Result<Vec<CategoryRate>, String> {
+    state.db.get_category_rates(category_id).map_err(|e| e.to_string())
+}
+
+/// Set the hourly rate of a category from `effective_from` on
+#[tauri::command]
+pub fn set_category_rate(
+    state: State<'_, AppState>,
+    category_id: i64,
+    hourly_rate: f64,
+    currency: String,
+    effective_from: i64,
+) -> Result<CategoryRate, String> {
+    if !hourly_rate.is_finite() || hourly_rate < 0.0 {
+        return Err("Hourly rate must be zero or positive".to_string());
+    }
+    let currency = currency.trim().to_uppercase();
+    if currency.is_empty() {
+        return Err("Currency cannot be empty".to_string());
+    }
+    let id = state
+        .db
+        .set_category_rate(category_id, hourly_rate, &currency, effective_from)
+        .map_err(|e| e.to_string())?;
+    Ok(CategoryRate { id, category_id, hourly_rate, currency, effective_from })
+}
+
+/// Delete a rate; the previous rate of the category applies again
+#[tauri::command]
+pub fn delete_category_rate(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    state.db.delete_category_rate(id).map_err(|e| e.to_string())
+}
+
+/// Mark a category as billable or not, or with None make it follow its parent
+#[tauri::command]
+pub fn set_category_billable(state: State<'_, AppState>, id: i64, is_billable: Option<bool>) -> Result<(), String> {
+    state.db.set_category_billable(id, is_billable).map_err(|e| e.to_string())
+}
@@ -92,6 +92,8 @@ pub fn update_category(
         sort_order,
         is_system: current_category.is_system,
         is_pinned: is_pinned_bool,
+        parent_id: current_category.parent_id,
+        is_billable: current_category.is_billable,
     })
 }
 
@@ -101,6 +103,12 @@ pub fn delete_category(state: State<'_, AppState>, id: i64) -> Result<(), String
     state.db.delete_category(id).map_err(|e| e.to_string())
 }
 
+/// Nest a category under another one, or make it top-level with None
+#[tauri::command]
+pub fn set_category_parent(state: State<'_, AppState>, id: i64, parent_id: Option<i64>) -> Result<(), String> {
+    state.db.set_category_parent(id, parent_id).map_err(|e| e.to_string())
+}
+
 /// Reset system category to default values
 #[tauri::command]
 pub fn reset_system_category(state: State<'_, AppState>, id: i64) -> Result<CategoryResponse, String> {
@@ -20,6 +20,8 @@ pub struct CategoryResponse {
     pub sort_order: i64,
     pub is_system: bool,
     pub is_pinned: bool,
+    pub parent_id: Option<i64>,
+    pub is_billable: Option<bool>,
 }
 
 impl From<Category> for CategoryResponse {
@@ -33,10 +35,24 @@ impl From<Category> for CategoryResponse {
             sort_order: category.sort_order,
             is_system: category.is_system,
             is_pinned: category.is_pinned,
+            parent_id: category.parent_id,
+            is_billable: category.is_billable,
         }
     }
 }
 
+/// Colour of clients, projects and tags created without one
+pub const DEFAULT_COLOR: &str = "#888888";
+
+/// Trimmed name of a client, project or tag, rejecting empty ones
+pub fn checked_name(name: &str, what: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("{} name cannot be empty", what));
+    }
+    Ok(name.to_string())
+}
+
 /// Convert i32 from frontend (-1 = null, 0 = false, 1 = true) to Option<bool>.
 pub fn i32_to_opt_bool(val: i32) -> Option<bool> {
     match val {
@@ -50,7 +66,6 @@ pub fn i32_to_opt_bool(val: i32) -> Option<bool> {
 pub struct AppState {
     pub db: Arc<Database>,
     pub tracker: Arc<Mutex<Option<Arc<Tracker>>>>,
-    pub thinking_mode_entry_id: Arc<Mutex<Option<i64>>>,
     pub plugin_registry: Option<Arc<PluginRegistry>>,
     pub extension_registry: Option<Arc<ExtensionRegistry>>,
     pub plugin_loader: Option<Arc<PluginLoader>>,
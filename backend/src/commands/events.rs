@@ -0,0 +1,19 @@
+//! Watcher event commands
+
+use crate::commands::common::AppState;
+use crate::database::Event;
+use tauri::State;
+
+/// Get events reported by external watchers for a time range
+#[tauri::command]
+pub fn get_events(
+    state: State<'_, AppState>,
+    start: i64,
+    end: i64,
+    source: Option<String>,
+) -> Result<Vec<Event>, String> {
+    state
+        .db
+        .get_events(start, end, source.as_deref())
+        .map_err(|e| e.to_string())
+}
@@ -1,11 +1,53 @@
 //! Export commands
 
 use crate::commands::common::AppState;
+use crate::database::{Activity, BilledActivity, Note};
 use chrono::{Utc, TimeZone};
 use tauri::State;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 
+/// Billed rows of the billable activities in a range, by activity id; an activity
+/// crossing the start of a new rate has one row per rate
+fn billed_by_activity(state: &AppState, start: i64, end: i64) -> Result<HashMap<i64, Vec<BilledActivity>>, String> {
+    let mut billed: HashMap<i64, Vec<BilledActivity>> = HashMap::new();
+    for row in state.db.get_billed_activities(start, end).map_err(|e| e.to_string())? {
+        if let Some(activity_id) = row.activity_id {
+            billed.entry(activity_id).or_default().push(row);
+        }
+    }
+    Ok(billed)
+}
+
+/// Distinct rates of billed rows, in order
+fn billed_rates(rows: &[BilledActivity]) -> Vec<f64> {
+    let mut rates: Vec<f64> = Vec::new();
+    for rate in rows.iter().filter_map(|b| b.hourly_rate) {
+        if !rates.contains(&rate) {
+            rates.push(rate);
+        }
+    }
+    rates
+}
+
+/// Notes on an activity, then notes on spans overlapping it
+fn notes_for<'a>(notes: &'a [Note], activity: &Activity) -> Vec<&'a str> {
+    let attached = notes.iter().filter(|n| n.activity_id == Some(activity.id));
+    let spans = notes.iter().filter(|n| {
+        n.activity_id.is_none() && n.started_at < activity.ended_at && n.ended_at > activity.started_at
+    });
+    attached.chain(spans).map(|n| n.body.as_str()).collect()
+}
+
+/// Duration as HH:MM:SS
+fn format_duration(duration_sec: i64) -> String {
+    let hours = duration_sec / 3600;
+    let minutes = (duration_sec % 3600) / 60;
+    let seconds = duration_sec % 60;
+    format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+}
+
 /// Export to CSV
 #[tauri::command]
 pub fn export_to_csv(
@@ -16,6 +58,9 @@ pub fn export_to_csv(
 ) -> Result<(), String> {
     let activities = state.db.get_activities(start, end, None, None, None, None).map_err(|e| e.to_string())?;
     let categories = state.db.get_categories().map_err(|e| e.to_string())?;
+    let projects = state.db.get_projects(true).map_err(|e| e.to_string())?;
+    let billed = billed_by_activity(&state, start, end)?;
+    let notes = state.db.get_notes(start, end).map_err(|e| e.to_string())?;
     
     let mut file = File::create(&file_path)
         .map_err(|e| format!("Failed to create CSV file: {}", e))?;
@@ -25,7 +70,8 @@ pub fn export_to_csv(
     
     let mut wtr = csv::Writer::from_writer(file);
     
-    wtr.write_record(&["id", "app_name", "window_title", "category", "started_at", "duration", "is_idle"])
+    wtr.write_record(&["id", "app_name", "window_title", "category", "project", "started_at", "duration", "is_idle",
+                         "billable_duration", "hourly_rate", "currency", "amount", "notes"])
         .map_err(|e| format!("Failed to write CSV header: {}", e))?;
     
     for activity in &activities {
@@ -33,25 +79,35 @@ pub fn export_to_csv(
             .and_then(|id| categories.iter().find(|c| c.id == id))
             .map(|c| c.name.clone())
             .unwrap_or_else(|| "Uncategorized".to_string());
+        let project_name = activity.project_id
+            .and_then(|id| projects.iter().find(|p| p.id == id))
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
         
         let started_at_dt = Utc.timestamp_opt(activity.started_at, 0)
             .single()
             .ok_or_else(|| format!("Invalid timestamp: {}", activity.started_at))?;
         let started_at_formatted = started_at_dt.format("%Y-%m-%d %H:%M:%S").to_string();
         
-        let hours = activity.duration_sec / 3600;
-        let minutes = (activity.duration_sec % 3600) / 60;
-        let seconds = activity.duration_sec % 60;
-        let duration_formatted = format!("{:02}:{:02}:{:02}", hours, minutes, seconds);
+        let duration_formatted = format_duration(activity.duration_sec);
+        let billing = billed.get(&activity.id);
         
         wtr.write_record(&[
             activity.id.to_string(),
             activity.app_name.clone(),
             activity.window_title.clone().unwrap_or_else(|| "".to_string()),
             category_name,
+            project_name,
             started_at_formatted,
             duration_formatted,
             activity.is_idle.to_string(),
+            billing.map(|rows| format_duration(rows.iter().map(|b| b.billed_seconds).sum())).unwrap_or_default(),
+            billing
+                .map(|rows| billed_rates(rows).iter().map(|r| r.to_string()).collect::<Vec<_>>().join(" / "))
+                .unwrap_or_default(),
+            billing.and_then(|rows| rows.iter().find_map(|b| b.currency.clone())).unwrap_or_default(),
+            billing.map(|rows| format!("{:.2}", rows.iter().map(|b| b.amount).sum::<f64>())).unwrap_or_default(),
+            notes_for(&notes, activity).join(" | "),
         ]).map_err(|e| format!("Failed to write CSV row: {}", e))?;
     }
     
@@ -68,8 +124,50 @@ pub fn export_to_json(
     file_path: String,
 ) -> Result<(), String> {
     let activities = state.db.get_activities(start, end, None, None, None, None).map_err(|e| e.to_string())?;
+    let billed = billed_by_activity(&state, start, end)?;
+    let notes = state.db.get_notes(start, end).map_err(|e| e.to_string())?;
+
+    // Activities as stored, plus their notes and billed time and amount for billable ones
+    let mut rows = Vec::with_capacity(activities.len());
+    for activity in &activities {
+        let mut row = serde_json::to_value(activity)
+            .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+        if let Some(fields) = row.as_object_mut() {
+            let activity_notes = notes_for(&notes, activity);
+            if !activity_notes.is_empty() {
+                fields.insert("notes".to_string(), serde_json::json!(activity_notes));
+            }
+            if let Some(billing) = billed.get(&activity.id) {
+                let amount = (billing.iter().map(|b| b.amount).sum::<f64>() * 100.0).round() / 100.0;
+                fields.insert(
+                    "billed_duration_sec".to_string(),
+                    billing.iter().map(|b| b.billed_seconds).sum::<i64>().into(),
+                );
+                fields.insert("hourly_rate".to_string(), serde_json::json!(billing[0].hourly_rate));
+                fields.insert("currency".to_string(), serde_json::json!(billing[0].currency));
+                fields.insert("amount".to_string(), serde_json::json!(amount));
+                // Activities crossing the start of a new rate list their time per rate
+                if billing.len() > 1 {
+                    let parts: Vec<_> = billing
+                        .iter()
+                        .map(|b| {
+                            serde_json::json!({
+                                "started_at": b.started_at,
+                                "billed_duration_sec": b.billed_seconds,
+                                "hourly_rate": b.hourly_rate,
+                                "currency": b.currency,
+                                "amount": b.amount,
+                            })
+                        })
+                        .collect();
+                    fields.insert("billing".to_string(), serde_json::json!(parts));
+                }
+            }
+        }
+        rows.push(row);
+    }
     
-    let json = serde_json::to_string_pretty(&activities)
+    let json = serde_json::to_string_pretty(&rows)
         .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
     
     std::fs::write(&file_path, json)
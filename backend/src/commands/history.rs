@@ -0,0 +1,17 @@
+//! Edit history commands
+
+use crate::commands::common::AppState;
+use crate::database::Change;
+use tauri::State;
+
+/// Recorded changes to activities, manual entries, categories and rules, most recent first
+#[tauri::command]
+pub fn list_changes(state: State<'_, AppState>, limit: Option<i64>) -> Result<Vec<Change>, String> {
+    state.db.list_changes(limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
+/// Undo the most recent change that is not undone yet; returns it, or None if there is nothing to undo
+#[tauri::command]
+pub fn undo_last_change(state: State<'_, AppState>) -> Result<Option<Change>, String> {
+    state.db.undo_last_change().map_err(|e| e.to_string())
+}
@@ -1,19 +1,29 @@
 //! Idle detection commands
 
 use crate::commands::common::AppState;
+use crate::database::{IdleSegment, ManualEntry};
 use tauri::State;
 
 /// Get idle time
 #[tauri::command]
 pub fn get_idle_time() -> Result<u64, String> {
-    let monitor = crate::idle::IdleMonitor::new();
-    Ok(monitor.get_idle_time())
+    Ok(crate::idle::IdleMonitor::shared().get_idle_time())
+}
+
+/// Get the name of the idle detection source in use (e.g. "mutter", "logind")
+#[tauri::command]
+pub fn get_idle_source(state: State<'_, AppState>) -> Result<String, String> {
+    let tracker = state.tracker.lock().map_err(|e| e.to_string())?.clone();
+    Ok(match tracker {
+        Some(tracker) => tracker.idle_source_name().to_string(),
+        None => crate::idle::IdleMonitor::shared().source_name().to_string(),
+    })
 }
 
 /// Check idle state
 #[tauri::command]
 pub fn check_idle_state(seconds: Option<u64>) -> Result<bool, String> {
-    let monitor = crate::idle::IdleMonitor::new();
+    let monitor = crate::idle::IdleMonitor::shared();
     Ok(if let Some(secs) = seconds {
         monitor.is_idle_for(secs)
     } else {
@@ -47,3 +57,18 @@ pub fn classify_idle_time(
     
     Ok(())
 }
+
+/// Split an idle period into segments, each with its own category, description and
+/// project (e.g. a meeting, then lunch, then a call). The idle record starting at
+/// `idle_started_at` is replaced by one manual entry per segment; uncovered time stays idle.
+#[tauri::command]
+pub fn split_idle_time(
+    state: State<'_, AppState>,
+    idle_started_at: i64,
+    segments: Vec<IdleSegment>,
+) -> Result<Vec<ManualEntry>, String> {
+    state
+        .db
+        .split_idle_activity(idle_started_at, &segments)
+        .map_err(|e| e.to_string())
+}
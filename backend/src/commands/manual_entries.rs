@@ -2,7 +2,8 @@
 
 use crate::database::ManualEntry;
 use crate::commands::common::AppState;
-use chrono::Utc;
+use crate::commands::timers::stop_running_timer;
+use chrono::Local;
 use tauri::State;
 
 /// Add manual entry
@@ -128,66 +129,71 @@ pub fn delete_manual_entry(state: State<'_, AppState>, id: i64) -> Result<(), St
     state.db.delete_manual_entry(id).map_err(|e| e.to_string())
 }
 
-/// Start manual entry (for thinking mode, etc.)
+/// Manual entry by id after an edit
+fn edited_entry(state: &AppState, id: i64) -> Result<ManualEntry, String> {
+    state
+        .db
+        .get_manual_entry(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Entry not found".to_string())
+}
+
+/// Split a manual entry at `at`; returns the earlier and the later part
+#[tauri::command]
+pub fn split_manual_entry(state: State<'_, AppState>, id: i64, at: i64) -> Result<Vec<ManualEntry>, String> {
+    let earlier = state.db.split_manual_entry(id, at).map_err(|e| e.to_string())?;
+    Ok(vec![edited_entry(&state, earlier)?, edited_entry(&state, id)?])
+}
+
+/// Merge manual entries with the same category and project; returns the merged entry
+#[tauri::command]
+pub fn merge_manual_entries(state: State<'_, AppState>, ids: Vec<i64>) -> Result<ManualEntry, String> {
+    let id = state.db.merge_manual_entries(&ids).map_err(|e| e.to_string())?;
+    edited_entry(&state, id)
+}
+
+/// Move the start and end of a manual entry
+#[tauri::command]
+pub fn trim_manual_entry(
+    state: State<'_, AppState>,
+    id: i64,
+    started_at: i64,
+    ended_at: i64,
+) -> Result<ManualEntry, String> {
+    state
+        .db
+        .trim_manual_entry(id, started_at, ended_at)
+        .map_err(|e| e.to_string())?;
+    edited_entry(&state, id)
+}
+
+/// Start manual entry as a running timer, named after its description and start
+/// time so that entries with the same description can run side by side.
+/// Returns the timer id, which `stop_manual_entry` takes.
 #[tauri::command]
 pub fn start_manual_entry(
     state: State<'_, AppState>,
     category_id: i64,
     description: Option<String>,
 ) -> Result<i64, String> {
-    let now = Utc::now().timestamp();
-    let id = state
+    let started_at = Local::now();
+    let name = format!(
+        "{} ({})",
+        description
+            .as_deref()
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .unwrap_or("Manual entry"),
+        started_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    state
         .db
-        .add_manual_entry(
-            description.as_deref(),
-            Some(category_id),
-            now,
-            now, // Will be updated when stopped
-        )
-        .map_err(|e: rusqlite::Error| e.to_string())?;
-    
-    // Store the entry ID for later update
-    *state.thinking_mode_entry_id.lock().unwrap() = Some(id);
-    
-    Ok(id)
+        .start_timer(&name, Some(category_id), description.as_deref(), started_at.timestamp())
+        .map_err(|e: rusqlite::Error| e.to_string())
 }
 
-/// Stop manual entry
+/// Stop the timer `start_manual_entry` started and return the manual entry it became
 #[tauri::command]
-pub fn stop_manual_entry(state: State<'_, AppState>) -> Result<ManualEntry, String> {
-    let entry_id = state.thinking_mode_entry_id.lock().unwrap().take()
-        .ok_or_else(|| "No active manual entry".to_string())?;
-    
-    let now = Utc::now().timestamp();
-    
-    // Get the entry to find its start time
-    let entry = state
-        .db
-        .get_manual_entries(now - 86400, now + 86400)
-        .map_err(|e: rusqlite::Error| e.to_string())?
-        .into_iter()
-        .find(|e| e.id == entry_id)
-        .ok_or_else(|| "Entry not found".to_string())?;
-    
-    // Update with end time
-    state
-        .db
-        .update_manual_entry(
-            entry_id,
-            entry.description.as_deref(),
-            entry.category_id,
-            entry.started_at,
-            now,
-        )
-        .map_err(|e: rusqlite::Error| e.to_string())?;
-    
-    let updated_entry = state
-        .db
-        .get_manual_entries(now - 86400, now + 86400)
-        .map_err(|e: rusqlite::Error| e.to_string())?
-        .into_iter()
-        .find(|e| e.id == entry_id)
-        .ok_or_else(|| "Failed to retrieve updated entry".to_string())?;
-    
-    Ok(updated_entry)
+pub fn stop_manual_entry(state: State<'_, AppState>, id: i64) -> Result<ManualEntry, String> {
+    stop_running_timer(&state, id)
 }
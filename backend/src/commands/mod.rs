@@ -13,6 +13,15 @@
 //! - window: Window management commands
 //! - domains: Domain statistics commands
 //! - plugins: Plugin management commands
+//! - events: Watcher event commands
+//! - privacy: Privacy filter commands
+//! - schedule: Work-hours schedule commands
+//! - timers: Running timer commands
+//! - projects: Client and project commands
+//! - tags: Tag commands
+//! - billing: Category rate and billable commands
+//! - notes: Note commands
+//! - history: Edit history and undo commands
 //! - common: Shared types and utilities
 
 pub mod activities;
@@ -27,6 +36,15 @@ pub mod export;
 pub mod window;
 pub mod domains;
 pub mod plugins;
+pub mod events;
+pub mod privacy;
+pub mod schedule;
+pub mod timers;
+pub mod projects;
+pub mod tags;
+pub mod billing;
+pub mod notes;
+pub mod history;
 pub mod common;
 
 // Re-export AppState and common types
@@ -45,3 +63,12 @@ pub use export::*;
 pub use window::*;
 pub use domains::*;
 pub use plugins::*;
+pub use events::*;
+pub use privacy::*;
+pub use schedule::*;
+pub use timers::*;
+pub use projects::*;
+pub use tags::*;
+pub use billing::*;
+pub use notes::*;
+pub use history::*;
@@ -0,0 +1,91 @@
+//! Note commands
+
+use crate::commands::common::AppState;
+use crate::database::Note;
+use tauri::State;
+
+const DEFAULT_SEARCH_LIMIT: i64 = 100;
+
+/// Trimmed note text, rejecting empty notes
+fn checked_body(body: &str) -> Result<String, String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err("Note cannot be empty".to_string());
+    }
+    Ok(body.to_string())
+}
+
+/// Note by id, after it was written
+fn saved_note(state: &AppState, id: i64) -> Result<Note, String> {
+    state
+        .db
+        .get_note(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Note not found".to_string())
+}
+
+/// Get notes overlapping a time range
+#[tauri::command]
+pub fn get_notes(state: State<'_, AppState>, start: i64, end: i64) -> Result<Vec<Note>, String> {
+    state.db.get_notes(start, end).map_err(|e| e.to_string())
+}
+
+/// Get the notes of one activity
+#[tauri::command]
+pub fn get_activity_notes(state: State<'_, AppState>, activity_id: i64) -> Result<Vec<Note>, String> {
+    state.db.get_activity_notes(activity_id).map_err(|e| e.to_string())
+}
+
+/// Attach a note to an activity
+#[tauri::command]
+pub fn add_activity_note(state: State<'_, AppState>, activity_id: i64, body: String) -> Result<Note, String> {
+    let body = checked_body(&body)?;
+    let id = state.db.add_activity_note(activity_id, &body).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => "Activity not found".to_string(),
+        e => e.to_string(),
+    })?;
+    saved_note(&state, id)
+}
+
+/// Attach a note to the span [started_at, ended_at)
+#[tauri::command]
+pub fn add_span_note(
+    state: State<'_, AppState>,
+    started_at: i64,
+    ended_at: i64,
+    body: String,
+) -> Result<Note, String> {
+    if started_at >= ended_at {
+        return Err("Note span must end after it starts".to_string());
+    }
+    let body = checked_body(&body)?;
+    let id = state.db.add_span_note(started_at, ended_at, &body).map_err(|e| e.to_string())?;
+    saved_note(&state, id)
+}
+
+/// Replace the text of a note
+#[tauri::command]
+pub fn update_note(state: State<'_, AppState>, id: i64, body: String) -> Result<Note, String> {
+    let body = checked_body(&body)?;
+    state.db.update_note(id, &body).map_err(|e| e.to_string())?;
+    saved_note(&state, id)
+}
+
+/// Delete a note
+#[tauri::command]
+pub fn delete_note(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    state.db.delete_note(id).map_err(|e| e.to_string())
+}
+
+/// Find notes containing a text, most recent first
+#[tauri::command]
+pub fn search_notes(state: State<'_, AppState>, query: String, limit: Option<i64>) -> Result<Vec<Note>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    state
+        .db
+        .search_notes(query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,15 @@
+//! Privacy commands
+
+use crate::commands::common::AppState;
+use crate::database::PurgeSummary;
+use crate::privacy::PrivacyFilter;
+use tauri::State;
+
+/// Apply the current privacy settings to already recorded activities:
+/// delete ignored apps and private windows, hide masked titles and apply redactions
+#[tauri::command]
+pub fn purge_excluded_data(state: State<'_, AppState>) -> Result<PurgeSummary, String> {
+    let settings = state.db.get_all_settings().map_err(|e| e.to_string())?;
+    let filter = PrivacyFilter::from_settings(&settings)?;
+    state.db.purge_activities(&filter).map_err(|e| e.to_string())
+}
@@ -0,0 +1,115 @@
+//! Client and project commands
+
+use crate::commands::common::{AppState, DEFAULT_COLOR, checked_name};
+use crate::database::{Client, Project};
+use tauri::State;
+
+/// Get clients; archived ones only if requested
+#[tauri::command]
+pub fn get_clients(state: State<'_, AppState>, include_archived: Option<bool>) -> Result<Vec<Client>, String> {
+    state
+        .db
+        .get_clients(include_archived.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// Create a client
+#[tauri::command]
+pub fn create_client(state: State<'_, AppState>, name: String, color: Option<String>) -> Result<Client, String> {
+    let name = checked_name(&name, "Client")?;
+    let color = color.unwrap_or_else(|| DEFAULT_COLOR.to_string());
+    let id = state.db.create_client(&name, &color).map_err(|e| e.to_string())?;
+    Ok(Client { id, name, color, archived: false })
+}
+
+/// Update a client, including archiving or restoring it
+#[tauri::command]
+pub fn update_client(
+    state: State<'_, AppState>,
+    id: i64,
+    name: String,
+    color: String,
+    archived: bool,
+) -> Result<Client, String> {
+    let name = checked_name(&name, "Client")?;
+    state
+        .db
+        .update_client(id, &name, &color, archived)
+        .map_err(|e| e.to_string())?;
+    Ok(Client { id, name, color, archived })
+}
+
+/// Delete a client; its projects are kept without a client
+#[tauri::command]
+pub fn delete_client(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    state.db.delete_client(id).map_err(|e| e.to_string())
+}
+
+/// Get projects; archived ones only if requested
+#[tauri::command]
+pub fn get_projects(state: State<'_, AppState>, include_archived: Option<bool>) -> Result<Vec<Project>, String> {
+    state
+        .db
+        .get_projects(include_archived.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// Create a project, optionally for a client
+#[tauri::command]
+pub fn create_project(
+    state: State<'_, AppState>,
+    name: String,
+    client_id: Option<i64>,
+    color: Option<String>,
+) -> Result<Project, String> {
+    let name = checked_name(&name, "Project")?;
+    let color = color.unwrap_or_else(|| DEFAULT_COLOR.to_string());
+    let id = state
+        .db
+        .create_project(&name, client_id, &color)
+        .map_err(|e| e.to_string())?;
+    Ok(Project { id, name, client_id, color, archived: false })
+}
+
+/// Update a project, including archiving or restoring it
+#[tauri::command]
+pub fn update_project(
+    state: State<'_, AppState>,
+    id: i64,
+    name: String,
+    client_id: Option<i64>,
+    color: String,
+    archived: bool,
+) -> Result<Project, String> {
+    let name = checked_name(&name, "Project")?;
+    state
+        .db
+        .update_project(id, &name, client_id, &color, archived)
+        .map_err(|e| e.to_string())?;
+    Ok(Project { id, name, client_id, color, archived })
+}
+
+/// Delete a project; activities and manual entries assigned to it are unassigned
+#[tauri::command]
+pub fn delete_project(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    state.db.delete_project(id).map_err(|e| e.to_string())
+}
+
+/// Assign an activity to a project, or unassign it with None
+#[tauri::command]
+pub fn set_activity_project(state: State<'_, AppState>, id: i64, project_id: Option<i64>) -> Result<(), String> {
+    state.db.set_activity_project(id, project_id).map_err(|e| e.to_string())
+}
+
+/// Assign a manual entry to a project, or unassign it with None
+#[tauri::command]
+pub fn set_manual_entry_project(
+    state: State<'_, AppState>,
+    id: i64,
+    project_id: Option<i64>,
+) -> Result<(), String> {
+    state
+        .db
+        .set_manual_entry_project(id, project_id)
+        .map_err(|e| e.to_string())
+}
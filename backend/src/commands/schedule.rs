@@ -0,0 +1,102 @@
+//! Work-hours schedule commands
+
+use crate::commands::common::AppState;
+use crate::database::{ScheduleException, ScheduleWindow};
+use crate::schedule::{self, Schedule, SCHEDULE_ENABLED_KEY};
+use serde::Serialize;
+use tauri::State;
+
+/// Schedule response structure
+#[derive(Serialize)]
+pub struct ScheduleResponse {
+    pub enabled: bool,
+    pub windows: Vec<ScheduleWindow>,
+    pub exceptions: Vec<ScheduleException>,
+    /// Whether tracking is currently allowed; None while the schedule is disabled
+    pub in_schedule: Option<bool>,
+}
+
+/// Reload the schedule into the running tracker after a change
+fn reload_schedule(state: &AppState) {
+    if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
+        tracker.set_schedule(Schedule::load(&state.db));
+    }
+}
+
+/// Get the schedule with its windows and exceptions
+#[tauri::command]
+pub fn get_schedule(state: State<'_, AppState>) -> Result<ScheduleResponse, String> {
+    let schedule = Schedule::load(&state.db);
+    let in_schedule = schedule
+        .is_enabled()
+        .then(|| schedule.contains_timestamp(chrono::Utc::now().timestamp()));
+    Ok(ScheduleResponse {
+        enabled: schedule.is_enabled(),
+        windows: state.db.get_schedule_windows().map_err(|e| e.to_string())?,
+        exceptions: state.db.get_schedule_exceptions().map_err(|e| e.to_string())?,
+        in_schedule,
+    })
+}
+
+/// Turn enforcement of the schedule on or off
+#[tauri::command]
+pub fn set_schedule_enabled(state: State<'_, AppState>, enabled: bool) -> Result<(), String> {
+    state
+        .db
+        .set_setting(SCHEDULE_ENABLED_KEY, if enabled { "true" } else { "false" })
+        .map_err(|e| e.to_string())?;
+    reload_schedule(&state);
+    Ok(())
+}
+
+/// Add a weekly window (weekday 0 = Monday, minutes since midnight)
+#[tauri::command]
+pub fn add_schedule_window(
+    state: State<'_, AppState>,
+    weekday: i64,
+    start_minute: i64,
+    end_minute: i64,
+) -> Result<i64, String> {
+    schedule::validate_window(weekday, start_minute, end_minute)?;
+    let id = state
+        .db
+        .add_schedule_window(weekday, start_minute, end_minute)
+        .map_err(|e| e.to_string())?;
+    reload_schedule(&state);
+    Ok(id)
+}
+
+/// Delete a weekly window
+#[tauri::command]
+pub fn delete_schedule_window(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    state.db.delete_schedule_window(id).map_err(|e| e.to_string())?;
+    reload_schedule(&state);
+    Ok(())
+}
+
+/// Add a holiday, or a workday with its own hours, on a date (YYYY-MM-DD)
+#[tauri::command]
+pub fn add_schedule_exception(
+    state: State<'_, AppState>,
+    date: String,
+    kind: String,
+    start_minute: Option<i64>,
+    end_minute: Option<i64>,
+    label: Option<String>,
+) -> Result<i64, String> {
+    schedule::validate_exception(&date, &kind, start_minute, end_minute)?;
+    let id = state
+        .db
+        .add_schedule_exception(&date, &kind, start_minute, end_minute, label.as_deref())
+        .map_err(|e| e.to_string())?;
+    reload_schedule(&state);
+    Ok(id)
+}
+
+/// Delete a holiday or workday exception
+#[tauri::command]
+pub fn delete_schedule_exception(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    state.db.delete_schedule_exception(id).map_err(|e| e.to_string())?;
+    reload_schedule(&state);
+    Ok(())
+}
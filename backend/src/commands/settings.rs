@@ -1,6 +1,9 @@
 //! Settings management commands
 
 use crate::commands::common::AppState;
+use crate::database::billing::{ROUNDING_MINUTES_KEY, ROUNDING_SCOPE_KEY};
+use crate::privacy::{self, PrivacyFilter};
+use crate::tracker::NEVER_IDLE_APPS_KEY;
 use tauri::{State, AppHandle};
 use serde::{Deserialize, Serialize};
 
@@ -21,6 +24,20 @@ pub struct SettingsResponse {
     pub idle_prompt_threshold_seconds: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub plugin_registry_urls: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy_ignored_apps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy_masked_apps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy_title_redactions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy_skip_private_windows: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub never_idle_apps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_rounding_minutes: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_rounding_scope: Option<String>,
 }
 
 /// Get setting value
@@ -93,9 +110,39 @@ pub fn get_settings(state: State<'_, AppState>) -> Result<SettingsResponse, Stri
             .unwrap_or_else(|| "24h".to_string()),
         plugin_registry_urls: settings.get("plugin_registry_urls")
             .and_then(|v| serde_json::from_str::<Vec<String>>(v).ok()),
+        privacy_ignored_apps: Some(json_list(&settings, privacy::IGNORED_APPS_KEY)),
+        privacy_masked_apps: Some(json_list(&settings, privacy::MASKED_APPS_KEY)),
+        privacy_title_redactions: Some(json_list(&settings, privacy::TITLE_REDACTIONS_KEY)),
+        privacy_skip_private_windows: Some(
+            settings
+                .get(privacy::SKIP_PRIVATE_WINDOWS_KEY)
+                .map(|v| v != "false")
+                .unwrap_or(true),
+        ),
+        never_idle_apps: Some(json_list(&settings, NEVER_IDLE_APPS_KEY)),
+        billing_rounding_minutes: Some(
+            settings
+                .get(ROUNDING_MINUTES_KEY)
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(0),
+        ),
+        billing_rounding_scope: Some(
+            settings
+                .get(ROUNDING_SCOPE_KEY)
+                .cloned()
+                .unwrap_or_else(|| "entry".to_string()),
+        ),
     })
 }
 
+/// String list stored as a JSON array; empty if missing or malformed
+fn json_list(settings: &std::collections::HashMap<String, String>, key: &str) -> Vec<String> {
+    settings
+        .get(key)
+        .and_then(|v| serde_json::from_str::<Vec<String>>(v).ok())
+        .unwrap_or_default()
+}
+
 /// Update settings
 #[tauri::command]
 pub fn update_settings(
@@ -128,11 +175,46 @@ pub fn update_settings(
         }
     }
     
+    for (key, list) in [
+        (privacy::IGNORED_APPS_KEY, &settings.privacy_ignored_apps),
+        (privacy::MASKED_APPS_KEY, &settings.privacy_masked_apps),
+        (privacy::TITLE_REDACTIONS_KEY, &settings.privacy_title_redactions),
+        (NEVER_IDLE_APPS_KEY, &settings.never_idle_apps),
+    ] {
+        if let Some(list) = list {
+            settings_map.insert(key.to_string(), serde_json::to_string(list).map_err(|e| e.to_string())?);
+        }
+    }
+    if let Some(skip) = settings.privacy_skip_private_windows {
+        settings_map.insert(privacy::SKIP_PRIVATE_WINDOWS_KEY.to_string(), skip.to_string());
+    }
+    if let Some(minutes) = settings.billing_rounding_minutes {
+        if minutes < 0 {
+            return Err("Billing rounding cannot be negative".to_string());
+        }
+        settings_map.insert(ROUNDING_MINUTES_KEY.to_string(), minutes.to_string());
+    }
+    if let Some(scope) = &settings.billing_rounding_scope {
+        if scope != "entry" && scope != "day" {
+            return Err(format!("Unknown billing rounding scope: {}", scope));
+        }
+        settings_map.insert(ROUNDING_SCOPE_KEY.to_string(), scope.clone());
+    }
+
+    // Reject invalid redaction patterns before anything is saved
+    let mut privacy_settings = state.db.get_all_settings().map_err(|e| e.to_string())?;
+    privacy_settings.extend(settings_map.clone());
+    let privacy_filter = PrivacyFilter::from_settings(&privacy_settings)?;
+    
     state.db.set_settings(&settings_map).map_err(|e| e.to_string())?;
     
     if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
         tracker.set_idle_threshold(idle_threshold_secs as u64);
         tracker.set_prompt_threshold(idle_prompt_threshold_secs as u64);
+        tracker.set_privacy_filter(privacy_filter);
+        if let Some(apps) = settings.never_idle_apps {
+            tracker.set_never_idle_apps(apps);
+        }
     }
     
     if current_autostart != settings.autostart {
@@ -1,7 +1,7 @@
 //! Statistics commands
 
 use crate::commands::common::AppState;
-use crate::database::RangeStats;
+use crate::database::{CategoryTreeNode, CategoryUsageStat, Earning, ProjectStat, RangeStats, TagUsageStat};
 use tauri::State;
 use serde::Serialize;
 
@@ -12,6 +12,9 @@ pub struct StatsResponse {
     pub productive_seconds: i64,
     pub category_breakdown: Vec<CategoryTime>,
     pub app_breakdown: Vec<AppTime>,
+    pub category_tree: Vec<CategoryTreeNode>,
+    pub earnings: Vec<Earning>,
+    pub project_breakdown: Vec<ProjectTime>,
 }
 
 #[derive(Serialize)]
@@ -28,6 +31,14 @@ pub struct AppTime {
     pub seconds: i64,
 }
 
+#[derive(Serialize)]
+pub struct ProjectTime {
+    pub project_id: i64,
+    pub project_name: String,
+    pub color: String,
+    pub seconds: i64,
+}
+
 /// Get aggregated stats for a time range (SQL aggregation)
 #[tauri::command]
 pub fn get_stats(
@@ -40,6 +51,9 @@ pub fn get_stats(
         productive_seconds,
         category_breakdown: category_rows,
         app_breakdown: app_rows,
+        category_tree,
+        earnings,
+        project_breakdown: project_rows,
     } = state.db.get_stats_for_range(start, end).map_err(|e| e.to_string())?;
 
     let category_breakdown: Vec<CategoryTime> = category_rows
@@ -57,11 +71,24 @@ pub fn get_stats(
         .map(|(app_name, seconds)| AppTime { app_name, seconds })
         .collect();
 
+    let project_breakdown: Vec<ProjectTime> = project_rows
+        .into_iter()
+        .map(|(project_id, project_name, color, seconds)| ProjectTime {
+            project_id,
+            project_name,
+            color,
+            seconds,
+        })
+        .collect();
+
     Ok(StatsResponse {
         total_seconds,
         productive_seconds,
         category_breakdown,
         app_breakdown,
+        category_tree,
+        earnings,
+        project_breakdown,
     })
 }
 
@@ -84,6 +111,7 @@ pub fn get_daily_stats(
                 "icon": c.icon,
                 "is_productive": c.is_productive,
                 "sort_order": c.sort_order,
+                "parent_id": c.parent_id,
             })),
             "duration_sec": cs.duration_sec,
             "percentage": cs.percentage,
@@ -100,6 +128,7 @@ pub fn get_daily_stats(
                 "sort_order": c.sort_order,
             })),
         })).collect::<Vec<_>>(),
+        "category_tree": stats.category_tree,
     }))
 }
 
@@ -127,27 +156,63 @@ pub fn get_top_apps(
     })).collect())
 }
 
-/// Get category usage
+/// Get top projects recognised in editor window titles
 #[tauri::command]
-pub fn get_category_usage(
+pub fn get_top_projects(
     state: State<'_, AppState>,
     start: i64,
     end: i64,
-) -> Result<Vec<serde_json::Value>, String> {
-    let usage = state.db.get_category_usage(start, end).map_err(|e| e.to_string())?;
-    
-    Ok(usage.iter().map(|u| serde_json::json!({
-        "category": u.category.as_ref().map(|c| serde_json::json!({
+    limit: i64,
+) -> Result<Vec<ProjectStat>, String> {
+    state
+        .db
+        .get_top_projects(start, end, limit)
+        .map_err(|e| e.to_string())
+}
+
+/// Get time per tag; activities and manual entries with several tags count towards each
+#[tauri::command]
+pub fn get_tag_usage(
+    state: State<'_, AppState>,
+    start: i64,
+    end: i64,
+) -> Result<Vec<TagUsageStat>, String> {
+    state
+        .db
+        .get_tag_usage(start, end)
+        .map_err(|e| e.to_string())
+}
+
+/// Category usage node with its subcategories
+fn category_usage_json(u: &CategoryUsageStat) -> serde_json::Value {
+    let c = &u.category;
+    serde_json::json!({
+        "category": {
             "id": c.id,
             "name": c.name,
             "color": c.color,
             "icon": c.icon,
             "is_productive": c.is_productive,
             "sort_order": c.sort_order,
-        })),
+            "parent_id": c.parent_id,
+        },
         "duration_sec": u.duration_sec,
         "percentage": u.percentage,
-    })).collect())
+        "total_duration_sec": u.total_duration_sec,
+        "children": u.children.iter().map(category_usage_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Get category usage nested by parent category
+#[tauri::command]
+pub fn get_category_usage(
+    state: State<'_, AppState>,
+    start: i64,
+    end: i64,
+) -> Result<Vec<serde_json::Value>, String> {
+    let usage = state.db.get_category_usage(start, end).map_err(|e| e.to_string())?;
+    
+    Ok(usage.iter().map(category_usage_json).collect())
 }
 
 /// Get hourly activity
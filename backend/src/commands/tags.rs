@@ -0,0 +1,91 @@
+//! Tag commands
+
+use crate::commands::common::{AppState, DEFAULT_COLOR, checked_name};
+use crate::database::{ActivityFilter, Tag, TagLinks};
+use tauri::State;
+
+/// Get all tags
+#[tauri::command]
+pub fn get_tags(state: State<'_, AppState>) -> Result<Vec<Tag>, String> {
+    state.db.get_tags().map_err(|e| e.to_string())
+}
+
+/// Create a tag
+#[tauri::command]
+pub fn create_tag(state: State<'_, AppState>, name: String, color: Option<String>) -> Result<Tag, String> {
+    let name = checked_name(&name, "Tag")?;
+    let color = color.unwrap_or_else(|| DEFAULT_COLOR.to_string());
+    let id = state.db.create_tag(&name, &color).map_err(|e| e.to_string())?;
+    Ok(Tag { id, name, color })
+}
+
+/// Rename or recolour a tag
+#[tauri::command]
+pub fn update_tag(state: State<'_, AppState>, id: i64, name: String, color: String) -> Result<Tag, String> {
+    let name = checked_name(&name, "Tag")?;
+    state.db.update_tag(id, &name, &color).map_err(|e| e.to_string())?;
+    Ok(Tag { id, name, color })
+}
+
+/// Delete a tag and remove it from everything it was on
+#[tauri::command]
+pub fn delete_tag(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    state.db.delete_tag(id).map_err(|e| e.to_string())
+}
+
+/// Add a tag to activities and manual entries; returns how many were newly tagged
+#[tauri::command]
+pub fn tag_items(
+    state: State<'_, AppState>,
+    tag_id: i64,
+    activity_ids: Option<Vec<i64>>,
+    manual_entry_ids: Option<Vec<i64>>,
+) -> Result<usize, String> {
+    state
+        .db
+        .tag_items(
+            tag_id,
+            &activity_ids.unwrap_or_default(),
+            &manual_entry_ids.unwrap_or_default(),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Remove a tag from activities and manual entries; returns how many carried it
+#[tauri::command]
+pub fn untag_items(
+    state: State<'_, AppState>,
+    tag_id: i64,
+    activity_ids: Option<Vec<i64>>,
+    manual_entry_ids: Option<Vec<i64>>,
+) -> Result<usize, String> {
+    state
+        .db
+        .untag_items(
+            tag_id,
+            &activity_ids.unwrap_or_default(),
+            &manual_entry_ids.unwrap_or_default(),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Tag every activity in a time range that matches a filter; returns how many were newly tagged
+#[tauri::command]
+pub fn bulk_tag_activities(
+    state: State<'_, AppState>,
+    tag_id: i64,
+    start: i64,
+    end: i64,
+    filter: Option<ActivityFilter>,
+) -> Result<usize, String> {
+    state
+        .db
+        .bulk_tag_activities(tag_id, start, end, &filter.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Tags of the activities and manual entries in a time range
+#[tauri::command]
+pub fn get_tag_links(state: State<'_, AppState>, start: i64, end: i64) -> Result<TagLinks, String> {
+    state.db.get_tag_links(start, end).map_err(|e| e.to_string())
+}
@@ -0,0 +1,79 @@
+//! Running timer commands
+
+use crate::commands::common::AppState;
+use crate::commands::tracking::THINKING_MODE_TIMER;
+use crate::database::{ManualEntry, RunningTimer};
+use chrono::Utc;
+use tauri::State;
+
+/// Start a named timer; several timers may run at once, each with its own name
+#[tauri::command]
+pub fn start_timer(
+    state: State<'_, AppState>,
+    name: String,
+    category_id: Option<i64>,
+    description: Option<String>,
+) -> Result<RunningTimer, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Timer name cannot be empty".to_string());
+    }
+    let started_at = Utc::now().timestamp();
+    let id = state
+        .db
+        .start_timer(name, category_id, description.as_deref(), started_at)
+        .map_err(|e| e.to_string())?;
+    Ok(RunningTimer {
+        id,
+        name: name.to_string(),
+        category_id,
+        description,
+        started_at,
+    })
+}
+
+/// Stop a timer and record it as a manual entry
+#[tauri::command]
+pub fn stop_timer(state: State<'_, AppState>, id: i64) -> Result<ManualEntry, String> {
+    stop_running_timer(&state, id)
+}
+
+/// Stop a timer and record it as a manual entry. Stopping the thinking mode timer ends
+/// thinking mode, so tracking resumes as well; the tray and `stop_thinking_mode` stop
+/// timers through here too.
+pub(crate) fn stop_running_timer(state: &AppState, id: i64) -> Result<ManualEntry, String> {
+    let is_thinking_mode = state
+        .db
+        .get_running_timers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .any(|timer| timer.id == id && timer.name == THINKING_MODE_TIMER);
+    let entry = state
+        .db
+        .stop_timer(id, Utc::now().timestamp())
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Timer not found".to_string())?;
+
+    if is_thinking_mode {
+        if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
+            tracker.resume();
+        }
+    }
+    Ok(entry)
+}
+
+/// List running timers, including those started before a restart
+#[tauri::command]
+pub fn list_timers(state: State<'_, AppState>) -> Result<Vec<RunningTimer>, String> {
+    state.db.get_running_timers().map_err(|e| e.to_string())
+}
+
+/// Drop a running timer without recording it
+#[tauri::command]
+pub fn discard_timer(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    if state.db.discard_timer(id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err("Timer not found".to_string())
+    }
+}
@@ -22,11 +22,28 @@ pub fn get_today_total(state: State<'_, AppState>) -> Result<i64, String> {
     Ok(total)
 }
 
-/// Pause tracking
+/// Pause tracking, for `duration_secs` or until `resume_at` if given, otherwise until resumed.
+/// With a `category_id` the paused interval is recorded as a manual entry when it ends.
 #[tauri::command]
-pub fn pause_tracking(state: State<'_, AppState>) -> Result<(), String> {
+pub fn pause_tracking(
+    state: State<'_, AppState>,
+    duration_secs: Option<i64>,
+    resume_at: Option<i64>,
+    reason: Option<String>,
+    category_id: Option<i64>,
+) -> Result<(), String> {
+    let now = Utc::now().timestamp();
+    let resume_at = match (duration_secs, resume_at) {
+        (Some(_), Some(_)) => return Err("Pass either a duration or a resume time, not both".to_string()),
+        (Some(duration), None) if duration <= 0 => return Err("Pause duration must be positive".to_string()),
+        (Some(duration), None) => Some(now + duration),
+        (None, Some(resume_at)) if resume_at <= now => return Err("Resume time must be in the future".to_string()),
+        (None, resume_at) => resume_at,
+    };
+    let reason = reason.filter(|r| !r.trim().is_empty());
+
     if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
-        tracker.pause();
+        tracker.pause(resume_at, reason, category_id);
     }
     Ok(())
 }
@@ -43,13 +60,13 @@ pub fn resume_tracking(state: State<'_, AppState>) -> Result<(), String> {
 /// Get tracking status
 #[tauri::command]
 pub fn get_tracking_status(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    let (is_running, is_paused, current_app) = if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
+    let (is_running, pause_reasons, manual_pause, in_schedule, current_app) = if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
         let is_running = tracker.is_running();
-        let is_paused = tracker.is_paused();
-        let current_app = if !is_paused { tracker.get_current_app() } else { None };
-        (is_running, is_paused, current_app)
+        let pause_reasons = tracker.pause_reasons();
+        let current_app = if pause_reasons.is_empty() { tracker.get_current_app() } else { None };
+        (is_running, pause_reasons, tracker.manual_pause(), tracker.in_schedule(), current_app)
     } else {
-        (false, false, None)
+        (false, Vec::new(), None, None, None)
     };
 
     let active_session_duration: Option<i64> = state
@@ -57,71 +74,72 @@ pub fn get_tracking_status(state: State<'_, AppState>) -> Result<serde_json::Val
         .get_last_activity_today()
         .ok()
         .flatten()
-        .and_then(|(_, started_at, duration_sec, _)| {
+        .and_then(|(_, started_at, ended_at, _)| {
+            // The open session is extended every tracker tick; anything older has been closed
             let now = Utc::now().timestamp();
-            if now - started_at < 300 {
-                Some(duration_sec + (now - started_at))
+            if now - ended_at < 30 {
+                Some(now - started_at)
             } else {
                 None
             }
         });
 
+    let running_timers = state.db.get_running_timers().map_err(|e| e.to_string())?;
+
     Ok(serde_json::json!({
         "isTracking": is_running,
-        "isPaused": is_paused,
+        "isPaused": !pause_reasons.is_empty(),
+        "pauseReasons": pause_reasons,
+        "manualPause": manual_pause,
+        "inSchedule": in_schedule,
         "currentApp": current_app,
         "activeSessionDuration": active_session_duration,
+        "runningTimers": running_timers,
     }))
 }
 
-/// Start thinking mode
+/// Name of the timer that records thinking mode
+pub(crate) const THINKING_MODE_TIMER: &str = "Thinking mode";
+
+/// Start thinking mode: pause tracking and start the thinking mode timer
 #[tauri::command]
 pub fn start_thinking_mode(
     state: State<'_, AppState>,
 ) -> Result<i64, String> {
-    if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
-        tracker.pause();
-    }
-    
     let thinking_category_id = state
         .db
         .find_category_by_name("Thinking")
         .map_err(|e| e.to_string())?
         .ok_or_else(|| "Thinking category not found".to_string())?;
-    
-    crate::commands::manual_entries::start_manual_entry(state, thinking_category_id, Some("Thinking mode".to_string()))
+
+    let id = state
+        .db
+        .start_timer(
+            THINKING_MODE_TIMER,
+            Some(thinking_category_id),
+            Some(THINKING_MODE_TIMER),
+            Utc::now().timestamp(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
+        tracker.pause(None, Some(THINKING_MODE_TIMER.to_string()), None);
+    }
+
+    Ok(id)
 }
 
-/// Stop thinking mode
+/// Stop thinking mode: record the thinking mode timer and resume tracking
 #[tauri::command]
 pub fn stop_thinking_mode(state: State<'_, AppState>) -> Result<(), String> {
-    let entry_id = state.thinking_mode_entry_id.lock().unwrap().take()
-        .ok_or_else(|| "No active manual entry".to_string())?;
-    
-    let now = Utc::now().timestamp();
-    
-    let entry = state
+    let timer = state
         .db
-        .get_manual_entries(now - 86400, now + 86400)
+        .get_running_timers()
         .map_err(|e| e.to_string())?
         .into_iter()
-        .find(|e| e.id == entry_id)
-        .ok_or_else(|| "Entry not found".to_string())?;
-    
-    state
-        .db
-        .update_manual_entry(
-            entry_id,
-            entry.description.as_deref(),
-            entry.category_id,
-            entry.started_at,
-            now,
-        )
-        .map_err(|e| e.to_string())?;
-    
-    if let Some(tracker) = state.tracker.lock().unwrap().as_ref() {
-        tracker.resume();
-    }
-    
+        .find(|timer| timer.name == THINKING_MODE_TIMER)
+        .ok_or_else(|| "Thinking mode is not running".to_string())?;
+
+    super::timers::stop_running_timer(&state, timer.id)?;
     Ok(())
 }
@@ -3,78 +3,320 @@
 use rusqlite::{Connection, Result, params};
 use rusqlite::types::Value as SqliteValue;
 use super::common::Database;
-use super::models::Activity;
+use super::models::{Activity, ActivityFilter, ReapplyOptions, ReapplyProgress, WindowContext};
 use super::common::SYSTEM_CATEGORY_UNCATEGORIZED;
+use super::history::RecordedChange;
 use chrono::Local;
 
+/// Column list matching `activity_from_row`
+pub(crate) const ACTIVITY_COLUMNS: &str =
+    "id, app_name, window_title, domain, category_id, started_at, ended_at, duration_sec, is_idle, url, project, document, exe_path, cmdline, cwd, git_repo, git_branch, suppression_reason, project_id, category_source, category_rule_id";
+
+/// `category_source` values
+pub const SOURCE_RULE: &str = "rule";
+pub const SOURCE_MANUAL: &str = "manual";
+pub const SOURCE_PLUGIN: &str = "plugin";
+pub const SOURCE_DEFAULT: &str = "default";
+
+/// Activities looked at between two progress reports of a rule reapplication
+const REAPPLY_PROGRESS_INTERVAL: i64 = 5_000;
+
+/// Map a row selected with `ACTIVITY_COLUMNS` to an Activity
+pub(crate) fn activity_from_row(row: &rusqlite::Row) -> Result<Activity> {
+    Ok(Activity {
+        id: row.get(0)?,
+        app_name: row.get(1)?,
+        window_title: row.get(2)?,
+        domain: row.get(3)?,
+        category_id: row.get(4)?,
+        started_at: row.get(5)?,
+        ended_at: row.get(6)?,
+        duration_sec: row.get(7)?,
+        is_idle: row.get(8)?,
+        url: row.get(9)?,
+        project: row.get(10)?,
+        document: row.get(11)?,
+        exe_path: row.get(12)?,
+        cmdline: row.get(13)?,
+        cwd: row.get(14)?,
+        git_repo: row.get(15)?,
+        git_branch: row.get(16)?,
+        suppression_reason: row.get(17)?,
+        project_id: row.get(18)?,
+        category_source: row.get(19)?,
+        category_rule_id: row.get(20)?,
+    })
+}
+
+/// A categorization rule, as matched against activities
+pub(crate) struct CategoryRule {
+    pub id: i64,
+    pub rule_type: String,
+    pub pattern: String,
+    pub category_id: i64,
+}
+
+/// Rules whose category exists, highest priority first
+pub(crate) fn load_rules(conn: &Connection) -> Result<Vec<CategoryRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.rule_type, r.pattern, r.category_id FROM rules r
+         JOIN categories c ON c.id = r.category_id
+         ORDER BY r.priority DESC",
+    )?;
+    let rules = stmt
+        .query_map([], |row| {
+            Ok(CategoryRule {
+                id: row.get(0)?,
+                rule_type: row.get(1)?,
+                pattern: row.get(2)?,
+                category_id: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rules)
+}
+
+/// First rule matching an activity
+pub(crate) fn matching_rule<'a>(
+    rules: &'a [CategoryRule],
+    app_name: &str,
+    window_title: Option<&str>,
+    context: &WindowContext,
+) -> Option<&'a CategoryRule> {
+    rules.iter().find(|rule| {
+        let pattern = rule.pattern.as_str();
+        match rule.rule_type.as_str() {
+            "app_name" => matches_pattern(app_name, pattern),
+            "window_title" => window_title.is_some_and(|title| matches_pattern(title, pattern)),
+            "domain" => context.domain.as_deref().is_some_and(|d| matches_pattern(d, pattern)),
+            "project" => context.project.as_deref().is_some_and(|p| matches_pattern(p, pattern)),
+            "exe_path" => context.exe_path.as_deref().is_some_and(|p| matches_pattern(p, pattern)),
+            _ => false,
+        }
+    })
+}
+
+/// Category of activities no rule matches: Uncategorized, if it exists
+pub(crate) fn default_category(conn: &Connection) -> Option<i64> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM categories WHERE id = ?)",
+            params![SYSTEM_CATEGORY_UNCATEGORIZED],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+    exists.then_some(SYSTEM_CATEGORY_UNCATEGORIZED)
+}
+
+/// Category, source and rule the rules give an activity; None if no rule matches
+pub(crate) fn rule_category(rules: &[CategoryRule], activity: &Activity) -> Option<(Option<i64>, &'static str, Option<i64>)> {
+    matching_rule(rules, &activity.app_name, activity.window_title.as_deref(), &WindowContext::from(activity))
+        .map(|rule| (Some(rule.category_id), SOURCE_RULE, Some(rule.id)))
+}
+
+/// Source recorded for a category found by `find_category_for_activity`
+fn rule_source(rule_id: Option<i64>) -> &'static str {
+    if rule_id.is_some() { SOURCE_RULE } else { SOURCE_DEFAULT }
+}
+
+impl ActivityFilter {
+    /// SQL conditions on the `activities` table for this filter, with their parameters
+    pub(crate) fn conditions(&self) -> (Vec<String>, Vec<SqliteValue>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        let placeholders = |ids: &[i64]| vec!["?"; ids.len()].join(",");
+
+        if self.exclude_idle {
+            conditions.push("is_idle = 0".to_string());
+        }
+        if self.only_idle {
+            conditions.push("is_idle = 1".to_string());
+        }
+        for (column, pattern) in [
+            ("app_name", &self.app_pattern),
+            ("window_title", &self.title_pattern),
+            ("domain", &self.domain_pattern),
+        ] {
+            if let Some(pattern) = pattern.as_deref().filter(|p| !p.is_empty()) {
+                conditions.push(format!("{} LIKE ? ESCAPE '\\'", column));
+                values.push(SqliteValue::Text(like_pattern(pattern)));
+            }
+        }
+        if !self.category_ids.is_empty() {
+            conditions.push(format!("category_id IN ({})", placeholders(&self.category_ids)));
+            values.extend(self.category_ids.iter().map(|id| SqliteValue::Integer(*id)));
+        }
+        if !self.include_tags.is_empty() {
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM activity_tags t WHERE t.activity_id = activities.id AND t.tag_id IN ({}))",
+                placeholders(&self.include_tags)
+            ));
+            values.extend(self.include_tags.iter().map(|id| SqliteValue::Integer(*id)));
+        }
+        if !self.exclude_tags.is_empty() {
+            conditions.push(format!(
+                "NOT EXISTS (SELECT 1 FROM activity_tags t WHERE t.activity_id = activities.id AND t.tag_id IN ({}))",
+                placeholders(&self.exclude_tags)
+            ));
+            values.extend(self.exclude_tags.iter().map(|id| SqliteValue::Integer(*id)));
+        }
+        (conditions, values)
+    }
+}
+
+/// Case-insensitive rule match: `*x*` contains, `*x` ends with, `x*` starts with, `x` contains
+pub(crate) fn matches_pattern(value: &str, pattern: &str) -> bool {
+    let value = value.to_lowercase();
+    let pattern = pattern.to_lowercase();
+
+    if pattern.starts_with('*') && pattern.ends_with('*') {
+        value.contains(pattern.trim_start_matches('*').trim_end_matches('*'))
+    } else if let Some(suffix) = pattern.strip_prefix('*') {
+        value.ends_with(suffix)
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
+        value.starts_with(prefix)
+    } else {
+        value.contains(&pattern)
+    }
+}
+
+/// SQL LIKE pattern matching the same values as `matches_pattern` (case-insensitive for ASCII)
+pub(crate) fn like_pattern(pattern: &str) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    if pattern.starts_with('*') && pattern.ends_with('*') {
+        format!("%{}%", escape(pattern.trim_start_matches('*').trim_end_matches('*')))
+    } else if let Some(suffix) = pattern.strip_prefix('*') {
+        format!("%{}", escape(suffix))
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
+        format!("{}%", escape(prefix))
+    } else {
+        format!("%{}%", escape(pattern))
+    }
+}
+
 impl Database {
-    /// Insert or update an activity record.
-    /// Returns the activity id (existing or newly inserted).
-    pub fn upsert_activity(
+    /// Open a new focus session for a window.
+    /// The session starts with zero length and grows through `extend_activity`.
+    /// Returns the new activity id.
+    pub fn start_activity(
         &self,
         app_name: &str,
         window_title: Option<&str>,
-        domain: Option<&str>,
-        timestamp: i64,
+        context: &WindowContext,
+        started_at: i64,
     ) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
 
         // Try to find matching category
-        let category_id = self.find_category_for_activity(&conn, app_name, window_title, domain);
-
-        // Check if there's a recent activity for the same app and window title (within 5 minutes)
-        let existing: Option<(i64, i64, i64)> = if let Some(title) = window_title {
-            conn.query_row(
-                "SELECT id, duration_sec, started_at FROM activities 
-                 WHERE app_name = ? AND window_title = ? AND started_at > ? - 300 
-                 ORDER BY started_at DESC LIMIT 1",
-                params![app_name, title, timestamp],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-            )
-            .ok()
-        } else {
-            conn.query_row(
-                "SELECT id, duration_sec, started_at FROM activities 
-                 WHERE app_name = ? AND window_title IS NULL AND started_at > ? - 300 
-                 ORDER BY started_at DESC LIMIT 1",
-                params![app_name, timestamp],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-            )
-            .ok()
-        };
+        let (category_id, rule_id) = self.find_category_for_activity(&conn, app_name, window_title, context);
 
-        let id = if let Some((id, duration, started_at)) = existing {
-            let time_diff = timestamp - started_at;
-            let new_duration = std::cmp::max(duration + 5, time_diff);
+        conn.execute(
+            "INSERT INTO activities (app_name, window_title, domain, url, project, document, exe_path, cmdline, cwd, git_repo, git_branch, category_id, category_source, category_rule_id, started_at, ended_at, duration_sec, is_idle)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, FALSE)",
+            params![
+                app_name,
+                window_title,
+                context.domain,
+                context.url,
+                context.project,
+                context.document,
+                context.exe_path,
+                context.cmdline,
+                context.cwd,
+                context.git_repo,
+                context.git_branch,
+                category_id,
+                rule_source(rule_id),
+                rule_id,
+                started_at,
+                started_at,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
 
+    /// Attach the exact URL reported by the browser to a session that started without it.
+    /// The domain from the URL replaces the one guessed from the window title, and the
+    /// category is recomputed since domain rules may now match, unless it was set by hand.
+    pub fn set_activity_url(&self, id: i64, url: &str, domain: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let activity = conn.query_row(
+            &format!("SELECT {} FROM activities WHERE id = ?", ACTIVITY_COLUMNS),
+            params![id],
+            activity_from_row,
+        )?;
+        let context = WindowContext {
+            domain: domain.map(str::to_string),
+            ..WindowContext::from(&activity)
+        };
+        if activity.category_source.as_deref() == Some(SOURCE_MANUAL) {
             conn.execute(
-                "UPDATE activities SET duration_sec = ?, category_id = ? WHERE id = ?",
-                params![new_duration, category_id, id],
-            )?;
-            id
-        } else {
-            conn.execute(
-                "INSERT INTO activities (app_name, window_title, domain, category_id, started_at, duration_sec, is_idle)
-                 VALUES (?, ?, ?, ?, ?, 5, FALSE)",
-                params![app_name, window_title, domain, category_id, timestamp],
+                "UPDATE activities SET url = ?, domain = ? WHERE id = ?",
+                params![url, domain, id],
             )?;
-            conn.last_insert_rowid()
-        };
+            return Ok(());
+        }
+        let (category_id, rule_id) =
+            self.find_category_for_activity(&conn, &activity.app_name, activity.window_title.as_deref(), &context);
+        conn.execute(
+            "UPDATE activities SET url = ?, domain = ?, category_id = ?, category_source = ?, category_rule_id = ? WHERE id = ?",
+            params![url, domain, category_id, rule_source(rule_id), rule_id, id],
+        )?;
+        Ok(())
+    }
 
-        Ok(id)
+    /// Move the end of an open session forward to `ended_at`.
+    /// Never shortens a session; duration_sec is kept equal to ended_at - started_at.
+    pub fn extend_activity(&self, id: i64, ended_at: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE activities
+             SET ended_at = MAX(ended_at, ?1), duration_sec = MAX(ended_at, ?1) - started_at
+             WHERE id = ?2",
+            params![ended_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Note why idle detection was suppressed during an activity; the first reason is kept
+    pub fn set_activity_suppression(&self, id: i64, reason: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE activities SET suppression_reason = ? WHERE id = ? AND suppression_reason IS NULL",
+            params![reason, id],
+        )?;
+        Ok(())
     }
 
     /// Update an activity row by id (used after plugin hooks modify the activity).
+    /// A changed category is recorded as set by a plugin.
     pub fn update_activity_row(&self, activity: &Activity) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE activities SET app_name = ?, window_title = ?, domain = ?, category_id = ?, started_at = ?, duration_sec = ?, is_idle = ? WHERE id = ?",
+            &format!(
+                "UPDATE activities SET app_name = ?, window_title = ?, domain = ?, url = ?, project = ?, document = ?, exe_path = ?, cmdline = ?, cwd = ?, git_repo = ?, git_branch = ?, suppression_reason = ?,
+                 category_source = CASE WHEN category_id IS ?13 THEN category_source ELSE '{}' END,
+                 category_rule_id = CASE WHEN category_id IS ?13 THEN category_rule_id END,
+                 category_id = ?13, project_id = ?, started_at = ?, ended_at = ?, duration_sec = ?, is_idle = ? WHERE id = ?",
+                SOURCE_PLUGIN
+            ),
             params![
                 activity.app_name,
                 activity.window_title,
                 activity.domain,
+                activity.url,
+                activity.project,
+                activity.document,
+                activity.exe_path,
+                activity.cmdline,
+                activity.cwd,
+                activity.git_repo,
+                activity.git_branch,
+                activity.suppression_reason,
                 activity.category_id,
+                activity.project_id,
                 activity.started_at,
+                activity.ended_at,
                 activity.duration_sec,
                 activity.is_idle,
                 activity.id,
@@ -83,172 +325,127 @@ impl Database {
         Ok(())
     }
 
-    /// Find category for an activity based on rules
+    /// Category for an activity from the rules, with the id of the rule that matched;
+    /// Uncategorized without a rule id if none matches
     pub(crate) fn find_category_for_activity(
         &self,
         conn: &Connection,
         app_name: &str,
         window_title: Option<&str>,
-        domain: Option<&str>,
-    ) -> Option<i64> {
-        // Get rules ordered by priority
-        let mut stmt = conn
-            .prepare("SELECT rule_type, pattern, category_id FROM rules ORDER BY priority DESC")
-            .ok()?;
-
-        let rules = stmt
-            .query_map([], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, i64>(2)?,
-                ))
-            })
-            .ok()?;
-
-        for rule in rules.flatten() {
-            let (rule_type, pattern, category_id) = rule;
-            let matches = match rule_type.as_str() {
-                "app_name" => {
-                    let app_lower = app_name.to_lowercase();
-                    let pattern_lower = pattern.to_lowercase();
-                    
-                    if pattern_lower.starts_with('*') && pattern_lower.ends_with('*') {
-                        let pattern_clean = pattern_lower.trim_start_matches('*').trim_end_matches('*');
-                        app_lower.contains(&pattern_clean)
-                    } else if pattern_lower.starts_with('*') {
-                        let pattern_clean = pattern_lower.trim_start_matches('*');
-                        app_lower.ends_with(&pattern_clean)
-                    } else if pattern_lower.ends_with('*') {
-                        let pattern_clean = pattern_lower.trim_end_matches('*');
-                        app_lower.starts_with(&pattern_clean)
-                    } else {
-                        app_lower.contains(&pattern_lower)
-                    }
-                }
-                "window_title" => {
-                    if let Some(title) = window_title {
-                        let title_lower = title.to_lowercase();
-                        let pattern_lower = pattern.to_lowercase();
-                        
-                        if pattern_lower.starts_with('*') && pattern_lower.ends_with('*') {
-                            let pattern_clean = pattern_lower.trim_start_matches('*').trim_end_matches('*');
-                            title_lower.contains(&pattern_clean)
-                        } else if pattern_lower.starts_with('*') {
-                            let pattern_clean = pattern_lower.trim_start_matches('*');
-                            title_lower.ends_with(&pattern_clean)
-                        } else if pattern_lower.ends_with('*') {
-                            let pattern_clean = pattern_lower.trim_end_matches('*');
-                            title_lower.starts_with(&pattern_clean)
-                        } else {
-                            title_lower.contains(&pattern_lower)
-                        }
-                    } else {
-                        false
-                    }
-                }
-                "domain" => {
-                    if let Some(d) = domain {
-                        let domain_lower = d.to_lowercase();
-                        let pattern_lower = pattern.to_lowercase();
-                        
-                        if pattern_lower.starts_with('*') && pattern_lower.ends_with('*') {
-                            let pattern_clean = pattern_lower.trim_start_matches('*').trim_end_matches('*');
-                            domain_lower.contains(&pattern_clean)
-                        } else if pattern_lower.starts_with('*') {
-                            let pattern_clean = pattern_lower.trim_start_matches('*');
-                            domain_lower.ends_with(&pattern_clean)
-                        } else if pattern_lower.ends_with('*') {
-                            let pattern_clean = pattern_lower.trim_end_matches('*');
-                            domain_lower.starts_with(&pattern_clean)
-                        } else {
-                            domain_lower.contains(&pattern_lower)
-                        }
-                    } else {
-                        false
-                    }
-                }
-                _ => false,
-            };
-
-            if matches {
-                let category_exists: bool = conn
-                    .query_row(
-                        "SELECT EXISTS(SELECT 1 FROM categories WHERE id = ?)",
-                        params![category_id],
-                        |row| row.get(0),
-                    )
-                    .unwrap_or(false);
-                
-                if category_exists {
-                    return Some(category_id);
-                }
-            }
-        }
-
-        let uncategorized_exists: bool = conn
-            .query_row(
-                "SELECT EXISTS(SELECT 1 FROM categories WHERE id = ?)",
-                params![SYSTEM_CATEGORY_UNCATEGORIZED],
-                |row| row.get(0),
-            )
-            .unwrap_or(false);
-        
-        if uncategorized_exists {
-            Some(SYSTEM_CATEGORY_UNCATEGORIZED)
-        } else {
-            None
+        context: &WindowContext,
+    ) -> (Option<i64>, Option<i64>) {
+        let rules = load_rules(conn).unwrap_or_default();
+        match matching_rule(&rules, app_name, window_title, context) {
+            Some(rule) => (Some(rule.category_id), Some(rule.id)),
+            None => (default_category(conn), None),
         }
     }
 
-    /// Record idle start time
-    pub fn record_idle_start(&self, timestamp: i64) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+    /// Record the start of an idle period, backdated to when input stopped.
+    /// The start never moves before the start of the open focus session `open_activity`
+    /// or the end of the previous idle record, so only the open session is touched: in
+    /// the same transaction it is clipped at the idle start, or removed if nothing of it
+    /// is left. Returns the start actually recorded.
+    pub fn record_idle_start(&self, timestamp: i64, open_activity: Option<i64>) -> Result<i64> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
         
-        let category_exists: bool = conn.query_row(
+        let category_exists: bool = tx.query_row(
             "SELECT EXISTS(SELECT 1 FROM categories WHERE id = ?)",
             params![SYSTEM_CATEGORY_UNCATEGORIZED],
             |row| row.get(0),
         ).unwrap_or(false);
         
         if !category_exists {
-            conn.execute(
+            tx.execute(
                 "INSERT INTO categories (id, name, color, icon, is_productive, sort_order, is_system, is_pinned) VALUES (?, ?, ?, ?, ?, ?, TRUE, ?)",
                 params![SYSTEM_CATEGORY_UNCATEGORIZED, "Uncategorized", "#9E9E9E", "❓", None::<bool>, 8, false],
             )?;
         }
+
+        let previous_idle_end: Option<i64> = tx.query_row(
+            "SELECT MAX(ended_at) FROM activities WHERE is_idle = 1",
+            [],
+            |row| row.get(0),
+        )?;
+        let session_start: Option<i64> = tx
+            .query_row(
+                "SELECT started_at FROM activities WHERE id = ? AND is_idle = 0",
+                params![open_activity],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let start = [previous_idle_end, session_start]
+            .into_iter()
+            .flatten()
+            .fold(timestamp, i64::max);
+
+        if let Some(activity_id) = open_activity.filter(|_| session_start.is_some()) {
+            if session_start == Some(start) {
+                tx.execute("UPDATE events SET activity_id = NULL WHERE activity_id = ?", params![activity_id])?;
+                tx.execute("DELETE FROM activities WHERE id = ?", params![activity_id])?;
+            } else {
+                tx.execute(
+                    "UPDATE activities SET ended_at = ?1, duration_sec = ?1 - started_at
+                     WHERE id = ?2 AND ended_at > ?1",
+                    params![start, activity_id],
+                )?;
+            }
+        }
         
-        conn.execute(
-            "INSERT INTO activities (app_name, window_title, domain, category_id, started_at, duration_sec, is_idle)
-             VALUES ('Idle', NULL, NULL, ?, ?, 0, TRUE)",
-            params![SYSTEM_CATEGORY_UNCATEGORIZED, timestamp],
+        tx.execute(
+            "INSERT INTO activities (app_name, window_title, domain, category_id, category_source, started_at, ended_at, duration_sec, is_idle)
+             VALUES ('Idle', NULL, NULL, ?, ?, ?, ?, 0, TRUE)",
+            params![SYSTEM_CATEGORY_UNCATEGORIZED, SOURCE_DEFAULT, start, start],
         )?;
-        Ok(())
+        tx.commit()?;
+        Ok(start)
     }
 
     /// Update idle duration
     pub fn update_idle_duration(&self, started_at: i64, duration_sec: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE activities SET duration_sec = ? WHERE app_name = 'Idle' AND started_at = ?",
+            "UPDATE activities SET duration_sec = ?1, ended_at = started_at + ?1 WHERE app_name = 'Idle' AND started_at = ?2",
             params![duration_sec, started_at],
         )?;
         Ok(())
     }
 
-    /// Update idle activity with category and description
+    /// Update idle activity with category; the description becomes the idle activity's note
     pub fn update_idle_activity(
         &self,
         started_at: i64,
         category_id: i64,
         description: Option<&str>,
     ) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE activities SET category_id = ?, window_title = ? WHERE app_name = 'Idle' AND started_at = ?",
-            params![category_id, description, started_at],
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, "Categorize idle time")?;
+        tx.execute(
+            "UPDATE activities SET category_id = ?, category_source = ?, category_rule_id = NULL
+             WHERE app_name = 'Idle' AND started_at = ?",
+            params![category_id, SOURCE_MANUAL, started_at],
         )?;
-        Ok(())
+        if let Some(description) = description.map(str::trim).filter(|d| !d.is_empty()) {
+            let now = chrono::Utc::now().timestamp();
+            let updated = tx.execute(
+                "UPDATE notes SET body = ?1, updated_at = ?2 WHERE id = (
+                     SELECT MIN(n.id) FROM notes n JOIN activities a ON a.id = n.activity_id
+                     WHERE a.app_name = 'Idle' AND a.started_at = ?3
+                 )",
+                params![description, now, started_at],
+            )?;
+            if updated == 0 {
+                tx.execute(
+                    "INSERT INTO notes (activity_id, body, created_at, updated_at)
+                     SELECT id, ?1, ?2, ?2 FROM activities WHERE app_name = 'Idle' AND started_at = ?3",
+                    params![description, now, started_at],
+                )?;
+            }
+        }
+        change.finish()?;
+        tx.commit()
     }
 
     /// Get activities for a time range with optional pagination and filters
@@ -261,20 +458,24 @@ impl Database {
         exclude_idle: Option<bool>,
         category_ids: Option<&[i64]>,
     ) -> Result<Vec<Activity>> {
-        let conn = self.conn.lock().unwrap();
-        
-        let map_row = |row: &rusqlite::Row| -> Result<Activity> {
-            Ok(Activity {
-                id: row.get(0)?,
-                app_name: row.get(1)?,
-                window_title: row.get(2)?,
-                domain: row.get(3)?,
-                category_id: row.get(4)?,
-                started_at: row.get(5)?,
-                duration_sec: row.get(6)?,
-                is_idle: row.get(7)?,
-            })
+        let filter = ActivityFilter {
+            exclude_idle: exclude_idle == Some(true),
+            category_ids: category_ids.map(<[i64]>::to_vec).unwrap_or_default(),
+            ..Default::default()
         };
+        self.get_filtered_activities(start, end, limit, offset, &filter)
+    }
+
+    /// Get activities for a time range with optional pagination, matching `filter`
+    pub fn get_filtered_activities(
+        &self,
+        start: i64,
+        end: i64,
+        limit: Option<i64>,
+        offset: Option<i64>,
+        filter: &ActivityFilter,
+    ) -> Result<Vec<Activity>> {
+        let conn = self.conn.lock().unwrap();
         
         // Build WHERE clause components
         let mut where_parts: Vec<String> = vec!["started_at >= ?".to_string(), "started_at <= ?".to_string()];
@@ -283,28 +484,17 @@ impl Database {
             SqliteValue::Integer(end),
         ];
         
-        if let Some(true) = exclude_idle {
-            where_parts.push("is_idle = 0".to_string());
-        }
-        
-        if let Some(ids) = category_ids {
-            if !ids.is_empty() {
-                let placeholders: Vec<String> = (0..ids.len()).map(|_| "?".to_string()).collect();
-                let category_filter = format!("category_id IN ({})", placeholders.join(","));
-                where_parts.push(category_filter);
-                for id in ids {
-                    params_vec.push(SqliteValue::Integer(*id));
-                }
-            }
-        }
+        let (conditions, values) = filter.conditions();
+        where_parts.extend(conditions);
+        params_vec.extend(values);
         
         let where_clause = where_parts.join(" AND ");
         let mut query = format!(
-            "SELECT id, app_name, window_title, domain, category_id, started_at, duration_sec, is_idle
+            "SELECT {}
              FROM activities
              WHERE {}
              ORDER BY started_at ASC",
-            where_clause
+            ACTIVITY_COLUMNS, where_clause
         );
         
         // Add pagination
@@ -322,7 +512,7 @@ impl Database {
         }
         
         let mut stmt = conn.prepare(&query)?;
-        let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), map_row)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), activity_from_row)?;
         let activities = rows.collect::<Result<Vec<_>>>()?;
 
         Ok(activities)
@@ -332,85 +522,112 @@ impl Database {
     pub fn get_activity_by_id(&self, id: i64) -> Result<Option<Activity>> {
         let conn = self.conn.lock().unwrap();
         conn.query_row(
-            "SELECT id, app_name, window_title, domain, category_id, started_at, duration_sec, is_idle
-             FROM activities WHERE id = ?",
+            &format!("SELECT {} FROM activities WHERE id = ?", ACTIVITY_COLUMNS),
             params![id],
-            |row| {
-                Ok(Activity {
-                    id: row.get(0)?,
-                    app_name: row.get(1)?,
-                    window_title: row.get(2)?,
-                    domain: row.get(3)?,
-                    category_id: row.get(4)?,
-                    started_at: row.get(5)?,
-                    duration_sec: row.get(6)?,
-                    is_idle: row.get(7)?,
-                })
-            },
+            activity_from_row,
         )
         .optional()
     }
 
-    /// Update activity category
+    /// Set the category of an activity by hand; rule reapplication keeps it unless forced
     pub fn update_activity_category(&self, id: i64, category_id: Option<i64>) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Change category of activity {}", id))?;
         conn.execute(
-            "UPDATE activities SET category_id = ? WHERE id = ?",
-            params![category_id, id],
+            "UPDATE activities SET category_id = ?, category_source = ?, category_rule_id = NULL WHERE id = ?",
+            params![category_id, SOURCE_MANUAL, id],
         )?;
-        Ok(())
+        change.finish()
     }
 
     /// Delete activity
     pub fn delete_activity(&self, id: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Delete activity {}", id))?;
         conn.execute("DELETE FROM activities WHERE id = ?", params![id])?;
-        Ok(())
+        conn.execute("UPDATE events SET activity_id = NULL WHERE activity_id = ?", params![id])?;
+        change.finish()
     }
 
-    /// Reapply categorization rules to all activities
-    pub fn reapply_categorization_rules(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        
-        let mut stmt = conn.prepare(
-            "SELECT id, app_name, window_title, domain FROM activities"
+    /// Recategorize activities with the current rules, in one transaction. Categories set
+    /// by hand are kept unless `options.force` is set. Without a rule subset, activities no
+    /// rule matches go back to the default category. `progress` is called every few
+    /// thousand activities and once at the end.
+    pub fn reapply_categorization_rules(
+        &self,
+        options: &ReapplyOptions,
+        mut progress: impl FnMut(&ReapplyProgress),
+    ) -> Result<ReapplyProgress> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        // Only the category columns are recorded, not whole activities
+        let change = RecordedChange::begin_compact(&tx, "Reapply categorization rules")?;
+
+        let rules = load_rules(&tx)?;
+        let default = default_category(&tx);
+        let range = "started_at >= COALESCE(?1, started_at) AND started_at <= COALESCE(?2, started_at)";
+        let mut state = ReapplyProgress {
+            total: tx.query_row(
+                &format!("SELECT COUNT(*) FROM activities WHERE {}", range),
+                params![options.start, options.end],
+                |row| row.get(0),
+            )?,
+            ..Default::default()
+        };
+
+        let mut update = tx.prepare(
+            "UPDATE activities SET category_id = ?, category_source = ?, category_rule_id = ? WHERE id = ?",
         )?;
-        
-        let activities = stmt.query_map([], |row| {
-            Ok((
-                row.get::<_, i64>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, Option<String>>(2)?,
-                row.get::<_, Option<String>>(3)?,
-            ))
-        })?;
-        
-        for activity in activities {
-            let (id, app_name, window_title, domain) = activity?;
-            let category_id = self.find_category_for_activity(
-                &conn,
-                &app_name,
-                window_title.as_deref(),
-                domain.as_deref(),
-            );
-            
-            match category_id {
-                Some(cat_id) => {
-                    conn.execute(
-                        "UPDATE activities SET category_id = ? WHERE id = ?",
-                        params![cat_id, id],
-                    )?;
-                }
-                None => {
-                    conn.execute(
-                        "UPDATE activities SET category_id = NULL WHERE id = ?",
-                        params![id],
-                    )?;
+        let mut stmt = tx.prepare(&format!("SELECT {} FROM activities WHERE {} ORDER BY id", ACTIVITY_COLUMNS, range))?;
+        let mut rows = stmt.query(params![options.start, options.end])?;
+        while let Some(row) = rows.next()? {
+            let activity = activity_from_row(row)?;
+            state.processed += 1;
+            if state.processed % REAPPLY_PROGRESS_INTERVAL == 0 {
+                progress(&state);
+            }
+            if activity.category_source.as_deref() == Some(SOURCE_MANUAL) && !options.force {
+                state.skipped_manual += 1;
+                continue;
+            }
+
+            // All rules compete; with a subset, only activities one of its rules wins change
+            let found = rule_category(&rules, &activity);
+            if let Some(ids) = &options.rule_ids {
+                if !found.is_some_and(|(_, _, rule_id)| rule_id.is_some_and(|id| ids.contains(&id))) {
+                    continue;
                 }
             }
+            let (category_id, source, rule_id) = found.unwrap_or((default, SOURCE_DEFAULT, None));
+            if (activity.category_id, activity.category_source.as_deref(), activity.category_rule_id)
+                != (category_id, Some(source), rule_id)
+            {
+                update.execute(params![category_id, source, rule_id, activity.id])?;
+                change.record_update(
+                    "activities",
+                    activity.id,
+                    &serde_json::json!({
+                        "category_id": activity.category_id,
+                        "category_source": activity.category_source,
+                        "category_rule_id": activity.category_rule_id,
+                    }),
+                    &serde_json::json!({
+                        "category_id": category_id,
+                        "category_source": source,
+                        "category_rule_id": rule_id,
+                    }),
+                )?;
+                state.updated += 1;
+            }
         }
-        
-        Ok(())
+        drop(rows);
+        drop(stmt);
+        drop(update);
+
+        change.finish()?;
+        tx.commit()?;
+        progress(&state);
+        Ok(state)
     }
 
     /// Get total time for today
@@ -426,7 +643,7 @@ impl Database {
 
         let activities_total: i64 = conn
             .query_row(
-                "SELECT COALESCE(SUM(duration_sec), 0) FROM activities WHERE started_at >= ? AND is_idle = FALSE",
+                "SELECT COALESCE(SUM(ended_at - MAX(started_at, ?1)), 0) FROM activities WHERE ended_at > ?1 AND is_idle = FALSE",
                 params![today_start],
                 |row| row.get(0),
             )
@@ -443,7 +660,7 @@ impl Database {
         Ok(activities_total + manual_total)
     }
 
-    /// Get last activity started today (for active session calculation)
+    /// Get last activity started today as (id, started_at, ended_at, app_name) (for active session calculation)
     pub fn get_last_activity_today(&self) -> Result<Option<(i64, i64, i64, String)>> {
         let conn = self.conn.lock().unwrap();
         let today_start = Local::now()
@@ -455,7 +672,7 @@ impl Database {
             .timestamp();
         
         conn.query_row(
-            "SELECT id, started_at, duration_sec, app_name FROM activities WHERE started_at >= ? AND is_idle = FALSE ORDER BY started_at DESC LIMIT 1",
+            "SELECT id, started_at, ended_at, app_name FROM activities WHERE started_at >= ? AND is_idle = FALSE ORDER BY started_at DESC LIMIT 1",
             params![today_start],
             |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
         )
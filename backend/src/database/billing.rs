@@ -0,0 +1,389 @@
+//! Billing database operations
+//!
+//! Billable categories are priced by hourly rates with effective dates. Billed
+//! time is the tracked time rounded up to the configured increment, either per
+//! activity or per day.
+
+use chrono::{Local, NaiveDate, TimeZone};
+use rusqlite::{Result, params};
+use std::collections::HashMap;
+use super::common::Database;
+use super::history::RecordedChange;
+use super::models::{BilledActivity, BillingRounding, Category, CategoryRate, Earning};
+use super::stats::IN_RANGE;
+
+/// Rounding increment in minutes; 0 or missing bills exact seconds
+pub const ROUNDING_MINUTES_KEY: &str = "billing_rounding_minutes";
+/// "entry" rounds every activity, "day" rounds each day's total
+pub const ROUNDING_SCOPE_KEY: &str = "billing_rounding_scope";
+
+/// Local day, category and rate id that per-day rounding groups rows by
+type DayKey = (Option<NaiveDate>, i64, Option<i64>);
+
+impl Database {
+    /// Rates of one category, or of all categories, oldest first
+    pub fn get_category_rates(&self, category_id: Option<i64>) -> Result<Vec<CategoryRate>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, category_id, hourly_rate, currency, effective_from
+             FROM category_rates
+             WHERE ?1 IS NULL OR category_id = ?1
+             ORDER BY category_id, effective_from",
+        )?;
+        let rates = stmt
+            .query_map(params![category_id], |row| {
+                Ok(CategoryRate {
+                    id: row.get(0)?,
+                    category_id: row.get(1)?,
+                    hourly_rate: row.get(2)?,
+                    currency: row.get(3)?,
+                    effective_from: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rates)
+    }
+
+    /// Set the rate of a category from `effective_from` on; replaces a rate starting at the same time
+    pub fn set_category_rate(
+        &self,
+        category_id: i64,
+        hourly_rate: f64,
+        currency: &str,
+        effective_from: i64,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Set rate of category {}", category_id))?;
+        let id = conn.query_row(
+            "INSERT INTO category_rates (category_id, hourly_rate, currency, effective_from)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(category_id, effective_from)
+             DO UPDATE SET hourly_rate = excluded.hourly_rate, currency = excluded.currency
+             RETURNING id",
+            params![category_id, hourly_rate, currency, effective_from],
+            |row| row.get(0),
+        )?;
+        change.finish()?;
+        Ok(id)
+    }
+
+    /// Delete a rate; the previous rate of the category applies again
+    pub fn delete_category_rate(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Delete category rate {}", id))?;
+        conn.execute("DELETE FROM category_rates WHERE id = ?", params![id])?;
+        change.finish()
+    }
+
+    /// Mark a category as billable or not, or with None make it follow its parent
+    pub fn set_category_billable(&self, id: i64, is_billable: Option<bool>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Change billable flag of category {}", id))?;
+        conn.execute(
+            "UPDATE categories SET is_billable = ? WHERE id = ?",
+            params![is_billable, id],
+        )?;
+        change.finish()
+    }
+
+    /// Rounding configured in settings
+    pub fn get_billing_rounding(&self) -> Result<BillingRounding> {
+        let increment_minutes = self
+            .get_setting(ROUNDING_MINUTES_KEY)?
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(0)
+            .max(0);
+        let per_day = self.get_setting(ROUNDING_SCOPE_KEY)?.as_deref() == Some("day");
+        Ok(BillingRounding { increment_minutes, per_day })
+    }
+
+    /// Non-idle activities and manual entries in billable categories overlapping
+    /// [start, end], clipped to the range, with their rounded billed time. A category
+    /// without a flag or rate of its own uses its parent's. Time is split where a new
+    /// rate takes effect, so each row has the rate in effect when it starts; rounding
+    /// applies to an item's whole time and the extra time goes to its last row.
+    /// With per-day rounding the extra time of a day goes to its last row, so the
+    /// rows always add up to the rounded totals.
+    pub fn get_billed_activities(&self, start: i64, end: i64) -> Result<Vec<BilledActivity>> {
+        let rounding = self.get_billing_rounding()?;
+        let rates = self.get_category_rates(None)?;
+        let chains = rate_chains(&self.get_categories()?);
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT a.id, NULL, a.category_id, MAX(a.started_at, ?1), MIN(a.ended_at, ?2)
+             FROM activities a
+             WHERE {in_range} AND a.is_idle = 0 AND a.category_id IS NOT NULL
+             UNION ALL
+             SELECT NULL, a.id, a.category_id, MAX(a.started_at, ?1), MIN(a.ended_at, ?2)
+             FROM manual_entries a
+             WHERE {in_range} AND a.category_id IS NOT NULL
+             ORDER BY 4, 1, 2",
+            in_range = IN_RANGE,
+        ))?;
+        let rows = stmt
+            .query_map(params![start, end], |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        let increment = rounding.increment_minutes * 60;
+        let mut billed = Vec::with_capacity(rows.len());
+        // Index of the day's last row and the day's total
+        let mut days: HashMap<DayKey, (usize, i64)> = HashMap::new();
+        for (activity_id, manual_entry_id, category_id, started_at, ended_at) in rows {
+            let Some(chain) = chains.get(&category_id) else {
+                continue;
+            };
+            let mut bounds: Vec<i64> = rates
+                .iter()
+                .filter(|r| chain.contains(&r.category_id) && r.effective_from > started_at && r.effective_from < ended_at)
+                .map(|r| r.effective_from)
+                .collect();
+            bounds.sort_unstable();
+            bounds.dedup();
+            bounds.push(ended_at);
+
+            let mut from = started_at;
+            for to in bounds {
+                let rate = rate_at(&rates, chain, from);
+                let seconds = to - from;
+                if rounding.per_day {
+                    let day = Local.timestamp_opt(from, 0).single().map(|t| t.date_naive());
+                    let entry = days.entry((day, category_id, rate.map(|r| r.id))).or_insert((0, 0));
+                    *entry = (billed.len(), entry.1 + seconds);
+                }
+                billed.push(BilledActivity {
+                    activity_id,
+                    manual_entry_id,
+                    category_id,
+                    started_at: from,
+                    seconds,
+                    billed_seconds: seconds,
+                    hourly_rate: rate.map(|r| r.hourly_rate),
+                    currency: rate.map(|r| r.currency.clone()),
+                    amount: 0.0, // set below
+                });
+                from = to;
+            }
+            if !rounding.per_day {
+                let total = ended_at - started_at;
+                if let Some(last) = billed.last_mut() {
+                    last.billed_seconds += round_up(total, increment) - total;
+                }
+            }
+        }
+        for (last, total) in days.into_values() {
+            billed[last].billed_seconds += round_up(total, increment) - total;
+        }
+        for row in &mut billed {
+            row.amount = amount(row.billed_seconds, row.hourly_rate.unwrap_or(0.0));
+        }
+        Ok(billed)
+    }
+}
+
+/// Billable categories with the categories whose rates they use, nearest first: the
+/// category itself, then its parent and so on up. The nearest flag that is set decides
+/// whether a category is billable.
+fn rate_chains(categories: &[Category]) -> HashMap<i64, Vec<i64>> {
+    let known: HashMap<i64, &Category> = categories.iter().map(|c| (c.id, c)).collect();
+    let mut chains = HashMap::new();
+    for category in categories {
+        let mut chain = vec![category.id];
+        let mut parent = category.parent_id;
+        // Parenting rejects cycles; the check only guards against a corrupted table
+        while let Some(id) = parent.filter(|id| !chain.contains(id)) {
+            let Some(ancestor) = known.get(&id) else {
+                break;
+            };
+            chain.push(id);
+            parent = ancestor.parent_id;
+        }
+        if chain.iter().find_map(|id| known[id].is_billable).unwrap_or(false) {
+            chains.insert(category.id, chain);
+        }
+    }
+    chains
+}
+
+/// Rate in effect at `at` of the nearest category in the chain that has one
+fn rate_at<'a>(rates: &'a [CategoryRate], chain: &[i64], at: i64) -> Option<&'a CategoryRate> {
+    chain
+        .iter()
+        .find_map(|id| rates.iter().rfind(|r| r.category_id == *id && r.effective_from <= at))
+}
+
+/// Round seconds up to a whole number of increments
+fn round_up(seconds: i64, increment: i64) -> i64 {
+    if increment <= 0 || seconds <= 0 {
+        return seconds.max(0);
+    }
+    (seconds + increment - 1) / increment * increment
+}
+
+/// Amount for billed seconds at an hourly rate, rounded to cents
+fn amount(billed_seconds: i64, hourly_rate: f64) -> f64 {
+    (billed_seconds as f64 / 3600.0 * hourly_rate * 100.0).round() / 100.0
+}
+
+/// Sum billed rows per category and rate, largest amount first. Amounts are the sum
+/// of the rows' amounts, so they match the rows as exported.
+pub(crate) fn earnings(billed: &[BilledActivity], categories: &[Category]) -> Vec<Earning> {
+    let mut earnings: Vec<Earning> = Vec::new();
+    for activity in billed {
+        let existing = earnings.iter_mut().find(|e| {
+            e.category_id == activity.category_id
+                && e.hourly_rate == activity.hourly_rate
+                && e.currency == activity.currency
+        });
+        match existing {
+            Some(earning) => {
+                earning.seconds += activity.seconds;
+                earning.billed_seconds += activity.billed_seconds;
+                earning.amount += activity.amount;
+            }
+            None => earnings.push(Earning {
+                category_id: activity.category_id,
+                category_name: categories
+                    .iter()
+                    .find(|c| c.id == activity.category_id)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                hourly_rate: activity.hourly_rate,
+                currency: activity.currency.clone(),
+                seconds: activity.seconds,
+                billed_seconds: activity.billed_seconds,
+                amount: activity.amount,
+            }),
+        }
+    }
+    for earning in &mut earnings {
+        earning.amount = (earning.amount * 100.0).round() / 100.0;
+    }
+    earnings.sort_by(|a, b| b.amount.total_cmp(&a.amount).then(a.category_id.cmp(&b.category_id)));
+    earnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+
+    /// Midday UTC, so all activities fall on the same local day in most time zones
+    const NOON: i64 = 1_700_049_600;
+
+    fn activity(db: &Database, category_id: i64, start: i64, end: i64) -> i64 {
+        let id = db.start_activity("Consulting", None, &WindowContext::default(), start).unwrap();
+        db.extend_activity(id, end).unwrap();
+        db.update_activity_category(id, Some(category_id)).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_rates_rounding_and_earnings() {
+        let db = Database::open_in_memory().unwrap();
+        let client = db.create_category_core("Client A", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        db.set_category_billable(client, Some(true)).unwrap();
+        db.set_category_rate(client, 100.0, "EUR", 0).unwrap();
+        let raise = db.set_category_rate(client, 120.0, "EUR", NOON + 1_800).unwrap();
+
+        // 10 min at the old rate, 20 min of which 15 at the old rate, 5 min at the new one
+        let first = activity(&db, client, NOON, NOON + 600);
+        activity(&db, client, NOON + 900, NOON + 2_100);
+        activity(&db, client, NOON + 2_400, NOON + 2_700);
+        // Not billable
+        let other = db.create_category_core("Internal", "#888888", None, Some(true), 101, false, false).unwrap();
+        activity(&db, other, NOON + 3_000, NOON + 3_600);
+
+        let amounts = |db: &Database| -> Vec<(Option<f64>, i64, i64, f64)> {
+            db.get_stats_for_range(NOON - 3_600, NOON + 7_200)
+                .unwrap()
+                .earnings
+                .into_iter()
+                .map(|e| (e.hourly_rate, e.seconds, e.billed_seconds, e.amount))
+                .collect()
+        };
+        assert_eq!(amounts(&db), vec![(Some(100.0), 1_500, 1_500, 41.67), (Some(120.0), 600, 600, 20.0)]);
+
+        // Every activity rounded up to 15 minutes; the split one rounds as a whole
+        db.set_setting(ROUNDING_MINUTES_KEY, "15").unwrap();
+        assert_eq!(amounts(&db), vec![(Some(120.0), 600, 1_800, 60.0), (Some(100.0), 1_500, 1_800, 50.0)]);
+        db.set_setting(ROUNDING_MINUTES_KEY, "6").unwrap();
+        let billed = db.get_billed_activities(NOON - 3_600, NOON + 7_200).unwrap();
+        assert_eq!(billed.iter().map(|b| b.billed_seconds).collect::<Vec<_>>(), vec![720, 900, 540, 360]);
+        assert_eq!(billed[2].started_at, NOON + 1_800);
+
+        // Only each day's total is rounded; the last row of the day carries the difference
+        db.set_setting(ROUNDING_SCOPE_KEY, "day").unwrap();
+        let billed = db.get_billed_activities(NOON - 3_600, NOON + 7_200).unwrap();
+        assert_eq!(billed.iter().map(|b| b.billed_seconds).collect::<Vec<_>>(), vec![600, 1_200, 300, 420]);
+        assert_eq!(billed[0].activity_id, Some(first));
+
+        // Without a rate the time is still reported, at no amount
+        db.delete_category_rate(raise).unwrap();
+        db.set_setting(ROUNDING_MINUTES_KEY, "0").unwrap();
+        assert_eq!(amounts(&db), vec![(Some(100.0), 2_100, 2_100, 58.33)]);
+        db.delete_category_rate(db.get_category_rates(Some(client)).unwrap()[0].id).unwrap();
+        assert_eq!(amounts(&db), vec![(None, 2_100, 2_100, 0.0)]);
+    }
+
+    #[test]
+    fn test_subcategories_and_manual_entries_are_billed() {
+        let db = Database::open_in_memory().unwrap();
+        let client = db.create_category_core("Client B", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let support = db.create_category_core("Support", "#00ff00", None, Some(true), 101, false, false).unwrap();
+        db.set_category_parent(support, Some(client)).unwrap();
+        db.set_category_billable(client, Some(true)).unwrap();
+        db.set_category_rate(client, 100.0, "EUR", 0).unwrap();
+
+        // Three 10 second entries at 0.28 each; recomputing from the 30 second total would give 0.83
+        for start in [NOON, NOON + 100, NOON + 200] {
+            db.add_manual_entry(Some("Call"), Some(support), start, start + 10).unwrap();
+        }
+        let billed = db.get_billed_activities(NOON - 3_600, NOON + 3_600).unwrap();
+        assert_eq!(billed.len(), 3);
+        assert!(billed.iter().all(|b| b.activity_id.is_none() && b.manual_entry_id.is_some()));
+        assert!(billed.iter().all(|b| b.hourly_rate == Some(100.0) && b.amount == 0.28));
+        let earnings = db.get_stats_for_range(NOON - 3_600, NOON + 3_600).unwrap().earnings;
+        assert_eq!(
+            earnings.iter().map(|e| (e.category_id, e.seconds, e.amount)).collect::<Vec<_>>(),
+            vec![(support, 30, 0.84)]
+        );
+
+        // A rate of its own takes over from the parent's
+        db.set_category_rate(support, 60.0, "EUR", 0).unwrap();
+        let billed = db.get_billed_activities(NOON - 3_600, NOON + 3_600).unwrap();
+        assert!(billed.iter().all(|b| b.hourly_rate == Some(60.0)));
+        db.set_category_billable(client, Some(false)).unwrap();
+        assert!(db.get_billed_activities(NOON - 3_600, NOON + 3_600).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_subcategories_can_opt_out_of_billing() {
+        let db = Database::open_in_memory().unwrap();
+        let client = db.create_category_core("Client A", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let internal = db.create_category_core("Internal", "#888888", None, Some(true), 101, false, false).unwrap();
+        let meetings = db.create_category_core("Standups", "#00ff00", None, Some(true), 102, false, false).unwrap();
+        db.set_category_parent(internal, Some(client)).unwrap();
+        db.set_category_parent(meetings, Some(internal)).unwrap();
+        db.set_category_billable(client, Some(true)).unwrap();
+        db.add_manual_entry(Some("Planning"), Some(meetings), NOON, NOON + 600).unwrap();
+        let billed = |db: &Database| db.get_billed_activities(NOON - 3_600, NOON + 3_600).unwrap().len();
+        assert_eq!(billed(&db), 1);
+
+        // The nearest flag decides, for the category and everything under it
+        db.set_category_billable(internal, Some(false)).unwrap();
+        assert_eq!(billed(&db), 0);
+        db.set_category_billable(meetings, Some(true)).unwrap();
+        assert_eq!(billed(&db), 1);
+        db.set_category_billable(meetings, None).unwrap();
+        db.set_category_billable(internal, None).unwrap();
+        assert_eq!(billed(&db), 1);
+    }
+}
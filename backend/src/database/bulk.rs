@@ -0,0 +1,178 @@
+//! Bulk edits of activities matching a filter
+//!
+//! A bulk edit applies one action to every activity starting in a time range
+//! that matches an `ActivityFilter`, in one transaction. A dry run makes the
+//! same changes and rolls them back, so its counts are exactly what a real
+//! run would report.
+
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{Result, params_from_iter};
+use super::activities::{ACTIVITY_COLUMNS, SOURCE_MANUAL, activity_from_row};
+use super::common::Database;
+use super::history::RecordedChange;
+use super::models::{ActivityFilter, BulkAction, BulkResult};
+
+/// Matching activities returned with a result
+const PREVIEW_LIMIT: usize = 50;
+
+impl Database {
+    /// Apply `action` to every activity starting in [start, end] that matches `filter`.
+    /// `affected` counts the activities that changed, or the tag links added or removed.
+    pub fn bulk_edit_activities(
+        &self,
+        start: i64,
+        end: i64,
+        filter: &ActivityFilter,
+        action: &BulkAction,
+        dry_run: bool,
+    ) -> Result<BulkResult> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let (conditions, values) = filter.conditions();
+        let mut where_parts = vec!["started_at >= ?".to_string(), "started_at <= ?".to_string()];
+        where_parts.extend(conditions);
+        let mut filter_values = vec![SqliteValue::Integer(start), SqliteValue::Integer(end)];
+        filter_values.extend(values);
+        let matching = format!("SELECT id FROM activities WHERE {}", where_parts.join(" AND "));
+
+        let (matched, matched_duration_sec): (usize, i64) = tx.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(duration_sec), 0) FROM activities WHERE {}",
+                where_parts.join(" AND ")
+            ),
+            params_from_iter(filter_values.iter()),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let preview = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {} FROM activities WHERE {} ORDER BY started_at, id LIMIT {}",
+                ACTIVITY_COLUMNS,
+                where_parts.join(" AND "),
+                PREVIEW_LIMIT
+            ))?;
+            let rows = stmt.query_map(params_from_iter(filter_values.iter()), activity_from_row)?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        let change = RecordedChange::begin(&tx, &format!("Bulk edit of {} activities", matched))?;
+        // Action values come before the filter's in every statement
+        let run = |sql: String, mut action_values: Vec<SqliteValue>| -> Result<usize> {
+            action_values.extend(filter_values.iter().cloned());
+            tx.execute(&sql, params_from_iter(action_values.iter()))
+        };
+        let tag_values = |tag_ids: &[i64]| tag_ids.iter().map(|id| SqliteValue::Integer(*id)).collect::<Vec<_>>();
+        let placeholders = |ids: &[i64]| vec!["?"; ids.len()].join(",");
+        let affected = match action {
+            // Categories set in bulk count as set by hand
+            BulkAction::SetCategory { category_id } => run(
+                format!(
+                    "UPDATE activities SET category_id = ?1, category_source = ?2, category_rule_id = NULL
+                     WHERE (category_id IS NOT ?1 OR category_source IS NOT ?2) AND id IN ({})",
+                    matching
+                ),
+                vec![(*category_id).into(), SOURCE_MANUAL.to_string().into()],
+            )?,
+            BulkAction::SetProject { project_id } => run(
+                format!("UPDATE activities SET project_id = ? WHERE project_id IS NOT ? AND id IN ({})", matching),
+                vec![(*project_id).into(), (*project_id).into()],
+            )?,
+            BulkAction::SetIdle { is_idle } => run(
+                format!("UPDATE activities SET is_idle = ? WHERE is_idle IS NOT ? AND id IN ({})", matching),
+                vec![(*is_idle).into(), (*is_idle).into()],
+            )?,
+            BulkAction::AddTags { tag_ids } if !tag_ids.is_empty() => run(
+                format!(
+                    "INSERT OR IGNORE INTO activity_tags (activity_id, tag_id)
+                     SELECT a.id, t.id FROM activities a JOIN tags t ON t.id IN ({})
+                     WHERE a.id IN ({})",
+                    placeholders(tag_ids),
+                    matching
+                ),
+                tag_values(tag_ids),
+            )?,
+            BulkAction::RemoveTags { tag_ids } if !tag_ids.is_empty() => run(
+                format!(
+                    "DELETE FROM activity_tags WHERE tag_id IN ({}) AND activity_id IN ({})",
+                    placeholders(tag_ids),
+                    matching
+                ),
+                tag_values(tag_ids),
+            )?,
+            BulkAction::AddTags { .. } | BulkAction::RemoveTags { .. } => 0,
+            BulkAction::Delete => {
+                run(
+                    format!("UPDATE events SET activity_id = NULL WHERE activity_id IN ({})", matching),
+                    Vec::new(),
+                )?;
+                run(format!("DELETE FROM activities WHERE id IN ({})", matching), Vec::new())?
+            }
+        };
+
+        change.finish()?;
+        if !dry_run {
+            tx.commit()?;
+        }
+        Ok(BulkResult { dry_run, matched, affected, matched_duration_sec, preview })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+
+    fn activity(db: &Database, app: &str, title: &str, start: i64) -> i64 {
+        let id = db.start_activity(app, Some(title), &WindowContext::default(), start).unwrap();
+        db.extend_activity(id, start + 100).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_bulk_edit_with_dry_run() {
+        let db = Database::open_in_memory().unwrap();
+        let client = db.create_category_core("Client work", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let ticket = activity(&db, "Reviewboard", "Ticket 42_a", 1_000);
+        let other = activity(&db, "Reviewboard", "Ticket 4200", 1_200);
+        let wiki = activity(&db, "Wikipad", "Ticket 42_a notes", 1_400);
+        activity(&db, "Reviewboard", "Ticket 42_a", 5_000);
+
+        let filter = ActivityFilter {
+            app_pattern: Some("review*".to_string()),
+            title_pattern: Some("*42_*".to_string()),
+            ..Default::default()
+        };
+        let recategorize = BulkAction::SetCategory { category_id: Some(client) };
+        let preview = db.bulk_edit_activities(0, 2_000, &filter, &recategorize, true).unwrap();
+        assert_eq!((preview.matched, preview.affected, preview.matched_duration_sec), (1, 1, 100));
+        assert_eq!(preview.preview.iter().map(|a| a.id).collect::<Vec<_>>(), vec![ticket]);
+        assert_ne!(db.get_activity_by_id(ticket).unwrap().unwrap().category_id, Some(client));
+
+        let done = db.bulk_edit_activities(0, 2_000, &filter, &recategorize, false).unwrap();
+        assert_eq!((done.matched, done.affected), (1, 1));
+        assert_eq!(db.get_activity_by_id(ticket).unwrap().unwrap().category_id, Some(client));
+        // Already in the category: matched but unchanged
+        let again = db.bulk_edit_activities(0, 2_000, &filter, &recategorize, false).unwrap();
+        assert_eq!((again.matched, again.affected), (1, 0));
+
+        let by_category = ActivityFilter { category_ids: vec![client], ..Default::default() };
+        let tag = db.create_tag("billed", "#888888").unwrap();
+        let tagged = db
+            .bulk_edit_activities(0, 2_000, &ActivityFilter::default(), &BulkAction::AddTags { tag_ids: vec![tag] }, false)
+            .unwrap();
+        assert_eq!(tagged.affected, 3);
+        let idle = db.bulk_edit_activities(0, 2_000, &by_category, &BulkAction::SetIdle { is_idle: true }, false).unwrap();
+        assert_eq!(idle.affected, 1);
+        let idle_only = ActivityFilter { only_idle: true, ..Default::default() };
+
+        let deleted = db.bulk_edit_activities(0, 2_000, &idle_only, &BulkAction::Delete, true).unwrap();
+        assert_eq!(deleted.affected, 1);
+        assert!(db.get_activity_by_id(ticket).unwrap().is_some());
+        db.bulk_edit_activities(0, 2_000, &idle_only, &BulkAction::Delete, false).unwrap();
+        assert!(db.get_activity_by_id(ticket).unwrap().is_none());
+        assert_eq!(
+            db.get_tag_links(0, 2_000).unwrap().activities,
+            vec![(other, tag), (wiki, tag)]
+        );
+    }
+}
@@ -1,7 +1,9 @@
 //! Category management database operations
 
 use rusqlite::{Result, params};
-use super::common::Database;
+use std::collections::HashSet;
+use super::common::{Database, invalid, unique_violation};
+use super::history::RecordedChange;
 use super::models::Category;
 
 impl Database {
@@ -9,7 +11,8 @@ impl Database {
     pub fn get_categories(&self) -> Result<Vec<Category>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, name, color, icon, is_productive, sort_order, is_system, is_pinned
+            "SELECT id, name, color, icon, is_productive, sort_order, is_system, is_pinned, parent_id,
+                    is_billable
              FROM categories
              ORDER BY sort_order ASC",
         )?;
@@ -25,6 +28,8 @@ impl Database {
                     sort_order: row.get(5)?,
                     is_system: row.get(6)?,
                     is_pinned: row.get(7)?,
+                    parent_id: row.get(8)?,
+                    is_billable: row.get(9)?,
                 })
             })?
             .collect::<Result<Vec<_>>>()?;
@@ -44,32 +49,23 @@ impl Database {
         is_pinned: bool,
     ) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Create category {}", name))?;
         conn.execute(
-            "INSERT INTO categories (name, color, icon, is_productive, sort_order, is_system, is_pinned)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO categories (name, color, icon, is_productive, sort_order, is_system, is_pinned, is_billable)
+             VALUES (?, ?, ?, ?, ?, ?, ?, NULL)",
             params![name, color, icon, is_productive, sort_order, is_system, is_pinned],
         )
-        .map_err(|e| {
-            if let rusqlite::Error::SqliteFailure(ref err, Some(ref msg)) = e {
-                if err.code == rusqlite::ffi::ErrorCode::ConstraintViolation
-                    && (msg.contains("categories.name") 
-                        || (msg.contains("UNIQUE constraint") && msg.contains("categories")))
-                {
-                    return rusqlite::Error::SqliteFailure(
-                        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                        Some("A category with this name already exists".to_string()),
-                    );
-                }
-            }
-            e
-        })?;
-        Ok(conn.last_insert_rowid())
+        .map_err(|e| unique_violation(e, "A category with this name already exists"))?;
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
     }
 
 
     /// Reset system category to default values
     pub fn reset_system_category(&self, id: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Reset category {}", id))?;
         
         let is_system: bool = conn.query_row(
             "SELECT is_system FROM categories WHERE id = ?",
@@ -78,10 +74,7 @@ impl Database {
         )?;
 
         if !is_system {
-            return Err(rusqlite::Error::SqliteFailure(
-                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                Some("Only system categories can be reset".to_string()),
-            ));
+            return Err(invalid("Only system categories can be reset"));
         }
 
         let category_name: String = conn.query_row(
@@ -95,10 +88,7 @@ impl Database {
             "Break" => ("#795548", Some("☕"), Some(false), 7, true),
             "Uncategorized" => ("#9E9E9E", Some("❓"), None::<bool>, 8, false),
             _ => {
-                return Err(rusqlite::Error::SqliteFailure(
-                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                    Some(format!("Unknown system category: {}", category_name)),
-                ));
+                return Err(invalid(format!("Unknown system category: {}", category_name)));
             }
         };
 
@@ -108,7 +98,7 @@ impl Database {
             params![color, icon, is_productive, sort_order, is_pinned, id],
         )?;
         
-        Ok(())
+        change.finish()
     }
 
     /// Update category core fields only
@@ -123,33 +113,55 @@ impl Database {
         is_pinned: bool,
     ) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Update category {}", id))?;
         
         conn.execute(
             "UPDATE categories SET name = ?, color = ?, icon = ?, is_productive = ?, sort_order = ?, is_pinned = ?
              WHERE id = ?",
             params![name, color, icon, is_productive, sort_order, is_pinned, id],
         )
-        .map_err(|e| {
-            if let rusqlite::Error::SqliteFailure(ref err, Some(ref msg)) = e {
-                if err.code == rusqlite::ffi::ErrorCode::ConstraintViolation
-                    && (msg.contains("categories.name") 
-                        || (msg.contains("UNIQUE constraint") && msg.contains("categories")))
-                {
-                    return rusqlite::Error::SqliteFailure(
-                        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                        Some("A category with this name already exists".to_string()),
-                    );
-                }
-            }
-            e
-        })?;
-        Ok(())
+        .map_err(|e| unique_violation(e, "A category with this name already exists"))?;
+        change.finish()
     }
 
 
-    /// Delete category (with validation)
-    pub fn delete_category(&self, id: i64) -> Result<()> {
+    /// Nest a category under `parent_id`, or make it top-level with None.
+    /// Fails if the parent is the category itself or one of its descendants.
+    pub fn set_category_parent(&self, id: i64, parent_id: Option<i64>) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Move category {}", id))?;
+        // Stored parents may already loop; stop at the first repeat instead of walking forever
+        let mut visited = HashSet::new();
+        let mut ancestor = parent_id;
+        while let Some(current) = ancestor {
+            if current == id || !visited.insert(current) {
+                return Err(invalid("A category cannot be nested under itself or one of its subcategories"));
+            }
+            ancestor = conn
+                .query_row(
+                    "SELECT parent_id FROM categories WHERE id = ?",
+                    params![current],
+                    |row| row.get::<_, Option<i64>>(0),
+                )
+                .optional()?
+                .ok_or_else(|| {
+                    invalid(format!("Parent category {} does not exist", current))
+                })?;
+        }
+
+        conn.execute(
+            "UPDATE categories SET parent_id = ? WHERE id = ?",
+            params![parent_id, id],
+        )?;
+        change.finish()
+    }
+
+    /// Delete category (with validation).
+    /// Subcategories move up to the deleted category's parent.
+    pub fn delete_category(&self, id: i64) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let conn = conn.transaction()?;
+        let change = RecordedChange::begin(&conn, &format!("Delete category {}", id))?;
         
         let is_system: bool = conn.query_row(
             "SELECT is_system FROM categories WHERE id = ?",
@@ -158,10 +170,7 @@ impl Database {
         )?;
 
         if is_system {
-            return Err(rusqlite::Error::SqliteFailure(
-                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                Some("Cannot delete system category".to_string()),
-            ));
+            return Err(invalid("Cannot delete system category"));
         }
         
         let count: i64 = conn.query_row(
@@ -171,10 +180,7 @@ impl Database {
         )?;
 
         if count > 0 {
-            return Err(rusqlite::Error::SqliteFailure(
-                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                Some(format!("Category is used by {} activities", count)),
-            ));
+            return Err(invalid(format!("Category is used by {} activities", count)));
         }
 
         let count: i64 = conn.query_row(
@@ -184,14 +190,18 @@ impl Database {
         )?;
 
         if count > 0 {
-            return Err(rusqlite::Error::SqliteFailure(
-                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                Some(format!("Category is used by {} rules", count)),
-            ));
+            return Err(invalid(format!("Category is used by {} rules", count)));
         }
 
+        conn.execute(
+            "UPDATE categories SET parent_id = (SELECT parent_id FROM categories WHERE id = ?1)
+             WHERE parent_id = ?1",
+            params![id],
+        )?;
+        conn.execute("DELETE FROM category_rates WHERE category_id = ?", params![id])?;
         conn.execute("DELETE FROM categories WHERE id = ?", params![id])?;
-        Ok(())
+        change.finish()?;
+        conn.commit()
     }
 
     /// Find category by name
@@ -208,3 +218,66 @@ impl Database {
 
 // Use OptionalExtension from common module
 use super::common::OptionalExtension;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{CategoryUsageStat, WindowContext};
+
+    #[test]
+    fn test_category_hierarchy() {
+        let db = Database::open_in_memory().unwrap();
+        let work = db.create_category_core("Client work", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let coding = db.create_category_core("Implementation", "#00ff00", None, Some(true), 101, false, false).unwrap();
+        let review = db.create_category_core("Code review", "#ff0000", None, Some(true), 102, false, false).unwrap();
+        db.set_category_parent(coding, Some(work)).unwrap();
+        db.set_category_parent(review, Some(coding)).unwrap();
+
+        // No cycles, directly or through descendants
+        assert!(db.set_category_parent(work, Some(work)).is_err());
+        assert!(db.set_category_parent(work, Some(review)).unwrap_err().to_string().contains("subcategories"));
+        assert!(db.set_category_parent(work, Some(9_999)).is_err());
+        // A loop already stored between other categories is reported, not walked forever
+        let left = db.create_category_core("Left", "#111111", None, None, 103, false, false).unwrap();
+        let right = db.create_category_core("Right", "#222222", None, None, 104, false, false).unwrap();
+        db.conn
+            .lock()
+            .unwrap()
+            .execute_batch(&format!(
+                "UPDATE categories SET parent_id = {right} WHERE id = {left};
+                 UPDATE categories SET parent_id = {left} WHERE id = {right};"
+            ))
+            .unwrap();
+        assert!(db.set_category_parent(work, Some(left)).is_err());
+        db.conn.lock().unwrap().execute("DELETE FROM categories WHERE id IN (?, ?)", params![left, right]).unwrap();
+
+        // Rules still resolve to the subcategory itself
+        let rule = db.add_rule("app_name", "Reviewboard", review, 100).unwrap();
+        let activity = db.start_activity("Reviewboard", None, &WindowContext::default(), 1_000).unwrap();
+        db.extend_activity(activity, 1_600).unwrap();
+        assert_eq!(db.get_activity_by_id(activity).unwrap().unwrap().category_id, Some(review));
+        let other = db.start_activity("Scratchpad", None, &WindowContext::default(), 2_000).unwrap();
+        db.extend_activity(other, 2_300).unwrap();
+        db.update_activity_category(other, Some(work)).unwrap();
+
+        let tree = db.get_stats_for_range(0, 10_000).unwrap().category_tree;
+        assert_eq!(tree.len(), 1);
+        assert_eq!((tree[0].category_id, tree[0].self_seconds, tree[0].total_seconds), (work, 300, 900));
+        let child = &tree[0].children[0];
+        assert_eq!((child.category_id, child.self_seconds, child.total_seconds), (coding, 0, 600));
+        assert_eq!(child.children[0].total_seconds, 600);
+
+        let usage = db.get_category_usage(0, 10_000).unwrap();
+        assert_eq!(usage.len(), 1);
+        let node = |u: &CategoryUsageStat| (u.category.id, u.duration_sec, u.total_duration_sec, u.percentage);
+        assert_eq!(node(&usage[0]), (work, 300, 900, 100));
+        assert_eq!(node(&usage[0].children[0]), (coding, 0, 600, 66));
+        assert_eq!(node(&usage[0].children[0].children[0]), (review, 600, 600, 66));
+
+        // Subcategories move up to the deleted category's parent
+        db.delete_rule(rule).unwrap();
+        db.delete_category(coding).unwrap();
+        let parents: Vec<_> = db.get_categories().unwrap().into_iter().map(|c| (c.id, c.parent_id)).collect();
+        assert!(parents.contains(&(review, Some(work))));
+    }
+}
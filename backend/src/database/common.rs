@@ -5,7 +5,12 @@ use std::path::PathBuf;
 use std::sync::Mutex;
 
 /// Latest schema version; new installs get this without running migrations.
-const LATEST_SCHEMA_VERSION: i64 = 13;
+const LATEST_SCHEMA_VERSION: i64 = 32;
+
+/// Project names are unique per client and among projects without a client
+/// (a plain UNIQUE (client_id, name) lets NULL client ids repeat a name)
+const PROJECT_NAME_INDEX: &str =
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_projects_client_name ON projects(COALESCE(client_id, 0), name)";
 
 /// System category IDs (negative to avoid conflicts with regular categories)
 pub const SYSTEM_CATEGORY_UNCATEGORIZED: i64 = -1;
@@ -33,6 +38,16 @@ impl Database {
         Ok(db)
     }
 
+    /// Create an in-memory database with the full schema (used by tests)
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        let db = Self {
+            conn: Mutex::new(Connection::open_in_memory()?),
+        };
+        db.init()?;
+        Ok(db)
+    }
+
     /// Initialize the database schema
     pub(crate) fn init(&self) -> Result<()> {
         let conn = self.conn.lock().unwrap();
@@ -45,11 +60,26 @@ impl Database {
                 app_name TEXT NOT NULL,
                 window_title TEXT,
                 domain TEXT,
+                url TEXT,
+                project TEXT,
+                document TEXT,
+                exe_path TEXT,
+                cmdline TEXT,
+                cwd TEXT,
+                git_repo TEXT,
+                git_branch TEXT,
+                suppression_reason TEXT,
                 category_id INTEGER,
+                -- Where category_id came from: 'rule' (with category_rule_id), 'manual', 'plugin' or 'default'
+                category_source TEXT,
+                category_rule_id INTEGER,
+                project_id INTEGER,
                 started_at INTEGER NOT NULL,
+                ended_at INTEGER,
                 duration_sec INTEGER NOT NULL,
                 is_idle BOOLEAN DEFAULT FALSE,
-                FOREIGN KEY (category_id) REFERENCES categories(id)
+                FOREIGN KEY (category_id) REFERENCES categories(id),
+                FOREIGN KEY (project_id) REFERENCES projects(id)
             );
 
             CREATE INDEX IF NOT EXISTS idx_activities_started ON activities(started_at);
@@ -67,7 +97,9 @@ impl Database {
                 is_productive BOOLEAN DEFAULT TRUE,
                 sort_order INTEGER DEFAULT 0,
                 is_system BOOLEAN DEFAULT FALSE,
-                is_pinned BOOLEAN DEFAULT FALSE
+                is_pinned BOOLEAN DEFAULT FALSE,
+                parent_id INTEGER REFERENCES categories(id),
+                is_billable BOOLEAN
             );
 
             -- Rules table
@@ -86,9 +118,11 @@ impl Database {
                 entry_type TEXT NOT NULL,
                 description TEXT,
                 category_id INTEGER,
+                project_id INTEGER,
                 started_at INTEGER NOT NULL,
                 ended_at INTEGER NOT NULL,
-                FOREIGN KEY (category_id) REFERENCES categories(id)
+                FOREIGN KEY (category_id) REFERENCES categories(id),
+                FOREIGN KEY (project_id) REFERENCES projects(id)
             );
 
             CREATE INDEX IF NOT EXISTS idx_manual_entries_started ON manual_entries(started_at);
@@ -113,6 +147,167 @@ impl Database {
                 installed_at INTEGER NOT NULL,
                 enabled BOOLEAN DEFAULT TRUE
             );
+
+            -- Events reported by external watchers (merged heartbeats)
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                ended_at INTEGER NOT NULL,
+                data TEXT NOT NULL,
+                activity_id INTEGER,
+                FOREIGN KEY (activity_id) REFERENCES activities(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_events_source_ended ON events(source, ended_at);
+            CREATE INDEX IF NOT EXISTS idx_events_started ON events(started_at);
+            CREATE INDEX IF NOT EXISTS idx_events_activity ON events(activity_id);
+
+            -- Work-hours schedule: weekly windows and per-date exceptions
+            CREATE TABLE IF NOT EXISTS schedule_windows (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                weekday INTEGER NOT NULL,
+                start_minute INTEGER NOT NULL,
+                end_minute INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS schedule_exceptions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                start_minute INTEGER,
+                end_minute INTEGER,
+                label TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_schedule_exceptions_date ON schedule_exceptions(date);
+
+            -- Timers running until stopped, then turned into manual entries
+            CREATE TABLE IF NOT EXISTS running_timers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                category_id INTEGER,
+                description TEXT,
+                started_at INTEGER NOT NULL,
+                FOREIGN KEY (category_id) REFERENCES categories(id)
+            );
+
+            -- Clients and the projects time is assigned to
+            CREATE TABLE IF NOT EXISTS clients (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                color TEXT NOT NULL DEFAULT '#888888',
+                archived BOOLEAN NOT NULL DEFAULT FALSE
+            );
+
+            CREATE TABLE IF NOT EXISTS projects (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                client_id INTEGER,
+                color TEXT NOT NULL DEFAULT '#888888',
+                archived BOOLEAN NOT NULL DEFAULT FALSE,
+                FOREIGN KEY (client_id) REFERENCES clients(id)
+            );
+
+            -- Free-form tags; links go away with the tagged row or the tag
+            CREATE TABLE IF NOT EXISTS tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                color TEXT NOT NULL DEFAULT '#888888'
+            );
+
+            CREATE TABLE IF NOT EXISTS activity_tags (
+                activity_id INTEGER NOT NULL,
+                tag_id INTEGER NOT NULL,
+                PRIMARY KEY (activity_id, tag_id),
+                FOREIGN KEY (activity_id) REFERENCES activities(id),
+                FOREIGN KEY (tag_id) REFERENCES tags(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_activity_tags_tag ON activity_tags(tag_id);
+
+            CREATE TABLE IF NOT EXISTS manual_entry_tags (
+                manual_entry_id INTEGER NOT NULL,
+                tag_id INTEGER NOT NULL,
+                PRIMARY KEY (manual_entry_id, tag_id),
+                FOREIGN KEY (manual_entry_id) REFERENCES manual_entries(id),
+                FOREIGN KEY (tag_id) REFERENCES tags(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_manual_entry_tags_tag ON manual_entry_tags(tag_id);
+
+            CREATE TRIGGER IF NOT EXISTS activity_tags_on_delete AFTER DELETE ON activities
+            BEGIN
+                DELETE FROM activity_tags WHERE activity_id = OLD.id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS manual_entry_tags_on_delete AFTER DELETE ON manual_entries
+            BEGIN
+                DELETE FROM manual_entry_tags WHERE manual_entry_id = OLD.id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS tag_links_on_delete AFTER DELETE ON tags
+            BEGIN
+                DELETE FROM activity_tags WHERE tag_id = OLD.id;
+                DELETE FROM manual_entry_tags WHERE tag_id = OLD.id;
+            END;
+
+            -- Hourly rate of a category from effective_from until the next rate starts
+            CREATE TABLE IF NOT EXISTS category_rates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                category_id INTEGER NOT NULL,
+                hourly_rate REAL NOT NULL,
+                currency TEXT NOT NULL,
+                effective_from INTEGER NOT NULL,
+                UNIQUE(category_id, effective_from),
+                FOREIGN KEY (category_id) REFERENCES categories(id)
+            );
+
+            -- Free-text notes on one activity or on a [started_at, ended_at) span.
+            -- Activity notes take their span from the activity.
+            CREATE TABLE IF NOT EXISTS notes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                activity_id INTEGER,
+                started_at INTEGER,
+                ended_at INTEGER,
+                body TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                FOREIGN KEY (activity_id) REFERENCES activities(id),
+                CHECK (activity_id IS NOT NULL OR (started_at IS NOT NULL AND ended_at > started_at))
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_notes_activity ON notes(activity_id);
+            CREATE INDEX IF NOT EXISTS idx_notes_span ON notes(started_at, ended_at);
+
+            CREATE TRIGGER IF NOT EXISTS notes_on_activity_delete AFTER DELETE ON activities
+            BEGIN
+                DELETE FROM notes WHERE activity_id = OLD.id;
+            END;
+
+            -- Edit history: one change per user operation, with JSON images of the
+            -- rows it touched (before is NULL for inserts, after is NULL for deletes)
+            CREATE TABLE IF NOT EXISTS changes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                summary TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                is_open INTEGER NOT NULL DEFAULT 0,
+                undone_at INTEGER
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_changes_open ON changes(is_open) WHERE is_open = 1;
+
+            CREATE TABLE IF NOT EXISTS change_rows (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                change_id INTEGER NOT NULL,
+                table_name TEXT NOT NULL,
+                row_id INTEGER NOT NULL,
+                before TEXT,
+                after TEXT,
+                FOREIGN KEY (change_id) REFERENCES changes(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_change_rows_change ON change_rows(change_id);
         "#)?;
 
         // Check if this is a fresh install or existing database
@@ -127,6 +322,7 @@ impl Database {
         match existing_version {
             None => {
                 // Fresh install -- schema is already at latest, just record version
+                conn.execute(PROJECT_NAME_INDEX, [])?;
                 conn.execute(
                     "INSERT INTO settings (key, value) VALUES ('schema_version', ?)",
                     params![LATEST_SCHEMA_VERSION],
@@ -238,6 +434,8 @@ impl Database {
             }
         }
 
+        super::history::install_change_triggers(&conn)?;
+
         Ok(())
     }
 
@@ -267,6 +465,25 @@ impl Database {
         if version < 11 { self.migrate_v11(conn)?; }
         if version < 12 { self.migrate_v12(conn)?; }
         if version < 13 { self.migrate_v13(conn)?; }
+        if version < 14 { self.migrate_v14(conn)?; }
+        if version < 15 { self.migrate_v15(conn)?; }
+        if version < 16 { self.migrate_v16(conn)?; }
+        if version < 17 { self.migrate_v17(conn)?; }
+        if version < 18 { self.migrate_v18(conn)?; }
+        if version < 19 { self.migrate_v19(conn)?; }
+        if version < 20 { self.migrate_v20(conn)?; }
+        if version < 21 { self.migrate_v21(conn)?; }
+        if version < 22 { self.migrate_v22(conn)?; }
+        if version < 23 { self.migrate_v23(conn)?; }
+        if version < 24 { self.migrate_v24(conn)?; }
+        if version < 25 { self.migrate_v25(conn)?; }
+        if version < 26 { self.migrate_v26(conn)?; }
+        if version < 27 { self.migrate_v27(conn)?; }
+        if version < 28 { self.migrate_v28(conn)?; }
+        if version < 29 { self.migrate_v29(conn)?; }
+        if version < 30 { self.migrate_v30(conn)?; }
+        if version < 31 { self.migrate_v31(conn)?; }
+        if version < 32 { self.migrate_v32(conn)?; }
 
         Ok(())
     }
@@ -289,6 +506,345 @@ impl Database {
         Ok(())
     }
 
+    /// Activities become focus sessions with an explicit end.
+    /// Backfills ended_at from the old duration guess and clips every row at the
+    /// start of the next one, so overlapping (double-counted) time is removed.
+    fn migrate_v14(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if !Self::column_exists(conn, "activities", "ended_at") {
+            tx.execute("ALTER TABLE activities ADD COLUMN ended_at INTEGER", [])?;
+        }
+        tx.execute_batch(r#"
+            UPDATE activities SET ended_at = started_at + duration_sec WHERE ended_at IS NULL;
+
+            UPDATE activities SET ended_at = MAX(activities.started_at, o.next_start)
+            FROM (
+                SELECT id, LEAD(started_at) OVER (ORDER BY started_at, id) AS next_start
+                FROM activities
+            ) AS o
+            WHERE activities.id = o.id
+              AND o.next_start IS NOT NULL
+              AND o.next_start < activities.ended_at;
+
+            UPDATE activities SET duration_sec = ended_at - started_at;
+        "#)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '14')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Events table for heartbeats from external watchers; created by `init`,
+    /// so only the version is recorded here.
+    fn migrate_v15(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '15')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Full URL of the active browser tab
+    fn migrate_v16(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if !Self::column_exists(conn, "activities", "url") {
+            tx.execute("ALTER TABLE activities ADD COLUMN url TEXT", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '16')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Project and document recognised in editor window titles
+    fn migrate_v17(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        for column in ["project", "document"] {
+            if !Self::column_exists(conn, "activities", column) {
+                tx.execute(&format!("ALTER TABLE activities ADD COLUMN {} TEXT", column), [])?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '17')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Process executable, command line and working directory, and the enclosing git repository
+    fn migrate_v18(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        for column in ["exe_path", "cmdline", "cwd", "git_repo", "git_branch"] {
+            if !Self::column_exists(conn, "activities", column) {
+                tx.execute(&format!("ALTER TABLE activities ADD COLUMN {} TEXT", column), [])?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '18')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Schedule tables; created by `init`, so only the version is recorded here.
+    fn migrate_v19(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '19')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Running timers table; created by `init`, so only the version is recorded here.
+    fn migrate_v20(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '20')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Why idle detection was suppressed while an activity was recorded
+    fn migrate_v21(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if !Self::column_exists(conn, "activities", "suppression_reason") {
+            tx.execute("ALTER TABLE activities ADD COLUMN suppression_reason TEXT", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '21')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Project of a manual entry, e.g. one segment of classified idle time
+    fn migrate_v22(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if !Self::column_exists(conn, "manual_entries", "project") {
+            tx.execute("ALTER TABLE manual_entries ADD COLUMN project TEXT", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '22')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Project assignment of activities and manual entries; the clients and
+    /// projects tables are created by `init`
+    fn migrate_v23(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        for table in ["activities", "manual_entries"] {
+            if !Self::column_exists(conn, table, "project_id") {
+                tx.execute(
+                    &format!("ALTER TABLE {} ADD COLUMN project_id INTEGER REFERENCES projects(id)", table),
+                    [],
+                )?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '23')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Tags and their link tables; created by `init`, so only the version is recorded here.
+    fn migrate_v24(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '24')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Parent category, for categories nested under another
+    fn migrate_v25(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if !Self::column_exists(conn, "categories", "parent_id") {
+            tx.execute("ALTER TABLE categories ADD COLUMN parent_id INTEGER REFERENCES categories(id)", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '25')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Billable flag of categories; their rates live in `category_rates`, created by `init`
+    fn migrate_v26(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if !Self::column_exists(conn, "categories", "is_billable") {
+            tx.execute("ALTER TABLE categories ADD COLUMN is_billable BOOLEAN DEFAULT FALSE", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '26')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Idle descriptions used to overwrite the idle row's `window_title`; move them
+    /// into notes on the idle activity. The notes table is created by `init`.
+    fn migrate_v27(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO notes (activity_id, body, created_at, updated_at)
+             SELECT id, window_title, ended_at, ended_at FROM activities
+             WHERE app_name = 'Idle' AND is_idle = 1 AND TRIM(COALESCE(window_title, '')) <> ''",
+            [],
+        )?;
+        tx.execute(
+            "UPDATE activities SET window_title = NULL
+             WHERE app_name = 'Idle' AND is_idle = 1 AND window_title IS NOT NULL",
+            [],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '27')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Track where each activity's category came from. Existing rows keep a NULL
+    /// source: it is unknown whether their category was set by hand.
+    fn migrate_v28(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if !Self::column_exists(conn, "activities", "category_source") {
+            tx.execute("ALTER TABLE activities ADD COLUMN category_source TEXT", [])?;
+        }
+        if !Self::column_exists(conn, "activities", "category_rule_id") {
+            tx.execute("ALTER TABLE activities ADD COLUMN category_rule_id INTEGER", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '28')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Manual entries reference projects by id only. Project names stored as text since
+    /// version 22 become projects without a client, reusing one of the same name.
+    fn migrate_v29(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if Self::column_exists(conn, "manual_entries", "project") {
+            tx.execute(
+                "INSERT INTO projects (name)
+                 SELECT DISTINCT m.project FROM manual_entries m
+                 WHERE m.project_id IS NULL AND TRIM(COALESCE(m.project, '')) != ''
+                   AND NOT EXISTS (SELECT 1 FROM projects p WHERE p.name = m.project AND p.client_id IS NULL)",
+                [],
+            )?;
+            tx.execute(
+                "UPDATE manual_entries
+                 SET project_id = (SELECT MIN(p.id) FROM projects p WHERE p.name = manual_entries.project AND p.client_id IS NULL)
+                 WHERE project_id IS NULL AND TRIM(COALESCE(project, '')) != ''",
+                [],
+            )?;
+            tx.execute("ALTER TABLE manual_entries DROP COLUMN project", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '29')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Unique project names among projects without a client. Duplicates are merged into
+    /// the oldest project of the name, which keeps their activities and manual entries.
+    fn migrate_v30(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        for table in ["activities", "manual_entries"] {
+            tx.execute(
+                &format!(
+                    "UPDATE {table} SET project_id = (
+                         SELECT MIN(k.id) FROM projects k JOIN projects d ON d.name = k.name
+                         WHERE d.id = {table}.project_id AND k.client_id IS NULL
+                     )
+                     WHERE project_id IN (SELECT id FROM projects WHERE client_id IS NULL)",
+                    table = table,
+                ),
+                [],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM projects
+             WHERE client_id IS NULL
+               AND id > (SELECT MIN(k.id) FROM projects k WHERE k.client_id IS NULL AND k.name = projects.name)",
+            [],
+        )?;
+        tx.execute(PROJECT_NAME_INDEX, [])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '30')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Activities categorized before version 28 have no category source. Those in the
+    /// category the current rules give count as categorized by the rules, the others as
+    /// set by hand, so reapplying rules keeps them.
+    fn migrate_v31(&self, conn: &Connection) -> Result<()> {
+        use super::activities::{ACTIVITY_COLUMNS, SOURCE_DEFAULT, SOURCE_MANUAL, activity_from_row, default_category, load_rules, rule_category};
+
+        let tx = conn.unchecked_transaction()?;
+        let rules = load_rules(&tx)?;
+        let default = default_category(&tx);
+        {
+            let mut update = tx.prepare("UPDATE activities SET category_source = ?, category_rule_id = ? WHERE id = ?")?;
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {} FROM activities WHERE category_source IS NULL ORDER BY id",
+                ACTIVITY_COLUMNS
+            ))?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let activity = activity_from_row(row)?;
+                let (category_id, source, rule_id) =
+                    rule_category(&rules, &activity).unwrap_or((default, SOURCE_DEFAULT, None));
+                if activity.category_id == category_id {
+                    update.execute(params![source, rule_id, activity.id])?;
+                } else {
+                    update.execute(params![SOURCE_MANUAL, None::<i64>, activity.id])?;
+                }
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '31')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The billable flag is unset (NULL) for categories that follow their parent, so a
+    /// subcategory can opt out by being explicitly not billable. Unflagged categories
+    /// followed their parent already and keep doing so.
+    fn migrate_v32(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("UPDATE categories SET is_billable = NULL WHERE is_billable = 0", [])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('schema_version', '32')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
     fn migrate_v11(&self, conn: &Connection) -> Result<()> {
         let tx = conn.unchecked_transaction()?;
         tx.execute(
@@ -626,3 +1182,202 @@ impl<T> OptionalExtension<T> for Result<T> {
         }
     }
 }
+
+/// Constraint error carrying a readable message for the user
+pub(crate) fn invalid(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
+        Some(message.into()),
+    )
+}
+
+/// Replace a UNIQUE constraint failure with a readable message
+pub(crate) fn unique_violation(e: rusqlite::Error, message: &str) -> rusqlite::Error {
+    match e {
+        rusqlite::Error::SqliteFailure(ref err, _) if err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE => {
+            invalid(message)
+        }
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v14_clips_overlapping_activities() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        // Rows as written by the old duration-guessing tracker: the first two overlap
+        conn.execute_batch(r#"
+            INSERT INTO activities (app_name, started_at, duration_sec) VALUES ('Code', 1000, 120);
+            INSERT INTO activities (app_name, started_at, duration_sec) VALUES ('Slack', 1060, 5);
+            INSERT INTO activities (app_name, started_at, duration_sec) VALUES ('Code', 1200, 30);
+            UPDATE settings SET value = '13' WHERE key = 'schema_version';
+        "#).unwrap();
+
+        db.migrate(&conn).unwrap();
+
+        let rows: Vec<(i64, i64, i64)> = conn
+            .prepare("SELECT started_at, ended_at, duration_sec FROM activities ORDER BY started_at")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(rows, vec![(1000, 1060, 60), (1060, 1065, 5), (1200, 1230, 30)]);
+        assert_eq!(db.get_schema_version(&conn), LATEST_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_v27_moves_idle_descriptions_to_notes() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        // The idle prompt used to write its description into window_title
+        conn.execute_batch(r#"
+            INSERT INTO activities (app_name, window_title, started_at, ended_at, duration_sec, is_idle)
+                VALUES ('Idle', 'Design review', 1000, 1600, 600, TRUE);
+            INSERT INTO activities (app_name, window_title, started_at, ended_at, duration_sec, is_idle)
+                VALUES ('Idle', NULL, 2000, 2300, 300, TRUE);
+            INSERT INTO activities (app_name, window_title, started_at, ended_at, duration_sec, is_idle)
+                VALUES ('Code', 'main.rs', 1600, 2000, 400, FALSE);
+            UPDATE settings SET value = '26' WHERE key = 'schema_version';
+        "#).unwrap();
+
+        db.migrate(&conn).unwrap();
+
+        let titles: Vec<Option<String>> = conn
+            .prepare("SELECT window_title FROM activities ORDER BY started_at")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(titles, vec![None, Some("main.rs".to_string()), None]);
+        let notes: Vec<(i64, String)> = conn
+            .prepare("SELECT a.started_at, n.body FROM notes n JOIN activities a ON a.id = n.activity_id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(notes, vec![(1000, "Design review".to_string())]);
+    }
+
+    #[test]
+    fn test_migrate_v29_turns_manual_entry_project_names_into_projects() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        conn.execute_batch(r#"
+            ALTER TABLE manual_entries ADD COLUMN project TEXT;
+            INSERT INTO projects (name) VALUES ('Website');
+            INSERT INTO manual_entries (entry_type, project, started_at, ended_at) VALUES ('idle', 'Website', 1000, 1100);
+            INSERT INTO manual_entries (entry_type, project, started_at, ended_at) VALUES ('idle', 'Offsite', 1100, 1200);
+            INSERT INTO manual_entries (entry_type, project, started_at, ended_at) VALUES ('idle', 'Offsite', 1200, 1300);
+            INSERT INTO manual_entries (entry_type, project, started_at, ended_at) VALUES ('idle', '', 1300, 1400);
+            UPDATE settings SET value = '28' WHERE key = 'schema_version';
+        "#).unwrap();
+
+        db.migrate(&conn).unwrap();
+
+        assert!(!Database::column_exists(&conn, "manual_entries", "project"));
+        let projects: Vec<(i64, Option<String>)> = conn
+            .prepare("SELECT m.started_at, p.name FROM manual_entries m LEFT JOIN projects p ON p.id = m.project_id ORDER BY m.started_at")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let name = |n: &str| Some(n.to_string());
+        assert_eq!(
+            projects,
+            vec![(1000, name("Website")), (1100, name("Offsite")), (1200, name("Offsite")), (1300, None)]
+        );
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(db.get_schema_version(&conn), LATEST_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_v30_merges_duplicate_projects_without_client() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        conn.execute_batch(r#"
+            DROP INDEX idx_projects_client_name;
+            INSERT INTO clients (id, name) VALUES (1, 'Acme');
+            INSERT INTO projects (id, name) VALUES (1, 'Website');
+            INSERT INTO projects (id, name) VALUES (2, 'Website');
+            INSERT INTO projects (id, name, client_id) VALUES (3, 'Website', 1);
+            INSERT INTO activities (app_name, started_at, ended_at, duration_sec, project_id) VALUES ('Code', 0, 60, 60, 2);
+            INSERT INTO manual_entries (entry_type, started_at, ended_at, project_id) VALUES ('', 0, 60, 2);
+            INSERT INTO manual_entries (entry_type, started_at, ended_at, project_id) VALUES ('', 60, 120, 3);
+            UPDATE settings SET value = '29' WHERE key = 'schema_version';
+        "#).unwrap();
+
+        db.migrate(&conn).unwrap();
+
+        let ids = |sql: &str| -> Vec<i64> {
+            conn.prepare(sql).unwrap().query_map([], |row| row.get(0)).unwrap().collect::<Result<_>>().unwrap()
+        };
+        assert_eq!(ids("SELECT id FROM projects ORDER BY id"), vec![1, 3]);
+        assert_eq!(ids("SELECT project_id FROM activities"), vec![1]);
+        assert_eq!(ids("SELECT project_id FROM manual_entries ORDER BY started_at"), vec![1, 3]);
+        assert!(conn.execute("INSERT INTO projects (name) VALUES ('Website')", []).is_err());
+    }
+
+    #[test]
+    fn test_migrate_v31_marks_categories_rules_do_not_give_as_manual() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        conn.execute_batch(r#"
+            INSERT INTO categories (id, name, color) VALUES (10, 'Client work', '#0000ff');
+            INSERT INTO categories (id, name, color) VALUES (11, 'Reading', '#00ff00');
+            INSERT INTO rules (id, rule_type, pattern, category_id, priority) VALUES (500, 'app_name', 'Reviewboard', 10, 50);
+            INSERT INTO activities (app_name, started_at, ended_at, duration_sec, category_id) VALUES ('Reviewboard', 0, 60, 60, 10);
+            INSERT INTO activities (app_name, started_at, ended_at, duration_sec, category_id) VALUES ('Reviewboard', 60, 120, 60, 11);
+            INSERT INTO activities (app_name, started_at, ended_at, duration_sec, category_id) VALUES ('Wikipad', 120, 180, 60, -1);
+            INSERT INTO activities (app_name, started_at, ended_at, duration_sec, category_id) VALUES ('Wikipad', 180, 240, 60, 11);
+            UPDATE settings SET value = '30' WHERE key = 'schema_version';
+        "#).unwrap();
+
+        db.migrate(&conn).unwrap();
+
+        let sources: Vec<(String, Option<i64>)> = conn
+            .prepare("SELECT category_source, category_rule_id FROM activities ORDER BY started_at")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let source = |s: &str, rule: Option<i64>| (s.to_string(), rule);
+        assert_eq!(
+            sources,
+            vec![source("rule", Some(500)), source("manual", None), source("default", None), source("manual", None)]
+        );
+        assert_eq!(db.get_schema_version(&conn), LATEST_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_v32_lets_unflagged_categories_follow_their_parent() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        conn.execute_batch(r#"
+            INSERT INTO categories (id, name, color, is_billable) VALUES (10, 'Client A', '#0000ff', 1);
+            INSERT INTO categories (id, name, color, parent_id, is_billable) VALUES (11, 'Support', '#00ff00', 10, 0);
+            UPDATE settings SET value = '31' WHERE key = 'schema_version';
+        "#).unwrap();
+
+        db.migrate(&conn).unwrap();
+
+        let flags: Vec<Option<bool>> = conn
+            .prepare("SELECT is_billable FROM categories WHERE id IN (10, 11) ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(flags, vec![Some(true), None]);
+        assert_eq!(db.get_schema_version(&conn), LATEST_SCHEMA_VERSION);
+    }
+}
@@ -0,0 +1,366 @@
+//! Split, merge and trim of activities and manual entries
+//!
+//! Every edit runs in one transaction and refuses to make the row overlap
+//! another row of the same table. Rows keep their id for the latest part of
+//! the time they cover, so the session the tracker is still extending keeps
+//! growing the right row. Tags stay on every part, events follow the part
+//! whose span contains them, and notes stay on the row keeping its id.
+
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{Result, Transaction, params, params_from_iter};
+use super::common::{Database, invalid};
+use super::history::RecordedChange;
+
+/// Table of timed rows that can be edited
+struct Rows {
+    table: &'static str,
+    /// Singular name for messages
+    what: &'static str,
+    /// Columns that must be equal for rows to be merged, and the message if they are not
+    merge_key: &'static str,
+    merge_mismatch: &'static str,
+    /// Tag link table and its row column
+    tags: (&'static str, &'static str),
+    /// Activities also carry `duration_sec` and own events and notes
+    tracked: bool,
+}
+
+const ACTIVITY_ROWS: Rows = Rows {
+    table: "activities",
+    what: "activity",
+    merge_key: "app_name, is_idle, category_id, category_source, project_id",
+    merge_mismatch: "Only activities of the same app, category and project can be merged",
+    tags: ("activity_tags", "activity_id"),
+    tracked: true,
+};
+
+const MANUAL_ENTRY_ROWS: Rows = Rows {
+    table: "manual_entries",
+    what: "manual entry",
+    merge_key: "category_id, project_id",
+    merge_mismatch: "Only manual entries with the same category and project can be merged",
+    tags: ("manual_entry_tags", "manual_entry_id"),
+    tracked: false,
+};
+
+impl Database {
+    /// Split an activity at `at`. The activity keeps [at, end); a new activity with the
+    /// same details and tags gets [start, at). Returns the new activity's id.
+    pub fn split_activity(&self, id: i64, at: i64) -> Result<i64> {
+        self.split_row(&ACTIVITY_ROWS, id, at)
+    }
+
+    /// Merge activities of the same app, category and project into the latest of them,
+    /// which then covers everything from the first start to the last end. Nothing else
+    /// may be tracked in between. Returns the id of the merged activity.
+    pub fn merge_activities(&self, ids: &[i64]) -> Result<i64> {
+        self.merge_rows(&ACTIVITY_ROWS, ids)
+    }
+
+    /// Move the start and end of an activity; events outside the new span are detached
+    pub fn trim_activity(&self, id: i64, started_at: i64, ended_at: i64) -> Result<()> {
+        self.trim_row(&ACTIVITY_ROWS, id, started_at, ended_at)
+    }
+
+    /// Split a manual entry at `at`, like `split_activity`. Returns the new entry's id.
+    pub fn split_manual_entry(&self, id: i64, at: i64) -> Result<i64> {
+        self.split_row(&MANUAL_ENTRY_ROWS, id, at)
+    }
+
+    /// Merge manual entries with the same category and project, like `merge_activities`
+    pub fn merge_manual_entries(&self, ids: &[i64]) -> Result<i64> {
+        self.merge_rows(&MANUAL_ENTRY_ROWS, ids)
+    }
+
+    /// Move the start and end of a manual entry
+    pub fn trim_manual_entry(&self, id: i64, started_at: i64, ended_at: i64) -> Result<()> {
+        self.trim_row(&MANUAL_ENTRY_ROWS, id, started_at, ended_at)
+    }
+
+    fn split_row(&self, rows: &Rows, id: i64, at: i64) -> Result<i64> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, &format!("Split {} {}", rows.what, id))?;
+        let (started_at, ended_at) = span(&tx, rows, id)?;
+        if at <= started_at || at >= ended_at {
+            return Err(invalid(format!("The split point must be inside the {}", rows.what)));
+        }
+
+        let columns = copied_columns(&tx, rows)?;
+        let duration = if rows.tracked { ", duration_sec" } else { "" };
+        let new_duration = if rows.tracked { ", ?1 - started_at" } else { "" };
+        tx.execute(
+            &format!(
+                "INSERT INTO {table} ({columns}, started_at, ended_at{duration})
+                 SELECT {columns}, started_at, ?1{new_duration} FROM {table} WHERE id = ?2",
+                table = rows.table, columns = columns, duration = duration, new_duration = new_duration,
+            ),
+            params![at, id],
+        )?;
+        let new_id = tx.last_insert_rowid();
+        set_span(&tx, rows, id, at, ended_at)?;
+
+        let (tag_table, tag_column) = rows.tags;
+        tx.execute(
+            &format!(
+                "INSERT INTO {table} ({column}, tag_id) SELECT ?1, tag_id FROM {table} WHERE {column} = ?2",
+                table = tag_table, column = tag_column,
+            ),
+            params![new_id, id],
+        )?;
+        if rows.tracked {
+            tx.execute(
+                "UPDATE events SET activity_id = ?1 WHERE activity_id = ?2 AND started_at < ?3",
+                params![new_id, id, at],
+            )?;
+        }
+
+        change.finish()?;
+        tx.commit()?;
+        Ok(new_id)
+    }
+
+    fn merge_rows(&self, rows: &Rows, ids: &[i64]) -> Result<i64> {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() < 2 {
+            return Err(invalid("At least two rows are needed to merge".to_string()));
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, &format!("Merge {} {}", ids.len(), rows.table.replace('_', " ")))?;
+        let placeholders = vec!["?"; ids.len()].join(",");
+        let found: Vec<(i64, i64, i64, Vec<SqliteValue>)> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT id, started_at, ended_at, {key} FROM {table} WHERE id IN ({placeholders})
+                 ORDER BY started_at, id",
+                key = rows.merge_key, table = rows.table, placeholders = placeholders,
+            ))?;
+            let key_len = rows.merge_key.split(',').count();
+            let found = stmt.query_map(params_from_iter(ids.iter()), |row| {
+                let key = (0..key_len).map(|i| row.get(3 + i)).collect::<Result<Vec<SqliteValue>>>()?;
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, key))
+            })?;
+            found.collect::<Result<_>>()?
+        };
+        if found.len() != ids.len() {
+            return Err(invalid(format!("Some of the rows to merge do not exist in {}", rows.table)));
+        }
+        if found.windows(2).any(|pair| pair[0].3 != pair[1].3) {
+            return Err(invalid(rows.merge_mismatch.to_string()));
+        }
+
+        let started_at = found[0].1;
+        let ended_at = found.iter().map(|row| row.2).max().unwrap_or(started_at);
+        let survivor = found[found.len() - 1].0;
+        check_no_overlap(&tx, rows, &ids, started_at, ended_at)?;
+
+        let others: Vec<i64> = ids.iter().copied().filter(|id| *id != survivor).collect();
+        let others_list = others.iter().map(i64::to_string).collect::<Vec<_>>().join(",");
+        let (tag_table, tag_column) = rows.tags;
+        tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {table} ({column}, tag_id)
+                 SELECT ?1, tag_id FROM {table} WHERE {column} IN ({others})",
+                table = tag_table, column = tag_column, others = others_list,
+            ),
+            params![survivor],
+        )?;
+        if rows.tracked {
+            for table in ["events", "notes"] {
+                tx.execute(
+                    &format!("UPDATE {} SET activity_id = ?1 WHERE activity_id IN ({})", table, others_list),
+                    params![survivor],
+                )?;
+            }
+        }
+        tx.execute(&format!("DELETE FROM {} WHERE id IN ({})", rows.table, others_list), [])?;
+        set_span(&tx, rows, survivor, started_at, ended_at)?;
+
+        change.finish()?;
+        tx.commit()?;
+        Ok(survivor)
+    }
+
+    fn trim_row(&self, rows: &Rows, id: i64, started_at: i64, ended_at: i64) -> Result<()> {
+        if started_at >= ended_at {
+            return Err(invalid(format!("The {} must end after it starts", rows.what)));
+        }
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, &format!("Trim {} {}", rows.what, id))?;
+        span(&tx, rows, id)?;
+        check_no_overlap(&tx, rows, &[id], started_at, ended_at)?;
+        set_span(&tx, rows, id, started_at, ended_at)?;
+        if rows.tracked {
+            tx.execute(
+                "UPDATE events SET activity_id = NULL
+                 WHERE activity_id = ?1 AND (started_at >= ?3 OR ended_at <= ?2)",
+                params![id, started_at, ended_at],
+            )?;
+        }
+        change.finish()?;
+        tx.commit()
+    }
+}
+
+/// Columns copied to the new row on split: everything but the id and the span,
+/// including columns added by plugins
+fn copied_columns(tx: &Transaction, rows: &Rows) -> Result<String> {
+    let mut stmt = tx.prepare(
+        "SELECT name FROM pragma_table_info(?1)
+         WHERE name NOT IN ('id', 'started_at', 'ended_at', 'duration_sec')
+         ORDER BY cid",
+    )?;
+    let columns = stmt
+        .query_map(params![rows.table], |row| row.get::<_, String>(0))?
+        .map(|name| name.map(|name| format!("\"{}\"", name.replace('"', "\"\""))))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(columns.join(", "))
+}
+
+/// Current start and end of a row
+fn span(tx: &Transaction, rows: &Rows, id: i64) -> Result<(i64, i64)> {
+    tx.query_row(
+        &format!("SELECT started_at, ended_at FROM {} WHERE id = ?", rows.table),
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => invalid(format!("No {} with id {}", rows.what, id)),
+        e => e,
+    })
+}
+
+fn set_span(tx: &Transaction, rows: &Rows, id: i64, started_at: i64, ended_at: i64) -> Result<()> {
+    let duration = if rows.tracked { ", duration_sec = ?2 - ?1" } else { "" };
+    tx.execute(
+        &format!("UPDATE {} SET started_at = ?1, ended_at = ?2{} WHERE id = ?3", rows.table, duration),
+        params![started_at, ended_at, id],
+    )?;
+    Ok(())
+}
+
+/// Fail if a row other than `ids` overlaps [started_at, ended_at)
+fn check_no_overlap(tx: &Transaction, rows: &Rows, ids: &[i64], started_at: i64, ended_at: i64) -> Result<()> {
+    let mut values = vec![SqliteValue::Integer(started_at), SqliteValue::Integer(ended_at)];
+    values.extend(ids.iter().map(|id| SqliteValue::Integer(*id)));
+    let overlapping: Option<(i64, i64, i64)> = tx
+        .query_row(
+            &format!(
+                "SELECT id, started_at, ended_at FROM {} WHERE ended_at > ?1 AND started_at < ?2 AND id NOT IN ({})
+                 ORDER BY started_at LIMIT 1",
+                rows.table,
+                vec!["?"; ids.len()].join(","),
+            ),
+            params_from_iter(values.iter()),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    match overlapping {
+        Some((id, start, end)) => Err(invalid(format!(
+            "The {} would overlap {} {} ({}-{})",
+            rows.what, rows.what, id, start, end
+        ))),
+        None => Ok(()),
+    }
+}
+
+// Use OptionalExtension from common module
+use super::common::OptionalExtension;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+
+    fn activity(db: &Database, app: &str, start: i64, end: i64) -> i64 {
+        let id = db.start_activity(app, None, &WindowContext::default(), start).unwrap();
+        db.extend_activity(id, end).unwrap();
+        id
+    }
+
+    fn spans(db: &Database) -> Vec<(i64, i64, i64, i64)> {
+        db.get_activities(0, 100_000, None, None, None, None)
+            .unwrap()
+            .into_iter()
+            .map(|a| (a.id, a.started_at, a.ended_at, a.duration_sec))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_merge_and_trim_activities() {
+        let db = Database::open_in_memory().unwrap();
+        let code = activity(&db, "Code", 1_000, 8_200);
+        let browser = activity(&db, "Firefox", 8_200, 9_000);
+        let tag = db.create_tag("review", "#ff0000").unwrap();
+        db.tag_items(tag, &[code], &[]).unwrap();
+        db.set_activity_project(code, Some(db.create_project("Website", None, "#0000ff").unwrap())).unwrap();
+        // Columns added by plugins are copied too
+        db.conn
+            .lock()
+            .unwrap()
+            .execute_batch(&format!("ALTER TABLE activities ADD COLUMN jira_ticket TEXT; UPDATE activities SET jira_ticket = 'WEB-1' WHERE id = {code}"))
+            .unwrap();
+
+        // The earlier part is the new row and gets the same details and tags
+        let morning = db.split_activity(code, 4_000).unwrap();
+        assert_eq!(spans(&db), vec![(morning, 1_000, 4_000, 3_000), (code, 4_000, 8_200, 4_200), (browser, 8_200, 9_000, 800)]);
+        let (first, second) = (db.get_activity_by_id(morning).unwrap().unwrap(), db.get_activity_by_id(code).unwrap().unwrap());
+        assert_eq!((first.app_name.as_str(), first.category_id, first.project_id), ("Code", second.category_id, second.project_id));
+        assert_eq!(db.get_tag_links(0, 100_000).unwrap().activities, vec![(code, tag), (morning, tag)]);
+        assert!(db.split_activity(code, 8_200).is_err());
+        let ticket: Option<String> = db
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT jira_ticket FROM activities WHERE id = ?", params![morning], |row| row.get(0))
+            .unwrap();
+        assert_eq!(ticket.as_deref(), Some("WEB-1"));
+
+        // Only rows of the same app, and not across other activities
+        assert!(db.merge_activities(&[code, browser]).unwrap_err().to_string().contains("same app"));
+        let evening = activity(&db, "Code", 9_000, 9_600);
+        db.set_activity_project(evening, first.project_id).unwrap();
+        assert!(db.merge_activities(&[morning, evening]).unwrap_err().to_string().contains("overlap"));
+        db.add_activity_note(morning, "Planning").unwrap();
+        assert_eq!(db.merge_activities(&[code, morning]).unwrap(), code);
+        assert_eq!(spans(&db)[0], (code, 1_000, 8_200, 7_200));
+        assert_eq!(db.get_activity_notes(code).unwrap().len(), 1);
+        assert_eq!(db.get_tag_links(0, 100_000).unwrap().activities, vec![(code, tag)]);
+        // Nor rows of the same app with another category
+        let other = db.create_category_core("Side project", "#00ff00", None, None, 100, false, false).unwrap();
+        db.update_activity_category(evening, Some(other)).unwrap();
+        assert!(db.merge_activities(&[code, evening]).unwrap_err().to_string().contains("same app, category"));
+
+        assert!(db.trim_activity(code, 1_000, 8_500).unwrap_err().to_string().contains("overlap"));
+        assert!(db.trim_activity(code, 5_000, 5_000).is_err());
+        db.trim_activity(code, 2_000, 7_000).unwrap();
+        assert_eq!(spans(&db)[0], (code, 2_000, 7_000, 5_000));
+    }
+
+    #[test]
+    fn test_split_merge_and_trim_manual_entries() {
+        let db = Database::open_in_memory().unwrap();
+        let meeting = db.add_manual_entry(Some("Planning"), Some(1), 1_000, 4_600).unwrap();
+        let call = db.add_manual_entry(Some("Call"), Some(2), 4_600, 5_000).unwrap();
+
+        let first = db.split_manual_entry(meeting, 2_800).unwrap();
+        let entries = db.get_manual_entries(0, 100_000).unwrap();
+        let rows: Vec<_> = entries.iter().map(|e| (e.id, e.description.as_deref(), e.started_at, e.ended_at)).collect();
+        assert_eq!(
+            rows,
+            vec![(first, Some("Planning"), 1_000, 2_800), (meeting, Some("Planning"), 2_800, 4_600), (call, Some("Call"), 4_600, 5_000)]
+        );
+
+        assert!(db.merge_manual_entries(&[meeting, call]).unwrap_err().to_string().contains("same category"));
+        assert_eq!(db.merge_manual_entries(&[first, meeting]).unwrap(), meeting);
+        assert!(db.trim_manual_entry(meeting, 1_000, 4_700).is_err());
+        db.trim_manual_entry(meeting, 1_200, 4_600).unwrap();
+        let entries = db.get_manual_entries(0, 100_000).unwrap();
+        assert_eq!((entries[0].id, entries[0].started_at, entries[0].ended_at), (meeting, 1_200, 4_600));
+        assert_eq!(entries.len(), 2);
+    }
+}
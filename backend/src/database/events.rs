@@ -0,0 +1,122 @@
+//! Event database operations - Heartbeats reported by external watchers
+
+use rusqlite::{params, Connection, Result};
+use super::common::{Database, OptionalExtension, invalid};
+use super::models::Event;
+
+/// How far a heartbeat may lag behind the end of an activity and still be linked to it.
+/// The tracker extends the open activity once per poll, so its end trails real time.
+const ACTIVITY_LINK_SLACK_SECS: i64 = 10;
+
+impl Database {
+    /// Record a heartbeat from an external watcher.
+    ///
+    /// As in ActivityWatch, a heartbeat whose data equals the source's latest event and
+    /// that arrives no later than `pulsetime` seconds after that event ended extends it;
+    /// anything else starts a new event. Returns the event the heartbeat ended up in.
+    /// Negative durations and spans that overflow are rejected.
+    pub fn insert_heartbeat(
+        &self,
+        source: &str,
+        timestamp: i64,
+        duration: i64,
+        data: &serde_json::Value,
+        pulsetime: i64,
+    ) -> Result<Event> {
+        let heartbeat_end = Some(duration)
+            .filter(|duration| *duration >= 0)
+            .and_then(|duration| timestamp.checked_add(duration))
+            .ok_or_else(|| {
+                invalid(format!("Invalid heartbeat duration: {}", duration))
+            })?;
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+
+        let latest = tx
+            .query_row(
+                "SELECT id, source, started_at, ended_at, data, activity_id
+                 FROM events WHERE source = ? ORDER BY ended_at DESC, id DESC LIMIT 1",
+                params![source],
+                event_from_row,
+            )
+            .optional()?;
+
+        let event = match latest {
+            Some(mut event)
+                if event.data == *data
+                    && timestamp >= event.started_at
+                    && timestamp <= event.ended_at + pulsetime =>
+            {
+                event.ended_at = event.ended_at.max(heartbeat_end);
+                if event.activity_id.is_none() {
+                    event.activity_id = concurrent_activity(&tx, timestamp)?;
+                }
+                tx.execute(
+                    "UPDATE events SET ended_at = ?, activity_id = ? WHERE id = ?",
+                    params![event.ended_at, event.activity_id, event.id],
+                )?;
+                event
+            }
+            _ => {
+                let activity_id = concurrent_activity(&tx, timestamp)?;
+                tx.execute(
+                    "INSERT INTO events (source, started_at, ended_at, data, activity_id)
+                     VALUES (?, ?, ?, ?, ?)",
+                    params![source, timestamp, heartbeat_end, data.to_string(), activity_id],
+                )?;
+                Event {
+                    id: tx.last_insert_rowid(),
+                    source: source.to_string(),
+                    started_at: timestamp,
+                    ended_at: heartbeat_end,
+                    data: data.clone(),
+                    activity_id,
+                }
+            }
+        };
+
+        tx.commit()?;
+        Ok(event)
+    }
+
+    /// Get events overlapping a time range, optionally for a single source
+    pub fn get_events(&self, start: i64, end: i64, source: Option<&str>) -> Result<Vec<Event>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, source, started_at, ended_at, data, activity_id
+             FROM events
+             WHERE ended_at >= ?1 AND started_at <= ?2 AND (?3 IS NULL OR source = ?3)
+             ORDER BY started_at ASC, id ASC",
+        )?;
+
+        let events = stmt
+            .query_map(params![start, end, source], event_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(events)
+    }
+}
+
+fn event_from_row(row: &rusqlite::Row) -> Result<Event> {
+    let data: String = row.get(4)?;
+    Ok(Event {
+        id: row.get(0)?,
+        source: row.get(1)?,
+        started_at: row.get(2)?,
+        ended_at: row.get(3)?,
+        data: serde_json::from_str(&data).unwrap_or(serde_json::Value::Null),
+        activity_id: row.get(5)?,
+    })
+}
+
+/// Non-idle activity that was in focus at `timestamp`
+fn concurrent_activity(conn: &Connection, timestamp: i64) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM activities
+         WHERE is_idle = FALSE AND started_at <= ?1 AND ended_at + ?2 >= ?1
+         ORDER BY started_at DESC LIMIT 1",
+        params![timestamp, ACTIVITY_LINK_SLACK_SECS],
+        |row| row.get(0),
+    )
+    .optional()
+}
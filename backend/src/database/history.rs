@@ -0,0 +1,531 @@
+//! Edit history of user changes to tracked data
+//!
+//! Mutating operations on activities, manual entries, categories and rules
+//! open a change with `RecordedChange::begin` while they hold the connection.
+//! Triggers on the audited tables then store a JSON image of every row before
+//! and after each insert, update and delete, so the change can be undone.
+//! Tracker writes happen outside any open change and are not recorded. Bulk
+//! rewrites of a few columns record only those columns, without the triggers.
+
+use rusqlite::{Connection, Result, params};
+use std::collections::HashSet;
+use super::common::{Database, invalid};
+use super::models::Change;
+
+/// Tables whose rows are recorded; link tables, notes, events and rates follow
+/// the rows they belong to, so undoing a delete brings them back as well
+const AUDITED_TABLES: &[&str] = &[
+    "activities",
+    "manual_entries",
+    "categories",
+    "rules",
+    "category_rates",
+    "clients",
+    "projects",
+    "tags",
+    "running_timers",
+    "activity_tags",
+    "manual_entry_tags",
+    "notes",
+    "events",
+];
+
+/// Row image: column name to value
+type Image = serde_json::Map<String, serde_json::Value>;
+
+/// Changes kept; older ones are pruned when a new change is recorded
+pub const MAX_CHANGES: i64 = 100;
+
+/// Columns of a table
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    columns.collect()
+}
+
+/// Create the recording triggers for the current columns of the audited tables.
+/// The triggers list the columns that exist when they are created, so they must be
+/// created again after columns are added, as `apply_plugin_extensions` does. Being
+/// temporary, they are created by every connection that opens the database.
+pub(crate) fn install_change_triggers(conn: &Connection) -> Result<()> {
+    // A change left open by a crash must not swallow later tracker writes
+    conn.execute("UPDATE changes SET is_open = 0 WHERE is_open = 1", [])?;
+
+    for table in AUDITED_TABLES {
+        let columns = columns(conn, table)?;
+        let image = |row: &str| {
+            let fields = columns
+                .iter()
+                .map(|c| format!("'{c}', {row}.\"{c}\""))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("json_object({})", fields)
+        };
+        let (old, new) = (image("OLD"), image("NEW"));
+        conn.execute_batch(&format!(
+            "DROP TRIGGER IF EXISTS temp.record_{table}_insert;
+             DROP TRIGGER IF EXISTS temp.record_{table}_update;
+             DROP TRIGGER IF EXISTS temp.record_{table}_delete;
+
+             CREATE TEMP TRIGGER record_{table}_insert AFTER INSERT ON main.{table}
+             WHEN EXISTS (SELECT 1 FROM main.changes WHERE is_open = 1)
+             BEGIN
+                 INSERT INTO change_rows (change_id, table_name, row_id, before, after)
+                 SELECT MAX(id), '{table}', NEW.rowid, NULL, {new} FROM main.changes WHERE is_open = 1;
+             END;
+
+             CREATE TEMP TRIGGER record_{table}_update AFTER UPDATE ON main.{table}
+             WHEN EXISTS (SELECT 1 FROM main.changes WHERE is_open = 1) AND {old} IS NOT {new}
+             BEGIN
+                 INSERT INTO change_rows (change_id, table_name, row_id, before, after)
+                 SELECT MAX(id), '{table}', NEW.rowid, {old}, {new} FROM main.changes WHERE is_open = 1;
+             END;
+
+             CREATE TEMP TRIGGER record_{table}_delete AFTER DELETE ON main.{table}
+             WHEN EXISTS (SELECT 1 FROM main.changes WHERE is_open = 1)
+             BEGIN
+                 INSERT INTO change_rows (change_id, table_name, row_id, before, after)
+                 SELECT MAX(id), '{table}', OLD.rowid, {old}, NULL FROM main.changes WHERE is_open = 1;
+             END;"
+        ))?;
+    }
+    Ok(())
+}
+
+/// A change being recorded on a connection. Row changes made through the connection
+/// are recorded until `finish`; if the operation fails first, dropping the change closes it.
+pub(crate) struct RecordedChange<'a> {
+    conn: &'a Connection,
+    id: i64,
+    open: bool,
+}
+
+impl<'a> RecordedChange<'a> {
+    /// Start recording a change described by `summary`
+    pub(crate) fn begin(conn: &'a Connection, summary: &str) -> Result<Self> {
+        conn.execute(
+            "INSERT INTO changes (summary, created_at, is_open) VALUES (?, ?, 1)",
+            params![summary, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(Self { conn, id: conn.last_insert_rowid(), open: true })
+    }
+
+    /// Start recording a change whose rows the triggers leave alone, for rewrites of a
+    /// few columns of many rows where full row images would fill the history. Rows are
+    /// recorded with `record_update` instead.
+    pub(crate) fn begin_compact(conn: &'a Connection, summary: &str) -> Result<Self> {
+        conn.execute(
+            "INSERT INTO changes (summary, created_at, is_open) VALUES (?, ?, 0)",
+            params![summary, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(Self { conn, id: conn.last_insert_rowid(), open: true })
+    }
+
+    /// Record an update of a row, with images holding only the columns it set
+    pub(crate) fn record_update(
+        &self,
+        table: &str,
+        row_id: i64,
+        before: &serde_json::Value,
+        after: &serde_json::Value,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO change_rows (change_id, table_name, row_id, before, after) VALUES (?, ?, ?, ?, ?)",
+            params![self.id, table, row_id, before.to_string(), after.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Stop recording. A change that touched no rows is dropped, and only the
+    /// latest `MAX_CHANGES` changes are kept.
+    pub(crate) fn finish(mut self) -> Result<()> {
+        self.open = false;
+        close_change(self.conn, self.id)
+    }
+}
+
+impl Drop for RecordedChange<'_> {
+    fn drop(&mut self) {
+        if self.open {
+            let _ = close_change(self.conn, self.id);
+        }
+    }
+}
+
+fn close_change(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("UPDATE changes SET is_open = 0 WHERE id = ?", params![id])?;
+    conn.execute(
+        "DELETE FROM changes WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM change_rows WHERE change_id = ?1)",
+        params![id],
+    )?;
+    conn.execute(
+        "DELETE FROM change_rows WHERE change_id IN (
+             SELECT id FROM changes ORDER BY id DESC LIMIT -1 OFFSET ?
+         )",
+        params![MAX_CHANGES],
+    )?;
+    conn.execute(
+        "DELETE FROM changes WHERE id IN (SELECT id FROM changes ORDER BY id DESC LIMIT -1 OFFSET ?)",
+        params![MAX_CHANGES],
+    )?;
+    Ok(())
+}
+
+/// Parse a stored row image
+fn image(json: &str) -> Result<Image> {
+    match serde_json::from_str(json) {
+        Ok(serde_json::Value::Object(map)) => Ok(map),
+        _ => Err(invalid("Recorded row image is not a JSON object".to_string())),
+    }
+}
+
+/// Image of a row as it is now, built the way the triggers build them; None if it is gone
+fn current_image(conn: &Connection, table: &str, row_id: i64) -> Result<Option<Image>> {
+    let fields = columns(conn, table)?
+        .iter()
+        .map(|c| format!("'{c}', \"{c}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    conn.query_row(
+        &format!("SELECT json_object({}) FROM {} WHERE rowid = ?", fields, table),
+        params![row_id],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()?
+    .map(|json| image(&json))
+    .transpose()
+}
+
+/// First table with rows that refer to the row in `after` through a foreign key
+fn referencing_table(conn: &Connection, table: &str, after: &str) -> Result<Option<String>> {
+    let tables: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<Result<_>>()?
+    };
+    for referencing in tables {
+        let keys: Vec<(String, Option<String>)> = {
+            let mut stmt = conn.prepare("SELECT \"from\", \"to\" FROM pragma_foreign_key_list(?1) WHERE \"table\" = ?2")?;
+            let rows = stmt.query_map(params![referencing, table], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+        for (from, to) in keys {
+            // A key without a target column refers to the primary key
+            let to = to.unwrap_or_else(|| "id".to_string());
+            let referenced: bool = conn.query_row(
+                &format!(
+                    "SELECT EXISTS(SELECT 1 FROM {} WHERE \"{}\" = json_extract(?1, '$.\"{}\"'))",
+                    referencing, from, to
+                ),
+                params![after],
+                |row| row.get(0),
+            )?;
+            if referenced {
+                return Ok(Some(referencing));
+            }
+        }
+    }
+    Ok(None)
+}
+
+impl Database {
+    /// Recorded changes, most recent first
+    pub fn list_changes(&self, limit: i64) -> Result<Vec<Change>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.summary, c.created_at, c.undone_at, COUNT(r.id),
+                    COALESCE(GROUP_CONCAT(DISTINCT r.table_name), '')
+             FROM changes c
+             LEFT JOIN change_rows r ON r.change_id = c.id
+             WHERE c.is_open = 0
+             GROUP BY c.id
+             ORDER BY c.id DESC
+             LIMIT ?",
+        )?;
+        let changes = stmt
+            .query_map(params![limit], |row| {
+                let tables: String = row.get(5)?;
+                let mut tables: Vec<String> = tables.split(',').filter(|t| !t.is_empty()).map(str::to_string).collect();
+                tables.sort();
+                Ok(Change {
+                    id: row.get(0)?,
+                    summary: row.get(1)?,
+                    created_at: row.get(2)?,
+                    undone_at: row.get(3)?,
+                    rows: row.get(4)?,
+                    tables,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(changes)
+    }
+
+    /// Undo the most recent change that is not undone yet, in one transaction: rows it
+    /// inserted are deleted, rows it deleted come back and the columns it updated get
+    /// their previous values. Returns the undone change, or None if there is nothing to undo.
+    ///
+    /// The undo is refused if something not recorded changed the rows since: an inserted
+    /// row that is gone, differs or is referred to by other rows, a column the change set
+    /// that holds another value now, or a deleted row whose id is taken again.
+    pub fn undo_last_change(&self) -> Result<Option<Change>> {
+        let change_id = {
+            let mut conn = self.conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            // Rows come back in reverse order, so links may be restored before their rows
+            tx.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+            let Some(change_id) = tx
+                .query_row(
+                    "SELECT id FROM changes WHERE is_open = 0 AND undone_at IS NULL ORDER BY id DESC LIMIT 1",
+                    [],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()?
+            else {
+                return Ok(None);
+            };
+            let rows = {
+                let mut stmt = tx.prepare(
+                    "SELECT table_name, row_id, before, after FROM change_rows WHERE change_id = ? ORDER BY id DESC",
+                )?;
+                let rows = stmt.query_map(params![change_id], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                })?;
+                rows.collect::<Result<Vec<_>>>()?
+            };
+
+            for (table, row_id, before, after) in rows {
+                if !AUDITED_TABLES.contains(&table.as_str()) {
+                    return Err(invalid(format!("Cannot undo changes to table {}", table)));
+                }
+                let current: HashSet<String> = columns(&tx, &table)?.into_iter().collect();
+                // Columns to set back: all of them for a deleted row, the changed ones for an update
+                let restore = |before: &Image, after: Option<&Image>| {
+                    before
+                        .iter()
+                        .filter(|(column, value)| current.contains(*column) && after.is_none_or(|a| a.get(*column) != Some(value)))
+                        .map(|(column, _)| column.clone())
+                        .collect::<Vec<_>>()
+                };
+                let current_row = current_image(&tx, &table, row_id)?;
+                let conflict = || invalid(format!("Cannot undo: {} row {} has changed since", table, row_id));
+                match (before, after) {
+                    (None, after) => {
+                        let (Some(current_row), Some(after)) = (current_row, after) else {
+                            return Err(conflict());
+                        };
+                        if image(&after)?.iter().any(|(c, v)| current_row.get(c).is_some_and(|now| now != v)) {
+                            return Err(conflict());
+                        }
+                        if let Some(referencing) = referencing_table(&tx, &table, &after)? {
+                            return Err(invalid(format!(
+                                "Cannot undo: {} row {} is still used in {}",
+                                table, row_id, referencing
+                            )));
+                        }
+                        tx.execute(&format!("DELETE FROM {} WHERE rowid = ?", table), params![row_id])?;
+                    }
+                    (Some(before), None) => {
+                        if current_row.is_some() {
+                            return Err(conflict());
+                        }
+                        let restored = restore(&image(&before)?, None);
+                        tx.execute(
+                            &format!(
+                                "INSERT INTO {} ({}) SELECT {}",
+                                table,
+                                restored.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", "),
+                                restored.iter().map(|c| format!("json_extract(?1, '$.\"{}\"')", c)).collect::<Vec<_>>().join(", "),
+                            ),
+                            params![before],
+                        )?;
+                    }
+                    (Some(before), Some(after)) => {
+                        let after = image(&after)?;
+                        let restored = restore(&image(&before)?, Some(&after));
+                        if restored.is_empty() {
+                            continue;
+                        }
+                        // Only the columns the change set must still hold its values
+                        let Some(current_row) = current_row else {
+                            return Err(conflict());
+                        };
+                        if restored.iter().any(|c| current_row.get(c) != after.get(c)) {
+                            return Err(conflict());
+                        }
+                        tx.execute(
+                            &format!(
+                                "UPDATE {} SET {} WHERE rowid = ?2",
+                                table,
+                                restored.iter().map(|c| format!("\"{c}\" = json_extract(?1, '$.\"{c}\"')")).collect::<Vec<_>>().join(", "),
+                            ),
+                            params![before, row_id],
+                        )?;
+                    }
+                }
+            }
+
+            tx.execute(
+                "UPDATE changes SET undone_at = ? WHERE id = ?",
+                params![chrono::Utc::now().timestamp(), change_id],
+            )?;
+            tx.commit()?;
+            change_id
+        };
+        Ok(self.list_changes(MAX_CHANGES)?.into_iter().find(|c| c.id == change_id))
+    }
+}
+
+// Use OptionalExtension from common module
+use super::common::OptionalExtension;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+
+    #[test]
+    fn test_undo_changes() {
+        let db = Database::open_in_memory().unwrap();
+        assert!(db.undo_last_change().unwrap().is_none());
+        let client = db.create_category_core("Client work", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let rule = db.add_rule("app_name", "Reviewboard", client, 50).unwrap();
+        let review = db.start_activity("Reviewboard", Some("PR 7"), &WindowContext::default(), 1_000).unwrap();
+        let notes = db.start_activity("Wikipad", None, &WindowContext::default(), 2_000).unwrap();
+        // Tracker writes are not recorded
+        db.extend_activity(notes, 2_600).unwrap();
+        let tag = db.create_tag("billed", "#888888").unwrap();
+        db.tag_items(tag, &[review], &[]).unwrap();
+        db.add_activity_note(review, "Approved").unwrap();
+
+        db.update_activity_category(notes, Some(client)).unwrap();
+        db.delete_rule(rule).unwrap();
+        db.reapply_categorization_rules(&Default::default(), |_| {}).unwrap();
+        db.delete_activity(review).unwrap();
+
+        let changes = db.list_changes(10).unwrap();
+        let summaries: Vec<&str> = changes.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(
+            summaries,
+            vec![
+                &format!("Delete activity {}", review),
+                "Reapply categorization rules",
+                &format!("Delete rule {}", rule),
+                &format!("Change category of activity {}", notes),
+                &format!("Add note to activity {}", review),
+                &format!("Add tag {}", tag),
+                "Create tag billed",
+                "Add rule Reviewboard",
+                "Create category Client work",
+            ]
+        );
+        assert_eq!(changes[0].tables, vec!["activities", "activity_tags", "notes"]);
+
+        // The deleted activity comes back with its tag and note
+        let undone = db.undo_last_change().unwrap().unwrap();
+        assert!(undone.undone_at.is_some());
+        let restored = db.get_activity_by_id(review).unwrap().unwrap();
+        assert_eq!(restored.window_title.as_deref(), Some("PR 7"));
+        assert_ne!(restored.category_id, Some(client));
+        assert_eq!(db.get_tag_links(0, 10_000).unwrap().activities, vec![(review, tag)]);
+        assert_eq!(db.get_activity_notes(review).unwrap()[0].body, "Approved");
+
+        // Reapplication moved both activities out of the client category
+        db.undo_last_change().unwrap();
+        assert_eq!(db.get_activity_by_id(review).unwrap().unwrap().category_id, Some(client));
+        assert_eq!(db.get_activity_by_id(notes).unwrap().unwrap().category_id, Some(client));
+
+        db.undo_last_change().unwrap();
+        assert_eq!(db.get_rules().unwrap().iter().filter(|r| r.id == rule).count(), 1);
+        // Only the category column is restored; the tracker's later writes stay
+        db.extend_activity(notes, 2_900).unwrap();
+        db.undo_last_change().unwrap();
+        let notes_activity = db.get_activity_by_id(notes).unwrap().unwrap();
+        assert_ne!(notes_activity.category_id, Some(client));
+        assert_eq!(notes_activity.ended_at, 2_900);
+    }
+
+    #[test]
+    fn test_undo_projects_timers_and_conflicts() {
+        let db = Database::open_in_memory().unwrap();
+        let website = db.create_project("Website", None, "#0000ff").unwrap();
+        let activity = db.start_activity("Code", None, &WindowContext::default(), 1_000).unwrap();
+        db.set_activity_project(activity, Some(website)).unwrap();
+        db.delete_project(website).unwrap();
+        db.undo_last_change().unwrap();
+        assert_eq!(db.get_projects(true).unwrap().len(), 1);
+        assert_eq!(db.get_activity_by_id(activity).unwrap().unwrap().project_id, Some(website));
+
+        // Undoing a stop brings the timer back instead of the entry
+        let timer = db.start_timer("Client call", None, None, 2_000).unwrap();
+        db.stop_timer(timer, 2_600).unwrap();
+        db.undo_last_change().unwrap();
+        assert!(db.get_manual_entries(0, i64::MAX).unwrap().is_empty());
+        assert_eq!(db.get_running_timers().unwrap()[0].id, timer);
+
+        // A category created by the change is used by an activity now
+        let client = db.create_category_core("Client work", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let write = |sql: &str| db.conn.lock().unwrap().execute(sql, []).unwrap();
+        write(&format!("UPDATE activities SET category_id = {} WHERE id = {}", client, activity));
+        let err = db.undo_last_change().unwrap_err().to_string();
+        assert!(err.contains("still used in activities"), "{}", err);
+        write(&format!("UPDATE activities SET category_id = NULL WHERE id = {}", activity));
+
+        // A column the change set was changed again without being recorded
+        let tag = db.create_tag("billed", "#888888").unwrap();
+        db.update_tag(tag, "invoiced", "#888888").unwrap();
+        write(&format!("UPDATE tags SET name = 'paid' WHERE id = {}", tag));
+        assert!(db.undo_last_change().unwrap_err().to_string().contains("changed since"));
+        assert!(db.list_changes(1).unwrap()[0].undone_at.is_none());
+        write(&format!("UPDATE tags SET name = 'invoiced' WHERE id = {}", tag));
+        db.undo_last_change().unwrap();
+        assert_eq!(db.get_tags().unwrap()[0].name, "billed");
+    }
+
+    #[test]
+    fn test_plugin_columns_are_recorded() {
+        use crate::plugin_system::extensions::{EntityType, Extension, ExtensionRegistry, ExtensionType, SchemaChange};
+
+        let db = Database::open_in_memory().unwrap();
+        let registry = ExtensionRegistry::new();
+        registry
+            .register(Extension {
+                plugin_id: "jira".to_string(),
+                entity_type: EntityType::Activity,
+                extension_type: ExtensionType::DatabaseSchema,
+                schema_changes: vec![SchemaChange::AddColumn {
+                    table: "activities".to_string(),
+                    column: "jira_ticket".to_string(),
+                    column_type: "TEXT".to_string(),
+                    default: None,
+                    foreign_key: None,
+                }],
+                model_fields: vec![],
+                hook: None,
+                query_filters: vec![],
+            })
+            .unwrap();
+        db.apply_plugin_extensions(&registry).unwrap();
+
+        let id = db.start_activity("Reviewboard", None, &WindowContext::default(), 1_000).unwrap();
+        let ticket = |db: &Database| -> Option<String> {
+            db.conn
+                .lock()
+                .unwrap()
+                .query_row("SELECT jira_ticket FROM activities WHERE id = ?", params![id], |row| row.get(0))
+                .unwrap()
+        };
+        db.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE activities SET jira_ticket = 'WEB-7' WHERE id = ?", params![id])
+            .unwrap();
+        db.delete_activity(id).unwrap();
+        db.undo_last_change().unwrap();
+        assert_eq!(ticket(&db).as_deref(), Some("WEB-7"));
+    }
+}
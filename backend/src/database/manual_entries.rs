@@ -1,8 +1,40 @@
 //! Manual entry database operations
 
 use rusqlite::{Result, params};
-use super::common::Database;
-use super::models::ManualEntry;
+use super::common::{Database, OptionalExtension, invalid};
+use super::history::RecordedChange;
+use super::models::{IdleSegment, ManualEntry};
+
+/// Check that idle segments are well formed, stay within the idle interval
+/// `start..end` and do not overlap; returns them ordered by start
+fn validate_idle_segments(start: i64, end: i64, segments: &[IdleSegment]) -> Result<Vec<IdleSegment>, String> {
+    if segments.is_empty() {
+        return Err("At least one segment is required".to_string());
+    }
+    let mut sorted = segments.to_vec();
+    sorted.sort_by_key(|segment| segment.started_at);
+    for segment in &sorted {
+        if segment.started_at >= segment.ended_at {
+            return Err(format!(
+                "Segment {}-{} must start before it ends",
+                segment.started_at, segment.ended_at
+            ));
+        }
+        if segment.started_at < start || segment.ended_at > end {
+            return Err(format!(
+                "Segment {}-{} is outside the idle interval {}-{}",
+                segment.started_at, segment.ended_at, start, end
+            ));
+        }
+    }
+    if let Some(pair) = sorted.windows(2).find(|pair| pair[1].started_at < pair[0].ended_at) {
+        return Err(format!(
+            "Segments {}-{} and {}-{} overlap",
+            pair[0].started_at, pair[0].ended_at, pair[1].started_at, pair[1].ended_at
+        ));
+    }
+    Ok(sorted)
+}
 
 impl Database {
     /// Add a manual entry
@@ -14,19 +46,22 @@ impl Database {
         ended_at: i64,
     ) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, "Add manual entry")?;
         conn.execute(
             "INSERT INTO manual_entries (entry_type, description, category_id, started_at, ended_at)
              VALUES ('', ?, ?, ?, ?)",
             params![description, category_id, started_at, ended_at],
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
     }
 
     /// Get manual entries for a time range
     pub fn get_manual_entries(&self, start: i64, end: i64) -> Result<Vec<ManualEntry>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, description, category_id, started_at, ended_at
+            "SELECT id, description, category_id, project_id, started_at, ended_at
              FROM manual_entries
              WHERE started_at >= ? AND started_at <= ?
              ORDER BY started_at ASC",
@@ -38,8 +73,9 @@ impl Database {
                     id: row.get(0)?,
                     description: row.get(1)?,
                     category_id: row.get(2)?,
-                    started_at: row.get(3)?,
-                    ended_at: row.get(4)?,
+                    project_id: row.get(3)?,
+                    started_at: row.get(4)?,
+                    ended_at: row.get(5)?,
                 })
             })?
             .collect::<Result<Vec<_>>>()?;
@@ -47,6 +83,27 @@ impl Database {
         Ok(entries)
     }
 
+    /// Get manual entry by ID
+    pub fn get_manual_entry(&self, id: i64) -> Result<Option<ManualEntry>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, description, category_id, project_id, started_at, ended_at
+             FROM manual_entries WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(ManualEntry {
+                    id: row.get(0)?,
+                    description: row.get(1)?,
+                    category_id: row.get(2)?,
+                    project_id: row.get(3)?,
+                    started_at: row.get(4)?,
+                    ended_at: row.get(5)?,
+                })
+            },
+        )
+        .optional()
+    }
+
     /// Update manual entry
     pub fn update_manual_entry(
         &self,
@@ -57,19 +114,158 @@ impl Database {
         ended_at: i64,
     ) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Update manual entry {}", id))?;
         conn.execute(
             "UPDATE manual_entries SET entry_type = '', description = ?, category_id = ?, 
              started_at = ?, ended_at = ? WHERE id = ?",
             params![description, category_id, started_at, ended_at, id],
         )?;
-        Ok(())
+        change.finish()
     }
     
 
     /// Delete manual entry
     pub fn delete_manual_entry(&self, id: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Delete manual entry {}", id))?;
         conn.execute("DELETE FROM manual_entries WHERE id = ?", params![id])?;
-        Ok(())
+        change.finish()
+    }
+
+    /// Replace the idle record starting at `started_at` with one manual entry per segment,
+    /// in one transaction. Time the segments leave uncovered stays idle. The segments are
+    /// checked with `validate_idle_segments` against the record's bounds as read in the
+    /// same transaction.
+    pub fn split_idle_activity(&self, started_at: i64, segments: &[IdleSegment]) -> Result<Vec<ManualEntry>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, "Split idle time into manual entries")?;
+        let (idle_id, category_id, category_source, ended_at): (i64, Option<i64>, Option<String>, i64) = tx
+            .query_row(
+                "SELECT id, category_id, category_source, ended_at FROM activities WHERE is_idle = 1 AND started_at = ?",
+                params![started_at],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?
+            .ok_or_else(|| invalid("Idle period not found".to_string()))?;
+        let segments = validate_idle_segments(started_at, ended_at, segments).map_err(invalid)?;
+
+        tx.execute("UPDATE events SET activity_id = NULL WHERE activity_id = ?", params![idle_id])?;
+        tx.execute("DELETE FROM activities WHERE id = ?", params![idle_id])?;
+
+        let mut gap_start = started_at;
+        let mut entries = Vec::with_capacity(segments.len());
+        for segment in &segments {
+            insert_idle_gap(&tx, category_id, category_source.as_deref(), gap_start, segment.started_at)?;
+            gap_start = segment.ended_at;
+
+            tx.execute(
+                "INSERT INTO manual_entries (entry_type, description, category_id, project_id, started_at, ended_at)
+                 VALUES ('idle', ?, ?, ?, ?, ?)",
+                params![
+                    segment.description,
+                    segment.category_id,
+                    segment.project_id,
+                    segment.started_at,
+                    segment.ended_at
+                ],
+            )?;
+            entries.push(ManualEntry {
+                id: tx.last_insert_rowid(),
+                description: segment.description.clone(),
+                category_id: Some(segment.category_id),
+                project_id: segment.project_id,
+                started_at: segment.started_at,
+                ended_at: segment.ended_at,
+            });
+        }
+        insert_idle_gap(&tx, category_id, category_source.as_deref(), gap_start, ended_at)?;
+
+        change.finish()?;
+        tx.commit()?;
+        Ok(entries)
+    }
+}
+
+/// Keep `start..end` of a split idle record as idle, if it is not empty
+fn insert_idle_gap(
+    tx: &rusqlite::Transaction,
+    category_id: Option<i64>,
+    category_source: Option<&str>,
+    start: i64,
+    end: i64,
+) -> Result<()> {
+    if start < end {
+        tx.execute(
+            "INSERT INTO activities (app_name, category_id, category_source, started_at, ended_at, duration_sec, is_idle)
+             VALUES ('Idle', ?, ?, ?, ?, ?, TRUE)",
+            params![category_id, category_source, start, end, end - start],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(started_at: i64, ended_at: i64, category_id: i64, description: &str) -> IdleSegment {
+        IdleSegment {
+            started_at,
+            ended_at,
+            category_id,
+            description: Some(description.to_string()),
+            project_id: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_idle_segments() {
+        let sorted = validate_idle_segments(100, 400, &[segment(250, 400, 2, "Call"), segment(100, 250, 1, "Lunch")]).unwrap();
+        assert_eq!(sorted.iter().map(|s| s.started_at).collect::<Vec<_>>(), vec![100, 250]);
+
+        assert!(validate_idle_segments(100, 400, &[]).is_err());
+        assert!(validate_idle_segments(100, 400, &[segment(200, 200, 1, "Empty")]).is_err());
+        assert!(validate_idle_segments(100, 400, &[segment(50, 200, 1, "Early")]).is_err());
+        assert!(validate_idle_segments(100, 400, &[segment(300, 401, 1, "Late")]).is_err());
+        let err = validate_idle_segments(100, 400, &[segment(100, 250, 1, "Lunch"), segment(240, 300, 2, "Call")]).unwrap_err();
+        assert!(err.contains("overlap"));
+    }
+
+    #[test]
+    fn test_split_idle_activity() {
+        let db = Database::open_in_memory().unwrap();
+        let start = db.record_idle_start(1_000, None).unwrap();
+        db.update_idle_duration(start, 3_600).unwrap();
+
+        // Meeting, then lunch; the last ten minutes are left unclassified
+        let offsite = db.create_project("Team offsite", None, "#00aa00").unwrap();
+        let mut lunch = segment(2_200, 4_000, 1, "Lunch");
+        lunch.project_id = Some(offsite);
+        // Segments are checked against the idle record inside the transaction
+        let err = db.split_idle_activity(start, &[segment(900, 1_100, 2, "Early")]).unwrap_err();
+        assert!(err.to_string().contains("outside the idle interval"));
+        let segments = [lunch, segment(1_000, 2_200, 2, "Meeting")];
+        let entries = db.split_idle_activity(start, &segments).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let stored = db.get_manual_entries(0, i64::MAX).unwrap();
+        let spans: Vec<_> = stored
+            .iter()
+            .map(|e| (e.started_at, e.ended_at, e.category_id, e.description.as_deref(), e.project_id))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (1_000, 2_200, Some(2), Some("Meeting"), None),
+                (2_200, 4_000, Some(1), Some("Lunch"), Some(offsite)),
+            ]
+        );
+
+        let activities = db.get_activities(0, i64::MAX, None, None, None, None).unwrap();
+        let idle: Vec<_> = activities.iter().map(|a| (a.is_idle, a.started_at, a.ended_at)).collect();
+        assert_eq!(idle, vec![(true, 4_000, 4_600)]);
+        let err = db.split_idle_activity(start, &segments).unwrap_err();
+        assert!(err.to_string().contains("not found"));
     }
 }
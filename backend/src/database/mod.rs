@@ -10,6 +10,17 @@
 //! - settings: Settings operations
 //! - stats: Statistics and reporting operations
 //! - plugins: Plugin management operations
+//! - events: Heartbeat events from external watchers
+//! - privacy: Retroactive privacy filtering of stored activities
+//! - schedule: Work-hours schedule windows and exceptions
+//! - timers: Running timers
+//! - projects: Clients and projects
+//! - tags: Tags and their links to activities and manual entries
+//! - billing: Category rates, rounding and billed time
+//! - notes: Notes on activities and time spans
+//! - editing: Split, merge and trim of activities and manual entries
+//! - bulk: Bulk edits of activities matching a filter
+//! - history: Edit history and undo of user changes
 //!
 
 pub mod models;
@@ -22,9 +33,21 @@ pub mod settings;
 pub mod stats;
 pub mod plugins;
 pub mod plugin_tables;
+pub mod events;
+pub mod privacy;
+pub mod schedule;
+pub mod timers;
+pub mod projects;
+pub mod tags;
+pub mod billing;
+pub mod notes;
+pub mod editing;
+pub mod bulk;
+pub mod history;
 
 // Re-export Database and constants
 pub use common::Database;
+pub(crate) use activities::matches_pattern;
 
 // Re-export all models
 pub use models::*;
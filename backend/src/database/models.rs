@@ -7,12 +7,81 @@ pub struct Activity {
     pub app_name: String,
     pub window_title: Option<String>,
     pub domain: Option<String>,
+    /// Full URL of the active browser tab, reported by the native messaging host
+    pub url: Option<String>,
+    /// Project recognised in an editor window title
+    pub project: Option<String>,
+    /// File open in the editor
+    pub document: Option<String>,
+    /// Executable of the process owning the window
+    pub exe_path: Option<String>,
+    pub cmdline: Option<String>,
+    /// Working directory of the process (of the foreground program for terminals)
+    pub cwd: Option<String>,
+    /// Root of the git repository enclosing `cwd`
+    pub git_repo: Option<String>,
+    pub git_branch: Option<String>,
+    /// Why the activity counted as active without input: `media:<player>`, `inhibit` or `app:<name>`
+    pub suppression_reason: Option<String>,
     pub category_id: Option<i64>,
+    /// Where the category came from: `rule`, `manual`, `plugin` or `default`;
+    /// None for activities recorded before sources were tracked
+    pub category_source: Option<String>,
+    /// Rule that set the category, when the source is `rule`
+    pub category_rule_id: Option<i64>,
+    pub project_id: Option<i64>,
     pub started_at: i64,
+    /// End of the focus session; duration_sec always equals ended_at - started_at
+    pub ended_at: i64,
     pub duration_sec: i64,
     pub is_idle: bool,
 }
 
+/// Details the tracker derived from a focused window when opening a session
+#[derive(Debug, Clone, Default)]
+pub struct WindowContext {
+    pub domain: Option<String>,
+    pub url: Option<String>,
+    pub project: Option<String>,
+    pub document: Option<String>,
+    pub exe_path: Option<String>,
+    pub cmdline: Option<String>,
+    pub cwd: Option<String>,
+    pub git_repo: Option<String>,
+    pub git_branch: Option<String>,
+}
+
+impl From<&Activity> for WindowContext {
+    fn from(activity: &Activity) -> Self {
+        Self {
+            domain: activity.domain.clone(),
+            url: activity.url.clone(),
+            project: activity.project.clone(),
+            document: activity.document.clone(),
+            exe_path: activity.exe_path.clone(),
+            cmdline: activity.cmdline.clone(),
+            cwd: activity.cwd.clone(),
+            git_repo: activity.git_repo.clone(),
+            git_branch: activity.git_branch.clone(),
+        }
+    }
+}
+
+/// Result of applying the privacy filter to stored activities
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PurgeSummary {
+    /// Activities of ignored apps and private windows
+    pub deleted: usize,
+    /// Activities of masked apps whose titles were hidden
+    pub masked: usize,
+    /// Activities whose title, URL or command line matched a redaction
+    pub redacted: usize,
+    /// Heartbeat events of ignored apps and incognito tabs
+    pub events_deleted: usize,
+    /// Heartbeat events that were masked or redacted
+    pub events_filtered: usize,
+}
+
 /// Category record
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Category {
@@ -24,6 +93,72 @@ pub struct Category {
     pub sort_order: i64,
     pub is_system: bool,
     pub is_pinned: bool,
+    /// Category this one is nested under; rules still assign the category itself
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    /// Time in this category is billed at its hourly rate; None follows the parent
+    #[serde(default)]
+    pub is_billable: Option<bool>,
+}
+
+/// Free-text note on an activity or on a time span
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Note {
+    pub id: i64,
+    /// Set for notes on an activity; the span is then the activity's
+    pub activity_id: Option<i64>,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub body: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Hourly rate of a category, in effect from `effective_from` until the next rate of the category
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CategoryRate {
+    pub id: i64,
+    pub category_id: i64,
+    pub hourly_rate: f64,
+    pub currency: String,
+    pub effective_from: i64,
+}
+
+/// How billed time is rounded up
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BillingRounding {
+    /// Increment in minutes; 0 bills exact seconds
+    pub increment_minutes: i64,
+    /// Round each day's total per category and rate instead of every activity
+    pub per_day: bool,
+}
+
+/// Billable time of an activity or manual entry at one rate, with its rounded time.
+/// Time crossing the start of a new rate is split into one row per rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BilledActivity {
+    pub activity_id: Option<i64>,
+    pub manual_entry_id: Option<i64>,
+    pub category_id: i64,
+    pub started_at: i64,
+    pub seconds: i64,
+    pub billed_seconds: i64,
+    /// None if neither the category nor a parent has a rate in effect at `started_at`
+    pub hourly_rate: Option<f64>,
+    pub currency: Option<String>,
+    pub amount: f64,
+}
+
+/// Billed time and amount of a category at one rate
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Earning {
+    pub category_id: i64,
+    pub category_name: String,
+    pub hourly_rate: Option<f64>,
+    pub currency: Option<String>,
+    pub seconds: i64,
+    pub billed_seconds: i64,
+    pub amount: f64,
 }
 
 /// Rule for auto-categorization
@@ -42,8 +177,199 @@ pub struct ManualEntry {
     pub id: i64,
     pub description: Option<String>,
     pub category_id: Option<i64>,
+    #[serde(default)]
+    pub project_id: Option<i64>,
+    pub started_at: i64,
+    pub ended_at: i64,
+}
+
+/// Filters for listing activities; empty lists do not filter
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ActivityFilter {
+    #[serde(default)]
+    pub exclude_idle: bool,
+    #[serde(default)]
+    pub category_ids: Vec<i64>,
+    /// Only activities carrying at least one of these tags
+    #[serde(default)]
+    pub include_tags: Vec<i64>,
+    /// Leave out activities carrying any of these tags
+    #[serde(default)]
+    pub exclude_tags: Vec<i64>,
+    /// Only idle records
+    #[serde(default)]
+    pub only_idle: bool,
+    /// App name, window title and domain patterns, with wildcards as in rules
+    #[serde(default)]
+    pub app_pattern: Option<String>,
+    #[serde(default)]
+    pub title_pattern: Option<String>,
+    #[serde(default)]
+    pub domain_pattern: Option<String>,
+}
+
+/// Which activities `reapply_categorization_rules` recategorizes
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct ReapplyOptions {
+    /// Only activities starting in [start, end]
+    #[serde(default)]
+    pub start: Option<i64>,
+    #[serde(default)]
+    pub end: Option<i64>,
+    /// Only recategorize activities where one of these rules wins over all the others
+    #[serde(default)]
+    pub rule_ids: Option<Vec<i64>>,
+    /// Also recategorize activities whose category was set by hand
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Progress of a rule reapplication
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ReapplyProgress {
+    /// Activities looked at so far, out of `total`
+    pub processed: i64,
+    pub total: i64,
+    pub updated: i64,
+    /// Activities left alone because their category was set by hand
+    pub skipped_manual: i64,
+}
+
+/// A recorded user change to tracked data
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Change {
+    pub id: i64,
+    pub summary: String,
+    pub created_at: i64,
+    pub undone_at: Option<i64>,
+    /// Row changes recorded, including tags, notes and events that followed the rows
+    pub rows: i64,
+    pub tables: Vec<String>,
+}
+
+/// Change applied to every activity matching a filter
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkAction {
+    SetCategory { category_id: Option<i64> },
+    SetProject { project_id: Option<i64> },
+    AddTags { tag_ids: Vec<i64> },
+    RemoveTags { tag_ids: Vec<i64> },
+    SetIdle { is_idle: bool },
+    Delete,
+}
+
+/// What a bulk action did, or would do in a dry run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BulkResult {
+    pub dry_run: bool,
+    /// Activities matching the filter
+    pub matched: usize,
+    /// Activities or tag links the action changed
+    pub affected: usize,
+    pub matched_duration_sec: i64,
+    /// First matching activities, as they were before the action
+    pub preview: Vec<Activity>,
+}
+
+/// Free-form label on activities and manual entries
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+}
+
+/// Tags of the rows in a range, as (activity or manual entry id, tag id) pairs
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TagLinks {
+    pub activities: Vec<(i64, i64)>,
+    pub manual_entries: Vec<(i64, i64)>,
+}
+
+/// Time carrying a tag in a range
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TagUsageStat {
+    pub tag: Tag,
+    pub duration_sec: i64,
+}
+
+/// Client that projects belong to
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Client {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+    pub archived: bool,
+}
+
+/// Project that activities and manual entries can be assigned to
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Project {
+    pub id: i64,
+    pub name: String,
+    pub client_id: Option<i64>,
+    pub color: String,
+    pub archived: bool,
+}
+
+/// Part of an idle period classified from the idle-return prompt
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IdleSegment {
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub category_id: i64,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<i64>,
+}
+
+/// Weekly interval in which tracking runs when the schedule is enabled
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScheduleWindow {
+    pub id: i64,
+    /// 0 = Monday ... 6 = Sunday
+    pub weekday: i64,
+    /// Minutes since local midnight; the window covers [start_minute, end_minute)
+    pub start_minute: i64,
+    pub end_minute: i64,
+}
+
+/// Date on which the weekly windows do not apply
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScheduleException {
+    pub id: i64,
+    /// Local date, YYYY-MM-DD
+    pub date: String,
+    /// "holiday": no tracking all day; "workday": tracking in the given hours instead
+    pub kind: String,
+    pub start_minute: Option<i64>,
+    pub end_minute: Option<i64>,
+    pub label: Option<String>,
+}
+
+/// Timer that becomes a manual entry when stopped; stored so it keeps running across restarts
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RunningTimer {
+    pub id: i64,
+    /// Unique among running timers
+    pub name: String,
+    pub category_id: Option<i64>,
+    pub description: Option<String>,
+    pub started_at: i64,
+}
+
+/// Event reported by an external watcher, merged from its heartbeats
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Event {
+    pub id: i64,
+    pub source: String,
     pub started_at: i64,
     pub ended_at: i64,
+    /// Watcher-defined payload (file, project, git branch, ...)
+    pub data: serde_json::Value,
+    /// Activity that was in focus when the event started
+    pub activity_id: Option<i64>,
 }
 
 /// Domain statistics
@@ -53,6 +379,13 @@ pub struct DomainStat {
     pub duration_sec: i64,
 }
 
+/// Project statistics
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProjectStat {
+    pub project: String,
+    pub duration_sec: i64,
+}
+
 /// Daily statistics
 #[derive(Debug, Clone)]
 pub struct DailyStats {
@@ -60,6 +393,7 @@ pub struct DailyStats {
     pub productive_seconds: i64,
     pub category_stats: Vec<CategoryStat>,
     pub app_stats: Vec<AppStat>,
+    pub category_tree: Vec<CategoryTreeNode>,
 }
 
 /// Category statistics
@@ -78,12 +412,28 @@ pub struct AppStat {
     pub category: Option<Category>,
 }
 
-/// Category usage statistics
+/// Category usage statistics, nested by parent category
 #[derive(Debug, Clone)]
 pub struct CategoryUsageStat {
-    pub category: Option<Category>,
+    pub category: Category,
+    /// Time assigned to the category itself
     pub duration_sec: i64,
+    /// Share of all time in the range, including subcategories
     pub percentage: i64,
+    /// Time of the category and all categories nested under it
+    pub total_duration_sec: i64,
+    pub children: Vec<CategoryUsageStat>,
+}
+
+/// Category with its own time and the time rolled up from nested categories
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CategoryTreeNode {
+    pub category_id: i64,
+    pub name: String,
+    pub color: String,
+    pub self_seconds: i64,
+    pub total_seconds: i64,
+    pub children: Vec<CategoryTreeNode>,
 }
 
 /// Hourly statistics
@@ -102,4 +452,10 @@ pub struct RangeStats {
     pub category_breakdown: Vec<(i64, String, String, i64)>,
     /// (app_name, seconds)
     pub app_breakdown: Vec<(String, i64)>,
+    /// Categories nested by parent, with self and rolled-up totals
+    pub category_tree: Vec<CategoryTreeNode>,
+    /// Billable time and amounts per category and rate
+    pub earnings: Vec<Earning>,
+    /// (project_id, project_name, color, seconds)
+    pub project_breakdown: Vec<(i64, String, String, i64)>,
 }
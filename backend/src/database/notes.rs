@@ -0,0 +1,194 @@
+//! Note database operations
+//!
+//! A note annotates either one activity, taking the activity's span, or an
+//! arbitrary [started_at, ended_at) span. Notes on deleted activities are
+//! removed by a trigger.
+
+use rusqlite::{Result, params};
+use super::common::Database;
+use super::history::RecordedChange;
+use super::models::Note;
+
+/// Note columns with the span resolved through the activity; needs `notes n LEFT JOIN activities a`
+const NOTE_COLUMNS: &str = "n.id, n.activity_id, COALESCE(a.started_at, n.started_at), \
+     COALESCE(a.ended_at, n.ended_at), n.body, n.created_at, n.updated_at";
+
+fn note_from_row(row: &rusqlite::Row) -> Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        activity_id: row.get(1)?,
+        started_at: row.get(2)?,
+        ended_at: row.get(3)?,
+        body: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+impl Database {
+    /// Add a note to an activity
+    pub fn add_activity_note(&self, activity_id: i64, body: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Add note to activity {}", activity_id))?;
+        let now = chrono::Utc::now().timestamp();
+        let inserted = conn.execute(
+            "INSERT INTO notes (activity_id, body, created_at, updated_at)
+             SELECT id, ?2, ?3, ?3 FROM activities WHERE id = ?1",
+            params![activity_id, body, now],
+        )?;
+        if inserted == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
+    }
+
+    /// Add a note to the span [started_at, ended_at)
+    pub fn add_span_note(&self, started_at: i64, ended_at: i64, body: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, "Add note")?;
+        let now = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO notes (started_at, ended_at, body, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+            params![started_at, ended_at, body, now, now],
+        )?;
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
+    }
+
+    /// Replace the text of a note
+    pub fn update_note(&self, id: i64, body: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Update note {}", id))?;
+        conn.execute(
+            "UPDATE notes SET body = ?, updated_at = ? WHERE id = ?",
+            params![body, chrono::Utc::now().timestamp(), id],
+        )?;
+        change.finish()
+    }
+
+    /// Delete a note
+    pub fn delete_note(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Delete note {}", id))?;
+        conn.execute("DELETE FROM notes WHERE id = ?", params![id])?;
+        change.finish()
+    }
+
+    /// Get a note by id
+    pub fn get_note(&self, id: i64) -> Result<Option<Note>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM notes n LEFT JOIN activities a ON a.id = n.activity_id WHERE n.id = ?",
+                NOTE_COLUMNS
+            ),
+            params![id],
+            note_from_row,
+        )
+        .optional()
+    }
+
+    /// Notes whose span overlaps [start, end), in chronological order
+    pub fn get_notes(&self, start: i64, end: i64) -> Result<Vec<Note>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notes n LEFT JOIN activities a ON a.id = n.activity_id
+             WHERE COALESCE(a.ended_at, n.ended_at) > ?1 AND COALESCE(a.started_at, n.started_at) < ?2
+             ORDER BY 3, n.id",
+            NOTE_COLUMNS
+        ))?;
+        let notes = stmt
+            .query_map(params![start, end], note_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(notes)
+    }
+
+    /// Notes attached to an activity, oldest first
+    pub fn get_activity_notes(&self, activity_id: i64) -> Result<Vec<Note>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notes n LEFT JOIN activities a ON a.id = n.activity_id
+             WHERE n.activity_id = ?
+             ORDER BY n.id",
+            NOTE_COLUMNS
+        ))?;
+        let notes = stmt
+            .query_map(params![activity_id], note_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(notes)
+    }
+
+    /// Notes containing `query` (case-insensitive), most recent span first
+    pub fn search_notes(&self, query: &str, limit: i64) -> Result<Vec<Note>> {
+        let conn = self.conn.lock().unwrap();
+        let pattern = format!(
+            "%{}%",
+            query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notes n LEFT JOIN activities a ON a.id = n.activity_id
+             WHERE n.body LIKE ? ESCAPE '\\'
+             ORDER BY 3 DESC, n.id DESC
+             LIMIT ?",
+            NOTE_COLUMNS
+        ))?;
+        let notes = stmt
+            .query_map(params![pattern, limit], note_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(notes)
+    }
+}
+
+// Use OptionalExtension from common module
+use super::common::OptionalExtension;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+
+    #[test]
+    fn test_activity_and_span_notes() {
+        let db = Database::open_in_memory().unwrap();
+        let code = db.start_activity("Code", Some("main.rs"), &WindowContext::default(), 1_000).unwrap();
+        let span = db.add_span_note(1_500, 2_500, "Pairing with Ana on 50% of the parser").unwrap();
+        let note = db.add_activity_note(code, "Fixed the parser bug").unwrap();
+        assert!(db.add_activity_note(9_999, "nothing to attach to").is_err());
+
+        // Activity notes follow the activity's span
+        db.extend_activity(code, 1_600).unwrap();
+        let notes = db.get_notes(1_550, 1_560).unwrap();
+        assert_eq!(notes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![note, span]);
+        assert_eq!((notes[0].started_at, notes[0].ended_at), (1_000, 1_600));
+        assert!(db.get_notes(2_500, 3_000).unwrap().is_empty());
+
+        let found = |query: &str| -> Vec<i64> { db.search_notes(query, 10).unwrap().iter().map(|n| n.id).collect() };
+        assert_eq!(found("PARSER"), vec![span, note]);
+        assert_eq!(found("50%"), vec![span]);
+        assert!(found("5_").is_empty());
+
+        db.update_note(note, "Fixed the lexer bug").unwrap();
+        assert_eq!(db.get_note(note).unwrap().unwrap().body, "Fixed the lexer bug");
+        db.delete_activity(code).unwrap();
+        assert!(db.get_note(note).unwrap().is_none());
+        db.delete_note(span).unwrap();
+        assert!(db.get_notes(0, 10_000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_idle_description_is_a_note() {
+        let db = Database::open_in_memory().unwrap();
+        db.record_idle_start(2_000, None).unwrap();
+        db.update_idle_duration(2_000, 300).unwrap();
+        db.update_idle_activity(2_000, 1, Some("Whiteboard session")).unwrap();
+        db.update_idle_activity(2_000, 1, Some("Whiteboard session with the team")).unwrap();
+
+        let idle = db.get_activities(0, 10_000, None, None, None, None).unwrap().remove(0);
+        assert_eq!(idle.window_title, None);
+        let notes = db.get_activity_notes(idle.id).unwrap();
+        assert_eq!(notes.iter().map(|n| n.body.as_str()).collect::<Vec<_>>(), vec!["Whiteboard session with the team"]);
+    }
+}
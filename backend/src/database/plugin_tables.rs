@@ -2,6 +2,7 @@
 //! Table and column names are validated (alphanumeric + underscore only).
 
 use super::common::{Database, OptionalExtension};
+use super::history::RecordedChange;
 use rusqlite::params;
 use rusqlite::types::Value as SqliteValue;
 
@@ -125,9 +126,12 @@ impl Database {
             .map(json_to_sqlite_value)
             .collect();
         param_values.push(SqliteValue::Integer(id));
+        let change = RecordedChange::begin(&conn, &format!("Update plugin fields of category {}", id))
+            .map_err(|e| e.to_string())?;
         let n = conn
             .execute(&sql, rusqlite::params_from_iter(param_values.iter()))
             .map_err(|e| e.to_string())?;
+        change.finish().map_err(|e| e.to_string())?;
         Ok(n)
     }
 
@@ -284,6 +284,11 @@ impl Database {
         }
         
         tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+        // The edit history triggers list the columns they record; rebuild them so
+        // columns added above are recorded and restored too
+        super::history::install_change_triggers(&conn)
+            .map_err(|e| format!("Failed to reinstall change triggers: {}", e))?;
         Ok(())
     }
 }
@@ -0,0 +1,268 @@
+//! Retroactive privacy filtering of stored activities
+
+use rusqlite::{params, Result};
+use std::collections::HashSet;
+
+use super::activities::{activity_from_row, ACTIVITY_COLUMNS};
+use super::common::Database;
+use super::models::PurgeSummary;
+use crate::privacy::{PrivacyDecision, PrivacyFilter, MASKED_TITLE};
+
+/// Whether a recorded image of an activity or event holds data the filter would remove
+fn image_is_filtered(filter: &PrivacyFilter, table: &str, image: &serde_json::Value) -> bool {
+    let text = |key: &str| image.get(key).and_then(|v| v.as_str());
+    match table {
+        "activities" if image.get("is_idle").and_then(|v| v.as_i64()) != Some(1) => {
+            match filter.decide(text("app_name").unwrap_or_default(), text("window_title")) {
+                PrivacyDecision::Ignore => true,
+                PrivacyDecision::Mask => {
+                    text("window_title") != Some(MASKED_TITLE)
+                        || ["url", "cmdline", "exe_path", "cwd", "git_repo", "git_branch"]
+                            .iter()
+                            .any(|key| text(key).is_some())
+                }
+                PrivacyDecision::Keep => ["window_title", "url", "project", "document", "cmdline"]
+                    .iter()
+                    .filter_map(|key| text(key))
+                    .any(|t| filter.redact(t) != t),
+            }
+        }
+        "events" => {
+            let data: serde_json::Value = text("data").and_then(|d| serde_json::from_str(d).ok()).unwrap_or_default();
+            filter.filter_event_data(text("source").unwrap_or_default(), &data).as_ref() != Some(&data)
+        }
+        _ => false,
+    }
+}
+
+impl Database {
+    /// Apply a privacy filter to everything recorded so far, in one transaction:
+    /// activities of ignored apps and private windows are deleted, titles of masked apps
+    /// are hidden and redactions are applied to titles, URLs and command lines.
+    /// Idle rows are left alone. Heartbeat events are dropped, masked or redacted the
+    /// same way.
+    ///
+    /// The purge is not recorded in the edit history, since undoing it would bring the
+    /// data back. Recorded changes holding images of rows the filter removes data from
+    /// are dropped from the history for the same reason.
+    pub fn purge_activities(&self, filter: &PrivacyFilter) -> Result<PurgeSummary> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        let mut summary = PurgeSummary::default();
+
+        let activities = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {} FROM activities WHERE is_idle = FALSE",
+                ACTIVITY_COLUMNS
+            ))?;
+            let rows = stmt.query_map([], activity_from_row)?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        for activity in activities {
+            match filter.decide(&activity.app_name, activity.window_title.as_deref()) {
+                PrivacyDecision::Ignore => {
+                    tx.execute("DELETE FROM activities WHERE id = ?", params![activity.id])?;
+                    tx.execute("UPDATE events SET activity_id = NULL WHERE activity_id = ?", params![activity.id])?;
+                    summary.deleted += 1;
+                }
+                PrivacyDecision::Mask => {
+                    let already_masked = activity.window_title.as_deref() == Some(MASKED_TITLE)
+                        && activity.url.is_none()
+                        && activity.cmdline.is_none()
+                        && activity.exe_path.is_none()
+                        && activity.cwd.is_none()
+                        && activity.git_repo.is_none();
+                    if !already_masked {
+                        tx.execute(
+                            "UPDATE activities
+                             SET window_title = ?, domain = NULL, url = NULL, project = NULL, document = NULL, cmdline = NULL,
+                                 exe_path = NULL, cwd = NULL, git_repo = NULL, git_branch = NULL
+                             WHERE id = ?",
+                            params![MASKED_TITLE, activity.id],
+                        )?;
+                        summary.masked += 1;
+                    }
+                }
+                PrivacyDecision::Keep => {
+                    let redact = |text: &Option<String>| text.as_deref().map(|t| filter.redact(t));
+                    let redacted = (
+                        redact(&activity.window_title),
+                        redact(&activity.url),
+                        redact(&activity.project),
+                        redact(&activity.document),
+                        redact(&activity.cmdline),
+                    );
+                    let original = (
+                        activity.window_title,
+                        activity.url,
+                        activity.project,
+                        activity.document,
+                        activity.cmdline,
+                    );
+                    if redacted != original {
+                        let (window_title, url, project, document, cmdline) = redacted;
+                        tx.execute(
+                            "UPDATE activities SET window_title = ?, url = ?, project = ?, document = ?, cmdline = ? WHERE id = ?",
+                            params![window_title, url, project, document, cmdline, activity.id],
+                        )?;
+                        summary.redacted += 1;
+                    }
+                }
+            }
+        }
+
+        // Heartbeats from watchers go through the same filter
+        let events = {
+            let mut stmt = tx.prepare("SELECT id, source, data FROM events")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        for (id, source, data) in events {
+            let data: serde_json::Value = serde_json::from_str(&data).unwrap_or_default();
+            match filter.filter_event_data(&source, &data) {
+                None => {
+                    tx.execute("DELETE FROM events WHERE id = ?", params![id])?;
+                    summary.events_deleted += 1;
+                }
+                Some(filtered) if filtered != data => {
+                    tx.execute("UPDATE events SET data = ? WHERE id = ?", params![filtered.to_string(), id])?;
+                    summary.events_filtered += 1;
+                }
+                Some(_) => {}
+            }
+        }
+
+        let recorded = {
+            let mut stmt = tx.prepare(
+                "SELECT change_id, table_name, before, after FROM change_rows
+                 WHERE table_name IN ('activities', 'events')",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let mut exposing = HashSet::new();
+        for (change_id, table, before, after) in recorded {
+            let filtered = [before, after].iter().flatten().any(|image| {
+                let image = serde_json::from_str(image).unwrap_or_default();
+                image_is_filtered(filter, &table, &image)
+            });
+            if filtered {
+                exposing.insert(change_id);
+            }
+        }
+        for change_id in exposing {
+            tx.execute("DELETE FROM change_rows WHERE change_id = ?", params![change_id])?;
+            tx.execute("DELETE FROM changes WHERE id = ?", params![change_id])?;
+        }
+
+        tx.commit()?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+    use crate::privacy::{IGNORED_APPS_KEY, MASKED_APPS_KEY, TITLE_REDACTIONS_KEY};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_purge_applies_new_exclusions() {
+        let db = Database::open_in_memory().unwrap();
+        let record = |app: &str, title: &str, url: Option<&str>| {
+            let context = WindowContext {
+                url: url.map(str::to_string),
+                ..Default::default()
+            };
+            db.start_activity(app, Some(title), &context, 1_700_000_000).unwrap()
+        };
+        let vault = record("KeePassXC", "Passwords.kdbx - KeePassXC", None);
+        let signal = record("Signal", "Alice: see you at 8", None);
+        db.update_activity_row(&crate::database::Activity {
+            exe_path: Some("/usr/bin/signal-desktop".to_string()),
+            cwd: Some("/home/me/chats".to_string()),
+            git_repo: Some("/home/me/chats".to_string()),
+            git_branch: Some("alice".to_string()),
+            ..db.get_activity_by_id(signal).unwrap().unwrap()
+        })
+        .unwrap();
+        record("firefox", "Reset password — Mozilla Firefox", Some("https://example.com/reset?token=s3cr3t"));
+        record("firefox", "Bank — Mozilla Firefox Private Browsing", None);
+        let code = record("Code", "main.rs - tracker - Visual Studio Code", None);
+        db.insert_heartbeat("vscode", 1_700_000_000, 0, &serde_json::json!({ "file": "a" }), 60).unwrap();
+        let heartbeat = |data: serde_json::Value| db.insert_heartbeat("watcher", 1_700_000_000, 0, &data, 60).unwrap();
+        heartbeat(serde_json::json!({ "app": "KeePassXC", "title": "Passwords.kdbx" }));
+        heartbeat(serde_json::json!({ "app": "Signal", "title": "Alice: see you at 8" }));
+        heartbeat(serde_json::json!({ "url": "https://example.com/reset?token=s3cr3t" }));
+
+        // Recorded edits keep images of the rows they touched
+        db.update_activity_category(vault, Some(1)).unwrap();
+        db.update_activity_category(code, Some(1)).unwrap();
+
+        let settings = HashMap::from([
+            (IGNORED_APPS_KEY.to_string(), r#"["KeePassXC"]"#.to_string()),
+            (MASKED_APPS_KEY.to_string(), r#"["Signal"]"#.to_string()),
+            (TITLE_REDACTIONS_KEY.to_string(), r#"["token=\\w+", "(?i)reset password"]"#.to_string()),
+        ]);
+        let filter = PrivacyFilter::from_settings(&settings).unwrap();
+        let summary = db.purge_activities(&filter).unwrap();
+        assert_eq!((summary.deleted, summary.masked, summary.redacted), (2, 1, 1));
+        assert_eq!((summary.events_deleted, summary.events_filtered), (1, 2));
+
+        let rows = db.get_activities(0, i64::MAX, None, None, None, None).unwrap();
+        let stored: Vec<_> = rows
+            .iter()
+            .map(|a| (a.app_name.as_str(), a.window_title.as_deref(), a.url.as_deref()))
+            .collect();
+        assert_eq!(
+            stored,
+            vec![
+                ("Signal", Some(MASKED_TITLE), None),
+                ("firefox", Some("[redacted] — Mozilla Firefox"), Some("https://example.com/reset?[redacted]")),
+                ("Code", Some("main.rs - tracker - Visual Studio Code"), None),
+            ]
+        );
+        let masked = &rows[0];
+        assert_eq!(
+            (&masked.exe_path, &masked.cwd, &masked.git_repo, &masked.git_branch),
+            (&None, &None, &None, &None)
+        );
+        let events = db.get_events(0, i64::MAX, None).unwrap();
+        assert!(events.iter().all(|e| e.activity_id != Some(vault)));
+        let data: Vec<_> = events.iter().map(|e| e.data.clone()).collect();
+        assert_eq!(
+            data,
+            vec![
+                serde_json::json!({ "file": "a" }),
+                serde_json::json!({ "app": "Signal", "title": MASKED_TITLE }),
+                serde_json::json!({ "url": "https://example.com/reset?[redacted]" }),
+            ]
+        );
+
+        // Only the edit of the code activity is left in the history, and nothing recorded
+        // still holds the removed data
+        let changes: Vec<_> = db.list_changes(10).unwrap().into_iter().map(|c| c.summary).collect();
+        assert_eq!(changes, vec![format!("Change category of activity {}", code)]);
+        let images: Vec<String> = {
+            let conn = db.conn.lock().unwrap();
+            let mut stmt = conn.prepare("SELECT COALESCE(before, '') || COALESCE(after, '') FROM change_rows").unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.collect::<Result<_>>().unwrap()
+        };
+        assert!(images.iter().all(|i| !i.contains("KeePassXC") && !i.contains("Alice") && !i.contains("s3cr3t")));
+
+        // Running it again changes nothing
+        let again = db.purge_activities(&filter).unwrap();
+        assert_eq!((again.deleted, again.masked, again.redacted), (0, 0, 0));
+        assert_eq!((again.events_deleted, again.events_filtered), (0, 0));
+    }
+}
@@ -0,0 +1,210 @@
+//! Client and project database operations
+
+use rusqlite::{Result, params};
+use super::common::{Database, unique_violation};
+use super::history::RecordedChange;
+use super::models::{Client, Project};
+
+impl Database {
+    /// Get clients ordered by name; archived clients only if requested
+    pub fn get_clients(&self, include_archived: bool) -> Result<Vec<Client>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, color, archived
+             FROM clients
+             WHERE archived = 0 OR ?
+             ORDER BY name COLLATE NOCASE",
+        )?;
+
+        let clients = stmt
+            .query_map(params![include_archived], |row| {
+                Ok(Client {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                    archived: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(clients)
+    }
+
+    /// Create a client
+    pub fn create_client(&self, name: &str, color: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Create client {}", name))?;
+        conn.execute(
+            "INSERT INTO clients (name, color) VALUES (?, ?)",
+            params![name, color],
+        )
+        .map_err(|e| unique_violation(e, "A client with this name already exists"))?;
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
+    }
+
+    /// Update a client's name, colour and archived state
+    pub fn update_client(&self, id: i64, name: &str, color: &str, archived: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Update client {}", id))?;
+        conn.execute(
+            "UPDATE clients SET name = ?, color = ?, archived = ? WHERE id = ?",
+            params![name, color, archived, id],
+        )
+        .map_err(|e| unique_violation(e, "A client with this name already exists"))?;
+        change.finish()
+    }
+
+    /// Delete a client; its projects are kept without a client
+    pub fn delete_client(&self, id: i64) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, &format!("Delete client {}", id))?;
+        tx.execute("UPDATE projects SET client_id = NULL WHERE client_id = ?", params![id])
+            .map_err(|e| unique_violation(e, "A project without a client already has the name of one of this client's projects"))?;
+        tx.execute("DELETE FROM clients WHERE id = ?", params![id])?;
+        change.finish()?;
+        tx.commit()
+    }
+
+    /// Get projects ordered by name; archived projects only if requested
+    pub fn get_projects(&self, include_archived: bool) -> Result<Vec<Project>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, client_id, color, archived
+             FROM projects
+             WHERE archived = 0 OR ?
+             ORDER BY name COLLATE NOCASE",
+        )?;
+
+        let projects = stmt
+            .query_map(params![include_archived], |row| {
+                Ok(Project {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    client_id: row.get(2)?,
+                    color: row.get(3)?,
+                    archived: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(projects)
+    }
+
+    /// Create a project, optionally for a client
+    pub fn create_project(&self, name: &str, client_id: Option<i64>, color: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Create project {}", name))?;
+        conn.execute(
+            "INSERT INTO projects (name, client_id, color) VALUES (?, ?, ?)",
+            params![name, client_id, color],
+        )
+        .map_err(|e| unique_violation(e, "A project with this name already exists for this client"))?;
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
+    }
+
+    /// Update a project's name, client, colour and archived state
+    pub fn update_project(
+        &self,
+        id: i64,
+        name: &str,
+        client_id: Option<i64>,
+        color: &str,
+        archived: bool,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Update project {}", id))?;
+        conn.execute(
+            "UPDATE projects SET name = ?, client_id = ?, color = ?, archived = ? WHERE id = ?",
+            params![name, client_id, color, archived, id],
+        )
+        .map_err(|e| unique_violation(e, "A project with this name already exists for this client"))?;
+        change.finish()
+    }
+
+    /// Delete a project; activities and manual entries assigned to it are unassigned
+    pub fn delete_project(&self, id: i64) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, &format!("Delete project {}", id))?;
+        tx.execute("UPDATE activities SET project_id = NULL WHERE project_id = ?", params![id])?;
+        tx.execute("UPDATE manual_entries SET project_id = NULL WHERE project_id = ?", params![id])?;
+        tx.execute("DELETE FROM projects WHERE id = ?", params![id])?;
+        change.finish()?;
+        tx.commit()
+    }
+
+    /// Assign an activity to a project, or unassign it with None
+    pub fn set_activity_project(&self, id: i64, project_id: Option<i64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Change project of activity {}", id))?;
+        conn.execute(
+            "UPDATE activities SET project_id = ? WHERE id = ?",
+            params![project_id, id],
+        )?;
+        change.finish()
+    }
+
+    /// Assign a manual entry to a project, or unassign it with None
+    pub fn set_manual_entry_project(&self, id: i64, project_id: Option<i64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Change project of manual entry {}", id))?;
+        conn.execute(
+            "UPDATE manual_entries SET project_id = ? WHERE id = ?",
+            params![project_id, id],
+        )?;
+        change.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+
+    #[test]
+    fn test_projects_and_clients() {
+        let db = Database::open_in_memory().unwrap();
+        let acme = db.create_client("Acme", "#ff0000").unwrap();
+        assert!(db.create_client("Acme", "#00ff00").unwrap_err().to_string().contains("already exists"));
+
+        let globex = db.create_client("Globex", "#00ff00").unwrap();
+        let website = db.create_project("Website", Some(acme), "#0000ff").unwrap();
+        let internal = db.create_project("Internal", None, "#888888").unwrap();
+        // Same name for another client is fine, but not twice for one client or without one
+        db.create_project("Website", Some(globex), "#888888").unwrap();
+        assert!(db.create_project("Website", Some(acme), "#888888").is_err());
+        assert!(db.create_project("Internal", None, "#888888").unwrap_err().to_string().contains("already exists"));
+
+        db.update_project(internal, "Internal", None, "#888888", true).unwrap();
+        let names: Vec<_> = db.get_projects(false).unwrap().into_iter().map(|p| (p.name, p.client_id)).collect();
+        assert_eq!(names, vec![("Website".to_string(), Some(acme)), ("Website".to_string(), Some(globex))]);
+        assert_eq!(db.get_projects(true).unwrap().len(), 3);
+
+        let activity = db.start_activity("Code", Some("main.rs"), &WindowContext::default(), 1_000).unwrap();
+        db.extend_activity(activity, 1_600).unwrap();
+        db.set_activity_project(activity, Some(website)).unwrap();
+        let entry = db.add_manual_entry(Some("Call"), None, 2_000, 2_300).unwrap();
+        db.set_manual_entry_project(entry, Some(website)).unwrap();
+
+        // Activities and manual entries both count, clipped to the range
+        let stats = db.get_stats_for_range(0, 10_000).unwrap();
+        assert_eq!(stats.project_breakdown, vec![(website, "Website".to_string(), "#0000ff".to_string(), 900)]);
+        let stats = db.get_stats_for_range(1_500, 2_100).unwrap();
+        assert_eq!(stats.project_breakdown, vec![(website, "Website".to_string(), "#0000ff".to_string(), 200)]);
+
+        // Leaving a project without a client may not duplicate a name
+        let clientless = db.create_project("Website", None, "#888888").unwrap();
+        assert!(db.delete_client(acme).unwrap_err().to_string().contains("without a client"));
+        db.delete_project(clientless).unwrap();
+        db.delete_client(acme).unwrap();
+        assert_eq!(db.get_projects(true).unwrap().iter().find(|p| p.id == website).unwrap().client_id, None);
+        db.delete_project(website).unwrap();
+        assert_eq!(db.get_activity_by_id(activity).unwrap().unwrap().project_id, None);
+        assert_eq!(db.get_manual_entries(0, i64::MAX).unwrap()[0].project_id, None);
+    }
+}
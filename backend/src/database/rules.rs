@@ -1,7 +1,8 @@
 //! Rule management database operations
 
 use rusqlite::{Result, params};
-use super::common::Database;
+use super::common::{Database, unique_violation};
+use super::history::RecordedChange;
 use super::models::Rule;
 
 impl Database {
@@ -38,34 +39,24 @@ impl Database {
         priority: i64,
     ) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Add rule {}", pattern))?;
         conn.execute(
             "INSERT INTO rules (rule_type, pattern, category_id, priority)
              VALUES (?, ?, ?, ?)",
             params![rule_type, pattern, category_id, priority],
         )
-        .map_err(|e| {
-            if let rusqlite::Error::SqliteFailure(ref err, Some(ref msg)) = e {
-                if err.code == rusqlite::ffi::ErrorCode::ConstraintViolation
-                    && (msg.contains("rules.rule_type") 
-                        || msg.contains("idx_rules_unique")
-                        || (msg.contains("UNIQUE constraint") && msg.contains("rules")))
-                {
-                    return rusqlite::Error::SqliteFailure(
-                        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                        Some("A rule with this pattern and category already exists".to_string()),
-                    );
-                }
-            }
-            e
-        })?;
-        Ok(conn.last_insert_rowid())
+        .map_err(|e| unique_violation(e, "A rule with this pattern and category already exists"))?;
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
     }
 
     /// Delete a rule
     pub fn delete_rule(&self, id: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Delete rule {}", id))?;
         conn.execute("DELETE FROM rules WHERE id = ?", params![id])?;
-        Ok(())
+        change.finish()
     }
 
     /// Update rule
@@ -78,26 +69,84 @@ impl Database {
         priority: i64,
     ) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Update rule {}", id))?;
         conn.execute(
             "UPDATE rules SET rule_type = ?, pattern = ?, category_id = ?, priority = ?
              WHERE id = ?",
             params![rule_type, pattern, category_id, priority, id],
         )
-        .map_err(|e| {
-            if let rusqlite::Error::SqliteFailure(ref err, Some(ref msg)) = e {
-                if err.code == rusqlite::ffi::ErrorCode::ConstraintViolation
-                    && (msg.contains("rules.rule_type") 
-                        || msg.contains("idx_rules_unique")
-                        || (msg.contains("UNIQUE constraint") && msg.contains("rules")))
-                {
-                    return rusqlite::Error::SqliteFailure(
-                        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                        Some("A rule with this pattern and category already exists".to_string()),
-                    );
-                }
-            }
-            e
-        })?;
-        Ok(())
+        .map_err(|e| unique_violation(e, "A rule with this pattern and category already exists"))?;
+        change.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::activities::{SOURCE_DEFAULT, SOURCE_MANUAL, SOURCE_RULE};
+    use crate::database::common::SYSTEM_CATEGORY_UNCATEGORIZED;
+    use crate::database::{ReapplyOptions, WindowContext};
+
+    fn category_and_source(db: &Database, id: i64) -> (Option<i64>, Option<String>, Option<i64>) {
+        let activity = db.get_activity_by_id(id).unwrap().unwrap();
+        (activity.category_id, activity.category_source, activity.category_rule_id)
+    }
+
+    #[test]
+    fn test_reapply_keeps_manual_categories() {
+        let db = Database::open_in_memory().unwrap();
+        let client = db.create_category_core("Client work", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let review = db.start_activity("Reviewboard", None, &WindowContext::default(), 1_000).unwrap();
+        let late = db.start_activity("Reviewboard", None, &WindowContext::default(), 5_000).unwrap();
+        let wiki = db.start_activity("Wikipad", None, &WindowContext::default(), 2_000).unwrap();
+        assert_eq!(category_and_source(&db, wiki).1.as_deref(), Some(SOURCE_DEFAULT));
+        db.update_activity_category(wiki, Some(client)).unwrap();
+
+        let rule = db.add_rule("app_name", "Reviewboard", client, 50).unwrap();
+        let wiki_rule = db.add_rule("app_name", "Wikipad", client, 50).unwrap();
+        let mut reports = Vec::new();
+        let options = ReapplyOptions { end: Some(3_000), rule_ids: Some(vec![rule]), ..Default::default() };
+        let summary = db.reapply_categorization_rules(&options, |p| reports.push(p.clone())).unwrap();
+        assert_eq!((summary.total, summary.processed, summary.updated, summary.skipped_manual), (2, 2, 1, 1));
+        assert_eq!(reports, vec![summary]);
+        assert_eq!(category_and_source(&db, review), (Some(client), Some(SOURCE_RULE.to_string()), Some(rule)));
+        // Outside the range
+        assert_eq!(category_and_source(&db, late).2, None);
+        assert_eq!(category_and_source(&db, wiki), (Some(client), Some(SOURCE_MANUAL.to_string()), None));
+        // Only the category columns are recorded
+        let before: String = db
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT before FROM change_rows ORDER BY id DESC LIMIT 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&before).unwrap(),
+            serde_json::json!({ "category_id": SYSTEM_CATEGORY_UNCATEGORIZED, "category_source": SOURCE_DEFAULT, "category_rule_id": null })
+        );
+
+        // Without a subset unmatched activities fall back to the default; forcing overrides manual categories
+        db.delete_rule(rule).unwrap();
+        let summary = db.reapply_categorization_rules(&ReapplyOptions { force: true, ..Default::default() }, |_| {}).unwrap();
+        assert_eq!((summary.updated, summary.skipped_manual), (2, 0));
+        assert_eq!(category_and_source(&db, review).1.as_deref(), Some(SOURCE_DEFAULT));
+        assert_eq!(category_and_source(&db, wiki), (Some(client), Some(SOURCE_RULE.to_string()), Some(wiki_rule)));
+    }
+
+    #[test]
+    fn test_reapply_subset_leaves_activities_other_rules_win() {
+        let db = Database::open_in_memory().unwrap();
+        let client = db.create_category_core("Client work", "#0000ff", None, Some(true), 100, false, false).unwrap();
+        let review = db.create_category_core("Review", "#00ff00", None, Some(true), 101, false, false).unwrap();
+        let ticket = db.start_activity("Reviewboard", Some("Ticket 42"), &WindowContext::default(), 1_000).unwrap();
+        let other = db.start_activity("Reviewboard", Some("Ticket 7"), &WindowContext::default(), 2_000).unwrap();
+
+        let app = db.add_rule("app_name", "Reviewboard", review, 10).unwrap();
+        db.add_rule("window_title", "*42*", client, 50).unwrap();
+        let options = ReapplyOptions { rule_ids: Some(vec![app]), ..Default::default() };
+        let summary = db.reapply_categorization_rules(&options, |_| {}).unwrap();
+        assert_eq!(summary.updated, 1);
+        assert_eq!(category_and_source(&db, other), (Some(review), Some(SOURCE_RULE.to_string()), Some(app)));
+        assert_eq!(category_and_source(&db, ticket).1.as_deref(), Some(SOURCE_DEFAULT));
     }
 }
@@ -0,0 +1,97 @@
+//! Work-hours schedule database operations
+
+use rusqlite::{Result, params};
+use super::common::Database;
+use super::models::{ScheduleException, ScheduleWindow};
+
+impl Database {
+    /// Get all weekly schedule windows
+    pub fn get_schedule_windows(&self) -> Result<Vec<ScheduleWindow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, weekday, start_minute, end_minute
+             FROM schedule_windows
+             ORDER BY weekday, start_minute",
+        )?;
+
+        let windows = stmt
+            .query_map([], |row| {
+                Ok(ScheduleWindow {
+                    id: row.get(0)?,
+                    weekday: row.get(1)?,
+                    start_minute: row.get(2)?,
+                    end_minute: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(windows)
+    }
+
+    /// Add a weekly schedule window
+    pub fn add_schedule_window(&self, weekday: i64, start_minute: i64, end_minute: i64) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO schedule_windows (weekday, start_minute, end_minute) VALUES (?, ?, ?)",
+            params![weekday, start_minute, end_minute],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Delete a weekly schedule window
+    pub fn delete_schedule_window(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM schedule_windows WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    /// Get all schedule exceptions and holidays
+    pub fn get_schedule_exceptions(&self) -> Result<Vec<ScheduleException>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, date, kind, start_minute, end_minute, label
+             FROM schedule_exceptions
+             ORDER BY date, start_minute",
+        )?;
+
+        let exceptions = stmt
+            .query_map([], |row| {
+                Ok(ScheduleException {
+                    id: row.get(0)?,
+                    date: row.get(1)?,
+                    kind: row.get(2)?,
+                    start_minute: row.get(3)?,
+                    end_minute: row.get(4)?,
+                    label: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(exceptions)
+    }
+
+    /// Add a schedule exception or holiday
+    pub fn add_schedule_exception(
+        &self,
+        date: &str,
+        kind: &str,
+        start_minute: Option<i64>,
+        end_minute: Option<i64>,
+        label: Option<&str>,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO schedule_exceptions (date, kind, start_minute, end_minute, label)
+             VALUES (?, ?, ?, ?, ?)",
+            params![date, kind, start_minute, end_minute, label],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Delete a schedule exception or holiday
+    pub fn delete_schedule_exception(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM schedule_exceptions WHERE id = ?", params![id])?;
+        Ok(())
+    }
+}
@@ -25,6 +25,13 @@ impl Database {
         Ok(())
     }
 
+    /// Delete a setting
+    pub fn delete_setting(&self, key: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM settings WHERE key = ?", params![key])?;
+        Ok(())
+    }
+
     /// Get all settings as a map
     pub fn get_all_settings(&self) -> Result<std::collections::HashMap<String, String>> {
         let conn = self.conn.lock().unwrap();
@@ -3,6 +3,62 @@
 use super::common::Database;
 use super::models::*;
 use rusqlite::{Result, params};
+use std::collections::HashMap;
+
+/// Seconds of activity `a` that fall inside the range [?1, ?2].
+/// Sessions crossing the range edges only count the overlapping part,
+/// so totals never exceed the wall-clock length of the range.
+pub(super) const CLIPPED_DURATION: &str = "(MIN(a.ended_at, ?2) - MAX(a.started_at, ?1))";
+
+/// Activities overlapping the range [?1, ?2]
+pub(super) const IN_RANGE: &str = "a.ended_at > ?1 AND a.started_at < ?2";
+
+/// Arrange per-category seconds by parent. Each node carries the time assigned to the
+/// category itself and the total including its subcategories; categories without any
+/// time in either are left out. Siblings are sorted by total, longest first.
+fn category_tree(categories: &[Category], self_seconds: &HashMap<i64, i64>) -> Vec<CategoryTreeNode> {
+    let known: HashMap<i64, &Category> = categories.iter().map(|c| (c.id, c)).collect();
+    let mut children: HashMap<Option<i64>, Vec<&Category>> = HashMap::new();
+    for category in categories {
+        // Categories whose parent is gone are shown at the top level
+        let parent = category.parent_id.filter(|id| known.contains_key(id));
+        children.entry(parent).or_default().push(category);
+    }
+
+    fn build(
+        parent: Option<i64>,
+        children: &HashMap<Option<i64>, Vec<&Category>>,
+        self_seconds: &HashMap<i64, i64>,
+        depth: usize,
+    ) -> Vec<CategoryTreeNode> {
+        // Parenting rejects cycles; the depth limit only guards against a corrupted table
+        if depth > children.len() {
+            return Vec::new();
+        }
+        let mut nodes: Vec<CategoryTreeNode> = children
+            .get(&parent)
+            .into_iter()
+            .flatten()
+            .filter_map(|category| {
+                let sub = build(Some(category.id), children, self_seconds, depth + 1);
+                let own = self_seconds.get(&category.id).copied().unwrap_or(0);
+                let total_seconds = own + sub.iter().map(|n| n.total_seconds).sum::<i64>();
+                (total_seconds > 0).then(|| CategoryTreeNode {
+                    category_id: category.id,
+                    name: category.name.clone(),
+                    color: category.color.clone(),
+                    self_seconds: own,
+                    total_seconds,
+                    children: sub,
+                })
+            })
+            .collect();
+        nodes.sort_by(|a, b| b.total_seconds.cmp(&a.total_seconds).then(a.category_id.cmp(&b.category_id)));
+        nodes
+    }
+
+    build(None, &children, self_seconds, 0)
+}
 
 impl Database {
     /// Get daily stats (SQL aggregation — no full activity load)
@@ -19,12 +75,15 @@ impl Database {
 
         // Query 1: total and productive seconds
         let (total_seconds, productive_seconds): (i64, i64) = conn.query_row(
-            "SELECT
-                COALESCE(SUM(a.duration_sec), 0),
-                COALESCE(SUM(CASE WHEN c.is_productive = 1 THEN a.duration_sec ELSE 0 END), 0)
-            FROM activities a
-            LEFT JOIN categories c ON a.category_id = c.id
-            WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0",
+            &format!(
+                "SELECT
+                    COALESCE(SUM({clipped}), 0),
+                    COALESCE(SUM(CASE WHEN c.is_productive = 1 THEN {clipped} ELSE 0 END), 0)
+                FROM activities a
+                LEFT JOIN categories c ON a.category_id = c.id
+                WHERE {in_range} AND a.is_idle = 0",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
             params![start, end],
             |row| Ok((row.get(0)?, row.get(1)?)),
         )?;
@@ -32,11 +91,14 @@ impl Database {
         // Query 2: category breakdown
         let mut category_stats: Vec<CategoryStat> = Vec::new();
         let mut stmt = conn.prepare(
-            "SELECT a.category_id, SUM(a.duration_sec) AS duration_sec
-             FROM activities a
-             WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0 AND a.category_id IS NOT NULL
-             GROUP BY a.category_id
-             ORDER BY duration_sec DESC",
+            &format!(
+                "SELECT a.category_id, SUM({clipped}) AS duration_sec
+                 FROM activities a
+                 WHERE {in_range} AND a.is_idle = 0 AND a.category_id IS NOT NULL
+                 GROUP BY a.category_id
+                 ORDER BY duration_sec DESC",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
         )?;
         let category_rows = stmt.query_map(params![start, end], |row| {
             Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
@@ -58,11 +120,14 @@ impl Database {
         // Query 3: app breakdown
         let mut app_stats: Vec<AppStat> = Vec::new();
         let mut stmt = conn.prepare(
-            "SELECT a.app_name, SUM(a.duration_sec) AS duration_sec, MAX(a.category_id) AS category_id
-             FROM activities a
-             WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0
-             GROUP BY a.app_name
-             ORDER BY duration_sec DESC",
+            &format!(
+                "SELECT a.app_name, SUM({clipped}) AS duration_sec, MAX(a.category_id) AS category_id
+                 FROM activities a
+                 WHERE {in_range} AND a.is_idle = 0
+                 GROUP BY a.app_name
+                 ORDER BY duration_sec DESC",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
         )?;
         let app_rows = stmt.query_map(params![start, end], |row| {
             Ok((
@@ -81,11 +146,18 @@ impl Database {
             });
         }
 
+        let self_seconds = category_stats
+            .iter()
+            .filter_map(|stat| stat.category.as_ref().map(|c| (c.id, stat.duration_sec)))
+            .collect();
+        let category_tree = category_tree(&categories, &self_seconds);
+
         Ok(DailyStats {
             total_seconds,
             productive_seconds,
             category_stats,
             app_stats,
+            category_tree,
         })
     }
 
@@ -98,12 +170,15 @@ impl Database {
             .collect();
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT a.app_name, SUM(a.duration_sec) AS duration_sec, MAX(a.category_id) AS category_id
-             FROM activities a
-             WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0
-             GROUP BY a.app_name
-             ORDER BY duration_sec DESC
-             LIMIT ?3",
+            &format!(
+                "SELECT a.app_name, SUM({clipped}) AS duration_sec, MAX(a.category_id) AS category_id
+                 FROM activities a
+                 WHERE {in_range} AND a.is_idle = 0
+                 GROUP BY a.app_name
+                 ORDER BY duration_sec DESC
+                 LIMIT ?3",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
         )?;
         let rows = stmt.query_map(params![start, end, limit], |row| {
             Ok((
@@ -125,56 +200,68 @@ impl Database {
         Ok(app_stats)
     }
 
-    /// Get category usage (SQL aggregation)
+    /// Get category usage nested by parent (SQL aggregation). Each node has the time
+    /// assigned to the category itself and the total including its subcategories;
+    /// percentages are of the total time in the range.
     pub fn get_category_usage(&self, start: i64, end: i64) -> Result<Vec<CategoryUsageStat>> {
         let categories = self.get_categories()?;
-        let cat_map: std::collections::HashMap<i64, Category> = categories
+        let cat_map: HashMap<i64, Category> = categories
             .iter()
             .map(|c| (c.id, c.clone()))
             .collect();
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT a.category_id, SUM(a.duration_sec) AS duration_sec
-             FROM activities a
-             WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0 AND a.category_id IS NOT NULL
-             GROUP BY a.category_id
-             ORDER BY duration_sec DESC",
+            &format!(
+                "SELECT a.category_id, SUM({clipped}) AS duration_sec
+                 FROM activities a
+                 WHERE {in_range} AND a.is_idle = 0 AND a.category_id IS NOT NULL
+                 GROUP BY a.category_id",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
         )?;
-        let rows = stmt.query_map(params![start, end], |row| {
-            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
-        })?;
-        let mut category_stats: Vec<CategoryUsageStat> = Vec::new();
-        let mut total: i64 = 0;
-        for row in rows {
-            let (category_id, duration_sec) = row?;
-            total += duration_sec;
-            category_stats.push(CategoryUsageStat {
-                category: cat_map.get(&category_id).cloned(),
-                duration_sec,
-                percentage: 0, // set below
-            });
-        }
-        if total > 0 {
-            for stat in &mut category_stats {
-                stat.percentage = (stat.duration_sec as f64 / total as f64 * 100.0) as i64;
-            }
+        let self_seconds = stmt
+            .query_map(params![start, end], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<HashMap<i64, i64>>>()?;
+        let total: i64 = self_seconds.values().sum();
+
+        fn usage(nodes: Vec<CategoryTreeNode>, cat_map: &HashMap<i64, Category>, total: i64) -> Vec<CategoryUsageStat> {
+            nodes
+                .into_iter()
+                .filter_map(|node| {
+                    Some(CategoryUsageStat {
+                        category: cat_map.get(&node.category_id)?.clone(),
+                        duration_sec: node.self_seconds,
+                        percentage: if total > 0 {
+                            (node.total_seconds as f64 / total as f64 * 100.0) as i64
+                        } else {
+                            0
+                        },
+                        total_duration_sec: node.total_seconds,
+                        children: usage(node.children, cat_map, total),
+                    })
+                })
+                .collect()
         }
-        Ok(category_stats)
+        Ok(usage(category_tree(&categories, &self_seconds), &cat_map, total))
     }
 
-    /// Get hourly activity (SQL aggregation)
+    /// Get hourly activity (SQL aggregation). Sessions spanning several hours count
+    /// towards each hour they overlap, with only the overlapping seconds.
     pub fn get_hourly_activity(&self, date: i64) -> Result<Vec<HourlyStat>> {
-        let start = date;
-        let end = date + 86400;
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT CAST((started_at - ?1) / 3600 AS INTEGER) AS hour, SUM(duration_sec) AS duration_sec
-             FROM activities
-             WHERE started_at >= ?1 AND started_at <= ?2 AND is_idle = 0
-             GROUP BY CAST((started_at - ?1) / 3600 AS INTEGER)
-             ORDER BY hour ASC",
+            "WITH RECURSIVE hours(hour) AS (SELECT 0 UNION ALL SELECT hour + 1 FROM hours WHERE hour < 23)
+             SELECT h.hour,
+                    SUM(MIN(a.ended_at, ?1 + (h.hour + 1) * 3600) - MAX(a.started_at, ?1 + h.hour * 3600)) AS duration_sec
+             FROM hours h
+             JOIN activities a ON a.ended_at > ?1 + h.hour * 3600 AND a.started_at < ?1 + (h.hour + 1) * 3600
+             WHERE a.is_idle = 0
+             GROUP BY h.hour
+             ORDER BY h.hour ASC",
         )?;
-        let rows = stmt.query_map(params![start, end], |row| {
+        let rows = stmt.query_map(params![date], |row| {
             Ok(HourlyStat {
                 hour: row.get(0)?,
                 duration_sec: row.get(1)?,
@@ -188,10 +275,13 @@ impl Database {
     pub fn get_productive_time(&self, start: i64, end: i64) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
         let productive_seconds: i64 = conn.query_row(
-            "SELECT COALESCE(SUM(a.duration_sec), 0) AS productive_seconds
-             FROM activities a
-             INNER JOIN categories c ON a.category_id = c.id
-             WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0 AND c.is_productive = 1",
+            &format!(
+                "SELECT COALESCE(SUM({clipped}), 0) AS productive_seconds
+                 FROM activities a
+                 INNER JOIN categories c ON a.category_id = c.id
+                 WHERE {in_range} AND a.is_idle = 0 AND c.is_productive = 1",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
             params![start, end],
             |row| row.get(0),
         )?;
@@ -203,12 +293,15 @@ impl Database {
     pub fn get_top_domains(&self, start: i64, end: i64, limit: i64) -> Result<Vec<DomainStat>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT domain, SUM(duration_sec) AS duration_sec
-             FROM activities
-             WHERE started_at >= ?1 AND started_at <= ?2 AND is_idle = 0 AND domain IS NOT NULL
-             GROUP BY domain
-             ORDER BY duration_sec DESC
-             LIMIT ?3",
+            &format!(
+                "SELECT a.domain, SUM({clipped}) AS duration_sec
+                 FROM activities a
+                 WHERE {in_range} AND a.is_idle = 0 AND a.domain IS NOT NULL
+                 GROUP BY a.domain
+                 ORDER BY duration_sec DESC
+                 LIMIT ?3",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
         )?;
         let rows = stmt.query_map(params![start, end, limit], |row| {
             Ok(DomainStat {
@@ -220,28 +313,59 @@ impl Database {
         Ok(domain_stats)
     }
 
+    /// Get top projects recognised in editor titles for a time range (SQL aggregation)
+    pub fn get_top_projects(&self, start: i64, end: i64, limit: i64) -> Result<Vec<ProjectStat>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT a.project, SUM({clipped}) AS duration_sec
+                 FROM activities a
+                 WHERE {in_range} AND a.is_idle = 0 AND a.project IS NOT NULL
+                 GROUP BY a.project
+                 ORDER BY duration_sec DESC
+                 LIMIT ?3",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
+        )?;
+        let rows = stmt.query_map(params![start, end, limit], |row| {
+            Ok(ProjectStat {
+                project: row.get(0)?,
+                duration_sec: row.get(1)?,
+            })
+        })?;
+        rows.collect()
+    }
+
     /// Get aggregated stats for an arbitrary time range (SQL aggregation, for get_stats command).
     pub fn get_stats_for_range(&self, start: i64, end: i64) -> Result<RangeStats> {
+        let categories = self.get_categories()?;
+        let earnings = super::billing::earnings(&self.get_billed_activities(start, end)?, &categories);
         let conn = self.conn.lock().unwrap();
 
         let (total_seconds, productive_seconds): (i64, i64) = conn.query_row(
-            "SELECT
-                COALESCE(SUM(a.duration_sec), 0),
-                COALESCE(SUM(CASE WHEN c.is_productive = 1 THEN a.duration_sec ELSE 0 END), 0)
-            FROM activities a
-            LEFT JOIN categories c ON a.category_id = c.id
-            WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0",
+            &format!(
+                "SELECT
+                    COALESCE(SUM({clipped}), 0),
+                    COALESCE(SUM(CASE WHEN c.is_productive = 1 THEN {clipped} ELSE 0 END), 0)
+                FROM activities a
+                LEFT JOIN categories c ON a.category_id = c.id
+                WHERE {in_range} AND a.is_idle = 0",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
             params![start, end],
             |row| Ok((row.get(0)?, row.get(1)?)),
         )?;
 
         let mut stmt = conn.prepare(
-            "SELECT a.category_id, COALESCE(c.name, 'Unknown'), COALESCE(c.color, '#888'), SUM(a.duration_sec) AS duration_sec
-             FROM activities a
-             LEFT JOIN categories c ON a.category_id = c.id
-             WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0 AND a.category_id IS NOT NULL
-             GROUP BY a.category_id
-             ORDER BY duration_sec DESC",
+            &format!(
+                "SELECT a.category_id, COALESCE(c.name, 'Unknown'), COALESCE(c.color, '#888'), SUM({clipped}) AS duration_sec
+                 FROM activities a
+                 LEFT JOIN categories c ON a.category_id = c.id
+                 WHERE {in_range} AND a.is_idle = 0 AND a.category_id IS NOT NULL
+                 GROUP BY a.category_id
+                 ORDER BY duration_sec DESC",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
         )?;
         let category_breakdown: Vec<(i64, String, String, i64)> = stmt
             .query_map(params![start, end], |row| {
@@ -255,21 +379,94 @@ impl Database {
             .collect::<Result<Vec<_>>>()?;
 
         let mut stmt = conn.prepare(
-            "SELECT a.app_name, SUM(a.duration_sec) AS duration_sec
-             FROM activities a
-             WHERE a.started_at >= ?1 AND a.started_at <= ?2 AND a.is_idle = 0
-             GROUP BY a.app_name
-             ORDER BY duration_sec DESC",
+            &format!(
+                "SELECT a.app_name, SUM({clipped}) AS duration_sec
+                 FROM activities a
+                 WHERE {in_range} AND a.is_idle = 0
+                 GROUP BY a.app_name
+                 ORDER BY duration_sec DESC",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
         )?;
         let app_breakdown: Vec<(String, i64)> = stmt
             .query_map(params![start, end], |row| Ok((row.get(0)?, row.get(1)?)))?
             .collect::<Result<Vec<_>>>()?;
 
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT p.id, p.name, p.color, SUM(u.duration_sec) AS duration_sec
+                 FROM (
+                     SELECT a.project_id, {clipped} AS duration_sec
+                     FROM activities a
+                     WHERE {in_range} AND a.is_idle = 0 AND a.project_id IS NOT NULL
+                     UNION ALL
+                     SELECT a.project_id, {clipped} AS duration_sec
+                     FROM manual_entries a
+                     WHERE {in_range} AND a.project_id IS NOT NULL
+                 ) u
+                 JOIN projects p ON p.id = u.project_id
+                 GROUP BY p.id
+                 ORDER BY duration_sec DESC",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
+        )?;
+        let project_breakdown: Vec<(i64, String, String, i64)> = stmt
+            .query_map(params![start, end], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        let self_seconds = category_breakdown
+            .iter()
+            .map(|(category_id, _, _, seconds)| (*category_id, *seconds))
+            .collect();
+        let category_tree = category_tree(&categories, &self_seconds);
+
         Ok(RangeStats {
             total_seconds,
             productive_seconds,
             category_breakdown,
             app_breakdown,
+            category_tree,
+            earnings,
+            project_breakdown,
         })
     }
+
+    /// Time carrying each tag in a range: non-idle activities and manual entries, clipped
+    /// to the range. Rows with several tags count towards each of them.
+    pub fn get_tag_usage(&self, start: i64, end: i64) -> Result<Vec<TagUsageStat>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT t.id, t.name, t.color, SUM(u.duration_sec) AS duration_sec
+                 FROM (
+                     SELECT l.tag_id, {clipped} AS duration_sec
+                     FROM activity_tags l
+                     JOIN activities a ON a.id = l.activity_id
+                     WHERE {in_range} AND a.is_idle = 0
+                     UNION ALL
+                     SELECT l.tag_id, {clipped} AS duration_sec
+                     FROM manual_entry_tags l
+                     JOIN manual_entries a ON a.id = l.manual_entry_id
+                     WHERE {in_range}
+                 ) u
+                 JOIN tags t ON t.id = u.tag_id
+                 GROUP BY t.id
+                 ORDER BY duration_sec DESC",
+                clipped = CLIPPED_DURATION, in_range = IN_RANGE,
+            ),
+        )?;
+        let rows = stmt.query_map(params![start, end], |row| {
+            Ok(TagUsageStat {
+                tag: Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                },
+                duration_sec: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
 }
@@ -0,0 +1,227 @@
+//! Tag database operations
+//!
+//! Tags are free-form labels that cut across categories. Links to deleted
+//! activities, manual entries or tags are removed by triggers.
+
+use rusqlite::types::Value as SqliteValue;
+use rusqlite::{Result, params, params_from_iter};
+use super::common::{Database, unique_violation};
+use super::history::RecordedChange;
+use super::models::{ActivityFilter, Tag, TagLinks};
+
+/// Link table and its item column for a kind of tagged row
+const ACTIVITY_LINKS: (&str, &str) = ("activity_tags", "activity_id");
+const MANUAL_ENTRY_LINKS: (&str, &str) = ("manual_entry_tags", "manual_entry_id");
+
+impl Database {
+    /// Get all tags ordered by name
+    pub fn get_tags(&self) -> Result<Vec<Tag>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, name, color FROM tags ORDER BY name")?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(tags)
+    }
+
+    /// Create a tag; names are unique regardless of case
+    pub fn create_tag(&self, name: &str, color: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Create tag {}", name))?;
+        conn.execute("INSERT INTO tags (name, color) VALUES (?, ?)", params![name, color])
+            .map_err(|e| unique_violation(e, "A tag with this name already exists"))?;
+        let id = conn.last_insert_rowid();
+        change.finish()?;
+        Ok(id)
+    }
+
+    /// Rename or recolour a tag
+    pub fn update_tag(&self, id: i64, name: &str, color: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Update tag {}", id))?;
+        conn.execute(
+            "UPDATE tags SET name = ?, color = ? WHERE id = ?",
+            params![name, color, id],
+        )
+        .map_err(|e| unique_violation(e, "A tag with this name already exists"))?;
+        change.finish()
+    }
+
+    /// Delete a tag and all its links
+    pub fn delete_tag(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let change = RecordedChange::begin(&conn, &format!("Delete tag {}", id))?;
+        conn.execute("DELETE FROM tags WHERE id = ?", params![id])?;
+        change.finish()
+    }
+
+    /// Add a tag to activities and manual entries in one transaction;
+    /// returns how many were not tagged yet
+    pub fn tag_items(&self, tag_id: i64, activity_ids: &[i64], entry_ids: &[i64]) -> Result<usize> {
+        self.change_links(
+            &format!("Add tag {}", tag_id),
+            |(table, column)| format!("INSERT OR IGNORE INTO {} ({}, tag_id) VALUES (?, ?)", table, column),
+            tag_id,
+            activity_ids,
+            entry_ids,
+        )
+    }
+
+    /// Remove a tag from activities and manual entries in one transaction;
+    /// returns how many carried it
+    pub fn untag_items(&self, tag_id: i64, activity_ids: &[i64], entry_ids: &[i64]) -> Result<usize> {
+        self.change_links(
+            &format!("Remove tag {}", tag_id),
+            |(table, column)| format!("DELETE FROM {} WHERE {} = ? AND tag_id = ?", table, column),
+            tag_id,
+            activity_ids,
+            entry_ids,
+        )
+    }
+
+    /// Tag every activity starting in [start, end] that matches `filter`;
+    /// returns how many were not tagged yet
+    pub fn bulk_tag_activities(&self, tag_id: i64, start: i64, end: i64, filter: &ActivityFilter) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let (conditions, values) = filter.conditions();
+        let mut where_parts = vec!["started_at >= ?".to_string(), "started_at <= ?".to_string()];
+        where_parts.extend(conditions);
+        let mut params_vec = vec![
+            SqliteValue::Integer(tag_id),
+            SqliteValue::Integer(start),
+            SqliteValue::Integer(end),
+        ];
+        params_vec.extend(values);
+
+        let change = RecordedChange::begin(&conn, &format!("Add tag {} to matching activities", tag_id))?;
+        let added = conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO activity_tags (activity_id, tag_id)
+                 SELECT id, ? FROM activities WHERE {}",
+                where_parts.join(" AND ")
+            ),
+            params_from_iter(params_vec.iter()),
+        )?;
+        change.finish()?;
+        Ok(added)
+    }
+
+    /// Tags of the activities and manual entries starting in [start, end]
+    pub fn get_tag_links(&self, start: i64, end: i64) -> Result<TagLinks> {
+        let conn = self.conn.lock().unwrap();
+        let links = |sql: &str| -> Result<Vec<(i64, i64)>> {
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt.query_map(params![start, end], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        };
+        Ok(TagLinks {
+            activities: links(
+                "SELECT l.activity_id, l.tag_id FROM activity_tags l
+                 JOIN activities a ON a.id = l.activity_id
+                 WHERE a.started_at >= ? AND a.started_at <= ?
+                 ORDER BY l.activity_id, l.tag_id",
+            )?,
+            manual_entries: links(
+                "SELECT l.manual_entry_id, l.tag_id FROM manual_entry_tags l
+                 JOIN manual_entries m ON m.id = l.manual_entry_id
+                 WHERE m.started_at >= ? AND m.started_at <= ?
+                 ORDER BY l.manual_entry_id, l.tag_id",
+            )?,
+        })
+    }
+
+    /// Run the statement built for each link table once per item id
+    fn change_links(
+        &self,
+        summary: &str,
+        statement: fn((&str, &str)) -> String,
+        tag_id: i64,
+        activity_ids: &[i64],
+        entry_ids: &[i64],
+    ) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, summary)?;
+        let mut changed = 0;
+        for (links, ids) in [(ACTIVITY_LINKS, activity_ids), (MANUAL_ENTRY_LINKS, entry_ids)] {
+            let mut stmt = tx.prepare(&statement(links))?;
+            for id in ids {
+                changed += stmt.execute(params![id, tag_id])?;
+            }
+        }
+        change.finish()?;
+        tx.commit()?;
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::WindowContext;
+
+    fn activity(db: &Database, app: &str, start: i64, end: i64) -> i64 {
+        let id = db.start_activity(app, None, &WindowContext::default(), start).unwrap();
+        db.extend_activity(id, end).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_tags_filters_and_usage() {
+        let db = Database::open_in_memory().unwrap();
+        let review = db.create_tag("review", "#ff0000").unwrap();
+        let overtime = db.create_tag("overtime", "#00ff00").unwrap();
+        assert!(db.create_tag("Review", "#000000").unwrap_err().to_string().contains("already exists"));
+
+        let code = activity(&db, "Code", 1_000, 1_600);
+        let browser = activity(&db, "Firefox", 1_600, 1_900);
+        let terminal = activity(&db, "Terminal", 1_900, 2_000);
+        let entry = db.add_manual_entry(Some("Pairing"), None, 3_000, 3_400).unwrap();
+
+        // A missing entry fails the whole call, leaving the activities untagged too
+        assert!(db.tag_items(review, &[code], &[entry + 1]).is_err());
+        assert!(db.get_tag_links(0, 10_000).unwrap().activities.is_empty());
+        assert_eq!(db.tag_items(review, &[code, browser], &[entry]).unwrap(), 3);
+        assert_eq!(db.tag_items(review, &[code], &[]).unwrap(), 0);
+        // Everything but the browser, which is already reviewed
+        let filter = ActivityFilter { exclude_tags: vec![review], ..Default::default() };
+        assert_eq!(db.bulk_tag_activities(overtime, 0, 10_000, &filter).unwrap(), 1);
+
+        let ids = |filter: &ActivityFilter| -> Vec<i64> {
+            db.get_filtered_activities(0, 10_000, None, None, filter).unwrap().iter().map(|a| a.id).collect()
+        };
+        assert_eq!(ids(&ActivityFilter { include_tags: vec![review], ..Default::default() }), vec![code, browser]);
+        assert_eq!(ids(&ActivityFilter { exclude_tags: vec![overtime], ..Default::default() }), vec![code, browser]);
+        assert_eq!(
+            ids(&ActivityFilter { include_tags: vec![review], exclude_tags: vec![overtime], ..Default::default() }),
+            vec![code, browser]
+        );
+        assert_eq!(ids(&ActivityFilter { include_tags: vec![overtime], ..Default::default() }), vec![terminal]);
+
+        // Clipped to the range; manual entries count too
+        let usage: Vec<_> = db
+            .get_tag_usage(1_300, 5_000)
+            .unwrap()
+            .into_iter()
+            .map(|u| (u.tag.name, u.duration_sec))
+            .collect();
+        assert_eq!(usage, vec![("review".to_string(), 300 + 300 + 400), ("overtime".to_string(), 100)]);
+
+        assert_eq!(db.untag_items(review, &[browser, terminal], &[]).unwrap(), 1);
+        db.delete_activity(code).unwrap();
+        db.delete_manual_entry(entry).unwrap();
+        let links = db.get_tag_links(0, 10_000).unwrap();
+        assert_eq!(links.activities, vec![(terminal, overtime)]);
+        assert!(links.manual_entries.is_empty());
+
+        db.delete_tag(overtime).unwrap();
+        assert!(db.get_tag_links(0, 10_000).unwrap().activities.is_empty());
+        assert_eq!(db.get_tags().unwrap().len(), 1);
+    }
+}
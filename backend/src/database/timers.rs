@@ -0,0 +1,124 @@
+//! Running timer database operations
+//!
+//! A timer only becomes a manual entry when it is stopped, so a restart or crash
+//! leaves it running instead of leaving a zero-length entry behind.
+
+use rusqlite::{Result, params};
+use super::common::{Database, OptionalExtension, unique_violation};
+use super::history::RecordedChange;
+use super::models::{ManualEntry, RunningTimer};
+
+impl Database {
+    /// Get all running timers, oldest first
+    pub fn get_running_timers(&self) -> Result<Vec<RunningTimer>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, category_id, description, started_at
+             FROM running_timers
+             ORDER BY started_at, id",
+        )?;
+
+        let timers = stmt
+            .query_map([], |row| {
+                Ok(RunningTimer {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    category_id: row.get(2)?,
+                    description: row.get(3)?,
+                    started_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(timers)
+    }
+
+    /// Start a named timer
+    pub fn start_timer(
+        &self,
+        name: &str,
+        category_id: Option<i64>,
+        description: Option<&str>,
+        started_at: i64,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO running_timers (name, category_id, description, started_at)
+             VALUES (?, ?, ?, ?)",
+            params![name, category_id, description, started_at],
+        )
+        .map_err(|e| unique_violation(e, &format!("A timer named '{}' is already running", name)))?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Stop a timer, turning it into a manual entry that ends at `ended_at`.
+    /// Returns None if no such timer is running.
+    pub fn stop_timer(&self, id: i64, ended_at: i64) -> Result<Option<ManualEntry>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let change = RecordedChange::begin(&tx, &format!("Stop timer {}", id))?;
+        let timer = tx
+            .query_row(
+                "SELECT category_id, description, started_at FROM running_timers WHERE id = ?",
+                params![id],
+                |row| Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, i64>(2)?)),
+            )
+            .optional()?;
+        let Some((category_id, description, started_at)) = timer else {
+            return Ok(None);
+        };
+
+        let ended_at = ended_at.max(started_at);
+        tx.execute(
+            "INSERT INTO manual_entries (entry_type, description, category_id, started_at, ended_at)
+             VALUES ('', ?, ?, ?, ?)",
+            params![description, category_id, started_at, ended_at],
+        )?;
+        let entry_id = tx.last_insert_rowid();
+        tx.execute("DELETE FROM running_timers WHERE id = ?", params![id])?;
+        change.finish()?;
+        tx.commit()?;
+
+        Ok(Some(ManualEntry {
+            id: entry_id,
+            description,
+            category_id,
+            project_id: None,
+            started_at,
+            ended_at,
+        }))
+    }
+
+    /// Delete a running timer without recording it; returns whether it existed
+    pub fn discard_timer(&self, id: i64) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM running_timers WHERE id = ?", params![id])?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_timers() {
+        let db = Database::open_in_memory().unwrap();
+        let thinking = db.start_timer("Thinking mode", Some(-3), Some("Thinking mode"), 1_000).unwrap();
+        let call = db.start_timer("Client call", None, None, 2_000).unwrap();
+        let err = db.start_timer("Client call", None, None, 2_500).unwrap_err();
+        assert!(err.to_string().contains("already running"));
+
+        // Longer than a day: stopping must not depend on a lookup window
+        let entry = db.stop_timer(thinking, 1_000 + 3 * 86_400).unwrap().unwrap();
+        assert_eq!((entry.started_at, entry.ended_at), (1_000, 1_000 + 3 * 86_400));
+        assert_eq!(entry.category_id, Some(-3));
+        assert_eq!(db.get_manual_entries(0, i64::MAX).unwrap().len(), 1);
+        assert!(db.stop_timer(thinking, 5_000).unwrap().is_none());
+
+        assert!(db.discard_timer(call).unwrap());
+        assert!(!db.discard_timer(call).unwrap());
+        assert!(db.get_running_timers().unwrap().is_empty());
+        assert_eq!(db.get_manual_entries(0, i64::MAX).unwrap().len(), 1);
+    }
+}
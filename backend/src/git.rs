@@ -0,0 +1,70 @@
+//! Git repository detection - Finds the repository and branch enclosing a directory
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Repository enclosing a directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRepository {
+    /// Top-level directory of the working tree
+    pub root: PathBuf,
+    /// Checked-out branch; None for a detached HEAD
+    pub branch: Option<String>,
+}
+
+/// Find the innermost repository containing `dir`, reading `.git/HEAD` directly
+pub fn find_repository(dir: &Path) -> Option<GitRepository> {
+    let root = dir.ancestors().find(|ancestor| ancestor.join(".git").exists())?;
+    let git_dir = git_dir(root)?;
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok();
+    let branch = head
+        .as_deref()
+        .and_then(|head| head.trim().strip_prefix("ref: refs/heads/"))
+        .map(str::to_string);
+
+    Some(GitRepository {
+        root: root.to_path_buf(),
+        branch,
+    })
+}
+
+/// `.git` is a directory, or a file with a `gitdir:` line in worktrees and submodules
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = fs::read_to_string(&dot_git).ok()?;
+    let target = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(root.join(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branch_and_worktree_detection() {
+        let base = std::env::temp_dir().join(format!("git-detect-{}", std::process::id()));
+        let repo = base.join("shop");
+        fs::create_dir_all(repo.join(".git/worktrees/hotfix")).unwrap();
+        fs::create_dir_all(repo.join("src/cart")).unwrap();
+        fs::write(repo.join(".git/HEAD"), "ref: refs/heads/feature/checkout\n").unwrap();
+
+        let found = find_repository(&repo.join("src/cart")).unwrap();
+        assert_eq!(found.root, repo);
+        assert_eq!(found.branch.as_deref(), Some("feature/checkout"));
+
+        // Linked worktree with a detached HEAD
+        let worktree = base.join("shop-hotfix");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(worktree.join(".git"), format!("gitdir: {}\n", repo.join(".git/worktrees/hotfix").display())).unwrap();
+        fs::write(repo.join(".git/worktrees/hotfix/HEAD"), "3f2a9c1d0e4b5a6f7c8d9e0f1a2b3c4d5e6f7a8b\n").unwrap();
+        let found = find_repository(&worktree).unwrap();
+        assert_eq!(found.root, worktree);
+        assert_eq!(found.branch, None);
+
+        assert_eq!(find_repository(&base), None);
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -1,12 +1,32 @@
 //! Idle detection module - Monitors user activity to detect idle state using native APIs
 
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+use std::sync::{Arc, OnceLock};
+
 /// Idle monitor for detecting user inactivity
-pub struct IdleMonitor;
+pub struct IdleMonitor {
+    /// Source chosen by probing; cleared when it stops answering so the next query re-probes
+    #[cfg(target_os = "linux")]
+    source: Mutex<linux::SourceCache>,
+}
 
 impl IdleMonitor {
     /// Create a new idle monitor
     pub fn new() -> Self {
-        Self
+        Self {
+            #[cfg(target_os = "linux")]
+            source: Mutex::new(linux::SourceCache::default()),
+        }
+    }
+
+    /// Monitor shared by the tracker and the idle commands, so the source is probed once
+    pub fn shared() -> Arc<Self> {
+        static SHARED: OnceLock<Arc<IdleMonitor>> = OnceLock::new();
+        Arc::clone(SHARED.get_or_init(|| Arc::new(Self::new())))
     }
 
     /// Get the current idle time in seconds using native APIs
@@ -72,35 +92,32 @@ impl IdleMonitor {
     /// Get the current idle time in seconds (Linux)
     #[cfg(target_os = "linux")]
     pub fn get_idle_time(&self) -> u64 {
-        // Try xprintidle first (if available)
-        use std::process::Command;
-        let output = Command::new("xprintidle").output();
-
-        match output {
-            Ok(output) => {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                output_str
-                    .trim()
-                    .parse::<u64>()
-                    .map(|ms| ms / 1000)
-                    .unwrap_or(0)
-            }
-            Err(_) => {
-                // Fallback: try xssstate
-                let output = Command::new("xssstate").args(["-i"]).output();
-                match output {
-                    Ok(output) => {
-                        let output_str = String::from_utf8_lossy(&output.stdout);
-                        output_str
-                            .trim()
-                            .parse::<u64>()
-                            .map(|ms| ms / 1000)
-                            .unwrap_or(0)
-                    }
-                    Err(_) => 0,
-                }
+        let mut cache = self.source.lock().unwrap();
+        if let Some(source) = cache.source() {
+            if let Some(secs) = source.idle_seconds() {
+                return secs;
             }
+            cache.forget();
         }
+        0
+    }
+
+    /// Name of the idle source in use (Linux)
+    #[cfg(target_os = "linux")]
+    pub fn source_name(&self) -> &'static str {
+        self.source.lock().unwrap().source().map(|s| s.name()).unwrap_or("none")
+    }
+
+    /// Name of the idle source in use
+    #[cfg(target_os = "windows")]
+    pub fn source_name(&self) -> &'static str {
+        "GetLastInputInfo"
+    }
+
+    /// Name of the idle source in use (macOS)
+    #[cfg(target_os = "macos")]
+    pub fn source_name(&self) -> &'static str {
+        "ioreg"
     }
 
     /// Fallback for unsupported platforms
@@ -109,6 +126,12 @@ impl IdleMonitor {
         0
     }
 
+    /// Fallback for unsupported platforms
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    pub fn source_name(&self) -> &'static str {
+        "none"
+    }
+
     /// Check if user is currently idle (using default threshold of 120 seconds)
     #[allow(dead_code)]
     pub fn is_idle(&self) -> bool {
@@ -0,0 +1,367 @@
+//! Linux idle sources - Asks the desktop session for the time since last input
+//!
+//! Sources are probed in order and the first one that answers wins:
+//!
+//! 1. Mutter `org.gnome.Mutter.IdleMonitor.GetIdletime` (GNOME, X11 and Wayland)
+//! 2. `org.freedesktop.ScreenSaver.GetSessionIdleTime` (KDE and other freedesktop sessions)
+//! 3. `xprintidle` / `xssstate` (plain X11 window managers, when installed)
+//! 4. logind `IdleHint` / `IdleSinceHint` on the system bus
+//!
+//! logind comes last: it is available almost everywhere, but the hint is only
+//! maintained by some desktops and has the granularity of their idle timeout.
+//!
+//! When no source answers, probing is retried at most once a minute, over the
+//! bus connections opened by the first probe.
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use zbus::blocking::{proxy, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+
+/// Wait before probing again after no source answered
+const PROBE_RETRY: Duration = Duration::from_secs(60);
+
+/// Session and system bus connections, opened on first use and kept for later probes
+#[derive(Default)]
+pub struct Buses {
+    session: Option<Connection>,
+    system: Option<Connection>,
+}
+
+impl Buses {
+    fn session(&mut self) -> Option<&Connection> {
+        if self.session.is_none() {
+            self.session = Connection::session().ok();
+        }
+        self.session.as_ref()
+    }
+
+    fn system(&mut self) -> Option<&Connection> {
+        if self.system.is_none() {
+            self.system = Connection::system().ok();
+        }
+        self.system.as_ref()
+    }
+}
+
+/// The source in use, found by probing when needed
+#[derive(Default)]
+pub struct SourceCache {
+    source: Option<LinuxIdleSource>,
+    buses: Buses,
+    /// Earliest time to probe again after a probe found nothing
+    next_probe: Option<Instant>,
+}
+
+impl SourceCache {
+    /// Source to query; probes if there is none and no failed probe is recent
+    pub fn source(&mut self) -> Option<&LinuxIdleSource> {
+        self.source_at(Instant::now())
+    }
+
+    fn source_at(&mut self, now: Instant) -> Option<&LinuxIdleSource> {
+        if self.source.is_none() && self.next_probe.is_none_or(|at| now >= at) {
+            self.source = LinuxIdleSource::probe(&mut self.buses);
+            self.next_probe = self.source.is_none().then(|| now + PROBE_RETRY);
+        }
+        self.source.as_ref()
+    }
+
+    /// Drop a source that stopped answering; the next query probes again right away
+    pub fn forget(&mut self) {
+        self.source = None;
+        self.next_probe = None;
+    }
+}
+
+/// A working idle source
+pub enum LinuxIdleSource {
+    Mutter(Proxy<'static>),
+    ScreenSaver(Proxy<'static>),
+    Logind(Proxy<'static>),
+    Xprintidle,
+    Xssstate,
+}
+
+impl LinuxIdleSource {
+    /// Find the first source that answers on this system
+    pub fn probe(buses: &mut Buses) -> Option<Self> {
+        buses
+            .session()
+            .and_then(Self::mutter)
+            .or_else(|| buses.session().and_then(Self::screensaver))
+            .or_else(|| Self::verified(Self::Xprintidle))
+            .or_else(|| Self::verified(Self::Xssstate))
+            .or_else(|| buses.system().and_then(Self::logind))
+    }
+
+    /// Mutter IdleMonitor on the given (session) bus
+    pub fn mutter(conn: &Connection) -> Option<Self> {
+        let proxy = uncached_proxy(
+            conn,
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "org.gnome.Mutter.IdleMonitor",
+        )?;
+        Self::verified(Self::Mutter(proxy))
+    }
+
+    /// freedesktop ScreenSaver on the given (session) bus
+    pub fn screensaver(conn: &Connection) -> Option<Self> {
+        let proxy = uncached_proxy(
+            conn,
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            "org.freedesktop.ScreenSaver",
+        )?;
+        Self::verified(Self::ScreenSaver(proxy))
+    }
+
+    /// logind session of the calling process on the given (system) bus
+    pub fn logind(conn: &Connection) -> Option<Self> {
+        let proxy = uncached_proxy(
+            conn,
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1/session/auto",
+            "org.freedesktop.login1.Session",
+        )?;
+        Self::verified(Self::Logind(proxy))
+    }
+
+    /// Short name reported to the frontend
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mutter(_) => "mutter",
+            Self::ScreenSaver(_) => "screensaver",
+            Self::Logind(_) => "logind",
+            Self::Xprintidle => "xprintidle",
+            Self::Xssstate => "xssstate",
+        }
+    }
+
+    /// Seconds since the last user input, or None if the source stopped answering
+    pub fn idle_seconds(&self) -> Option<u64> {
+        match self {
+            Self::Mutter(proxy) => {
+                let ms: u64 = proxy.call("GetIdletime", &()).ok()?;
+                Some(ms / 1000)
+            }
+            Self::ScreenSaver(proxy) => {
+                // KDE answers in milliseconds; GNOME replies with NotSupported
+                let ms: u32 = proxy.call("GetSessionIdleTime", &()).ok()?;
+                Some(ms as u64 / 1000)
+            }
+            Self::Logind(proxy) => {
+                let idle: bool = proxy.get_property("IdleHint").ok()?;
+                if !idle {
+                    return Some(0);
+                }
+                // IdleSinceHint is CLOCK_REALTIME in microseconds
+                let since_us: u64 = proxy.get_property("IdleSinceHint").ok()?;
+                let now_us = chrono::Utc::now().timestamp_micros().max(0) as u64;
+                Some(now_us.saturating_sub(since_us) / 1_000_000)
+            }
+            Self::Xprintidle => command_millis("xprintidle", &[]).map(|ms| ms / 1000),
+            Self::Xssstate => command_millis("xssstate", &["-i"]).map(|ms| ms / 1000),
+        }
+    }
+
+    /// Keep the source only if it answers a first query
+    fn verified(source: Self) -> Option<Self> {
+        source.idle_seconds().map(|_| source)
+    }
+}
+
+/// Build a proxy that reads properties on every call instead of caching them
+fn uncached_proxy(
+    conn: &Connection,
+    destination: &'static str,
+    path: &'static str,
+    interface: &'static str,
+) -> Option<Proxy<'static>> {
+    proxy::Builder::<Proxy>::new(conn)
+        .destination(destination)
+        .ok()?
+        .path(path)
+        .ok()?
+        .interface(interface)
+        .ok()?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .ok()
+}
+
+/// Run a helper that prints the idle time in milliseconds
+fn command_millis(program: &str, args: &[&str]) -> Option<u64> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::interface;
+
+    use crate::sources::test_bus::TestBus;
+
+    macro_rules! test_bus {
+        () => {
+            match TestBus::start() {
+                Some(bus) => bus,
+                None => {
+                    eprintln!("Skipping D-Bus test: dbus-daemon is not available");
+                    return;
+                }
+            }
+        };
+    }
+
+    struct MockMutter;
+
+    #[interface(name = "org.gnome.Mutter.IdleMonitor")]
+    impl MockMutter {
+        fn get_idletime(&self) -> u64 {
+            42_500
+        }
+    }
+
+    struct MockScreenSaver {
+        supported: bool,
+    }
+
+    #[interface(name = "org.freedesktop.ScreenSaver")]
+    impl MockScreenSaver {
+        fn get_session_idle_time(&self) -> zbus::fdo::Result<u32> {
+            if self.supported {
+                Ok(7_000)
+            } else {
+                Err(zbus::fdo::Error::NotSupported("not supported".into()))
+            }
+        }
+    }
+
+    struct MockLogindSession {
+        idle_since: Option<u64>,
+    }
+
+    #[interface(name = "org.freedesktop.login1.Session")]
+    impl MockLogindSession {
+        #[zbus(property)]
+        fn idle_hint(&self) -> bool {
+            self.idle_since.is_some()
+        }
+
+        #[zbus(property)]
+        fn idle_since_hint(&self) -> u64 {
+            self.idle_since.unwrap_or(0)
+        }
+    }
+
+    #[test]
+    fn test_mutter_idle_monitor() {
+        let bus = test_bus!();
+        let _service = bus.serve(
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            MockMutter,
+        );
+
+        let source = LinuxIdleSource::mutter(&bus.connect()).expect("mutter source");
+        assert_eq!(source.name(), "mutter");
+        assert_eq!(source.idle_seconds(), Some(42));
+    }
+
+    #[test]
+    fn test_screensaver_idle_time() {
+        let bus = test_bus!();
+        let _service = bus.serve(
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            MockScreenSaver { supported: true },
+        );
+
+        let conn = bus.connect();
+        assert!(LinuxIdleSource::mutter(&conn).is_none());
+        let source = LinuxIdleSource::screensaver(&conn).expect("screensaver source");
+        assert_eq!(source.idle_seconds(), Some(7));
+    }
+
+    #[test]
+    fn test_unsupported_screensaver_is_skipped() {
+        let bus = test_bus!();
+        let _service = bus.serve(
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            MockScreenSaver { supported: false },
+        );
+
+        assert!(LinuxIdleSource::screensaver(&bus.connect()).is_none());
+    }
+
+    #[test]
+    fn test_logind_idle_hint() {
+        let bus = test_bus!();
+        let idle_since = (chrono::Utc::now().timestamp() - 300) as u64 * 1_000_000;
+        let _idle = bus.serve(
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1/session/auto",
+            MockLogindSession { idle_since: Some(idle_since) },
+        );
+
+        let source = LinuxIdleSource::logind(&bus.connect()).expect("logind source");
+        assert_eq!(source.name(), "logind");
+        let idle = source.idle_seconds().unwrap();
+        assert!((300..310).contains(&idle), "idle = {}", idle);
+    }
+
+    #[test]
+    fn test_logind_active_session_is_not_idle() {
+        let bus = test_bus!();
+        let _active = bus.serve(
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1/session/auto",
+            MockLogindSession { idle_since: None },
+        );
+
+        let source = LinuxIdleSource::logind(&bus.connect()).expect("logind source");
+        assert_eq!(source.idle_seconds(), Some(0));
+    }
+
+    #[test]
+    fn test_failed_probe_is_retried_after_a_while() {
+        let bus = test_bus!();
+        let mut cache = SourceCache {
+            buses: Buses { session: Some(bus.connect()), system: Some(bus.connect()) },
+            ..Default::default()
+        };
+        let start = Instant::now();
+        assert!(cache.source_at(start).is_none());
+
+        let _service = bus.serve(
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            MockMutter,
+        );
+        assert!(cache.source_at(start + Duration::from_secs(5)).is_none());
+        let source = cache.source_at(start + PROBE_RETRY).expect("mutter source");
+        assert_eq!(source.name(), "mutter");
+    }
+
+    #[test]
+    fn test_source_reports_failure_when_service_goes_away() {
+        let bus = test_bus!();
+        let service = bus.serve(
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            MockMutter,
+        );
+
+        let source = LinuxIdleSource::mutter(&bus.connect()).expect("mutter source");
+        drop(service);
+        assert_eq!(source.idle_seconds(), None);
+    }
+}
@@ -0,0 +1,375 @@
+//! Heartbeat ingestion - Local HTTP endpoint for external watchers (editors, terminals, browsers)
+//!
+//! Watchers send heartbeats describing what they currently see:
+//!
+//! ```text
+//! POST /api/v1/heartbeat
+//! Content-Type: application/json
+//!
+//! { "source": "vscode", "timestamp": 1700000000, "duration": 0, "pulsetime": 60,
+//!   "data": { "file": "src/main.rs", "project": "time-tracker", "branch": "main" } }
+//! ```
+//!
+//! Heartbeats are merged into events by `Database::insert_heartbeat`. The server only
+//! listens on the loopback interface and only accepts `application/json` bodies, which a
+//! web page cannot send cross-origin without a CORS preflight that is never answered.
+//!
+//! Heartbeat data passes through the privacy filter first: heartbeats of ignored apps and
+//! incognito tabs are acknowledged with `{"ignored": true}` and dropped, masked apps keep
+//! only the app name, and redactions apply to every string value.
+//!
+//! Every request must carry the per-install token as `Authorization: Bearer <token>`.
+//! The token is created on first start and written to `ingest-token` in the data
+//! directory, where watchers read it. Requests whose `Host` is not `127.0.0.1` or
+//! `localhost` are refused, so a DNS-rebound page cannot reach the endpoint either.
+
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+use serde::Deserialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::database::Database;
+use crate::privacy::PrivacyFilter;
+
+/// Port used when the `ingest_port` setting is not set
+pub const DEFAULT_PORT: u16 = 5670;
+
+/// Merge window used when a heartbeat does not specify one
+const DEFAULT_PULSETIME: i64 = 60;
+
+/// Largest accepted request body
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+/// Setting holding the per-install token
+const TOKEN_SETTING: &str = "ingest_token";
+
+/// File in the data directory the token is published in for watchers
+pub const TOKEN_FILE: &str = "ingest-token";
+
+/// Heartbeats may end at most this many seconds in the future (clock skew between processes)
+const MAX_FUTURE_SECS: i64 = 3600;
+
+/// Host names a local watcher addresses the server by
+const ALLOWED_HOSTS: [&str; 2] = ["127.0.0.1", "localhost"];
+
+/// Heartbeat request body
+#[derive(Debug, Deserialize)]
+struct Heartbeat {
+    source: String,
+    /// Unix timestamp in seconds
+    timestamp: i64,
+    #[serde(default)]
+    duration: i64,
+    pulsetime: Option<i64>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// Running ingestion server
+pub struct IngestServer {
+    addr: SocketAddr,
+}
+
+/// Get the per-install token, creating it on first use, and publish it in `data_dir`
+pub fn install_token(db: &Database, data_dir: &Path) -> Result<String, String> {
+    let token = match db.get_setting(TOKEN_SETTING).map_err(|e| e.to_string())? {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            db.set_setting(TOKEN_SETTING, &token).map_err(|e| e.to_string())?;
+            token
+        }
+    };
+    std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+    std::fs::write(data_dir.join(TOKEN_FILE), &token)
+        .map_err(|e| format!("Failed to write the heartbeat token: {}", e))?;
+    Ok(token)
+}
+
+impl IngestServer {
+    /// Listen on 127.0.0.1:`port` (0 picks a free port) and serve requests authenticated
+    /// with `token` on a background thread
+    pub fn start(db: Arc<Database>, port: u16, token: String) -> Result<Self, String> {
+        let server = Server::http(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to start heartbeat server on port {}: {}", port, e))?;
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| "Heartbeat server is not listening on TCP".to_string())?;
+
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let (status, body) = handle(&db, &token, &mut request);
+                let response = Response::from_string(body.to_string())
+                    .with_status_code(status)
+                    .with_header(json_header());
+                if let Err(e) = request.respond(response) {
+                    eprintln!("Failed to answer heartbeat request: {}", e);
+                }
+            }
+        });
+
+        Ok(Self { addr })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+/// Route a request and produce the status code and JSON body of the response
+fn handle(db: &Database, token: &str, request: &mut Request) -> (u16, serde_json::Value) {
+    let path = request.url().split('?').next().unwrap_or_default().to_string();
+    if path != "/api/v1/heartbeat" {
+        return error(404, "Not found");
+    }
+    if *request.method() != Method::Post {
+        return error(405, "Method not allowed");
+    }
+
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv(name))
+            .map(|h| h.value.as_str().trim().to_string())
+    };
+    let host = header("Host").unwrap_or_default();
+    let host_name = host.rsplit_once(':').map_or(host.as_str(), |(name, _)| name);
+    if !ALLOWED_HOSTS.iter().any(|allowed| host_name.eq_ignore_ascii_case(allowed)) {
+        return error(403, "Host not allowed");
+    }
+    let authorized = header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer ").map(|t| t.trim() == token))
+        .unwrap_or(false);
+    if !authorized {
+        return error(401, "Missing or invalid token");
+    }
+
+    let is_json = request.headers().iter().any(|h| {
+        h.field.equiv("Content-Type") && h.value.as_str().trim_start().starts_with("application/json")
+    });
+    if !is_json {
+        return error(415, "Content-Type must be application/json");
+    }
+
+    let mut body = String::new();
+    if request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .is_err()
+    {
+        return error(400, "Failed to read request body");
+    }
+
+    let heartbeat: Heartbeat = match serde_json::from_str(&body) {
+        Ok(heartbeat) => heartbeat,
+        Err(e) => return error(400, &format!("Invalid heartbeat: {}", e)),
+    };
+    if heartbeat.source.trim().is_empty() {
+        return error(400, "Heartbeat source must not be empty");
+    }
+    let pulsetime = heartbeat.pulsetime.unwrap_or(DEFAULT_PULSETIME);
+    if heartbeat.duration < 0 || pulsetime < 0 {
+        return error(400, "Heartbeat duration and pulsetime must not be negative");
+    }
+    let latest_end = chrono::Utc::now().timestamp() + MAX_FUTURE_SECS;
+    match heartbeat.timestamp.checked_add(heartbeat.duration) {
+        Some(end) if heartbeat.timestamp > 0 && end <= latest_end => {}
+        _ => return error(400, "Heartbeat timestamp is out of range"),
+    }
+
+    // Nothing from a watcher is stored before the privacy filter has seen it
+    let source = heartbeat.source.trim();
+    let Some(data) = PrivacyFilter::load(db).filter_event_data(source, &heartbeat.data) else {
+        return (200, serde_json::json!({ "ignored": true }));
+    };
+
+    match db.insert_heartbeat(source, heartbeat.timestamp, heartbeat.duration, &data, pulsetime) {
+        Ok(event) => (200, serde_json::to_value(event).unwrap_or_default()),
+        Err(e) => error(500, &e.to_string()),
+    }
+}
+
+fn error(status: u16, message: &str) -> (u16, serde_json::Value) {
+    (status, serde_json::json!({ "error": message }))
+}
+
+fn json_header() -> Header {
+    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    const T: i64 = 1_700_000_000;
+    const TOKEN: &str = "test-token";
+
+    /// Stand-in watcher: sends one request and returns the status and JSON body
+    fn send(addr: SocketAddr, method: &str, path: &str, content_type: &str, body: &str) -> (u16, serde_json::Value) {
+        let headers = format!("Host: {}\r\nAuthorization: Bearer {}\r\nContent-Type: {}", addr, TOKEN, content_type);
+        send_raw(addr, method, path, &headers, body)
+    }
+
+    fn send_raw(addr: SocketAddr, method: &str, path: &str, headers: &str, body: &str) -> (u16, serde_json::Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\n{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            headers,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response.split(' ').nth(1).unwrap().parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+        (status, serde_json::from_str(body).unwrap_or_default())
+    }
+
+    fn heartbeat(addr: SocketAddr, body: serde_json::Value) -> serde_json::Value {
+        let (status, event) = send(addr, "POST", "/api/v1/heartbeat", "application/json", &body.to_string());
+        assert_eq!(status, 200, "{}", event);
+        event
+    }
+
+    fn start_server() -> (Arc<Database>, SocketAddr) {
+        let db = Arc::new(Database::open_in_memory().unwrap());
+        let server = IngestServer::start(Arc::clone(&db), 0, TOKEN.to_string()).unwrap();
+        (db, server.local_addr())
+    }
+
+    #[test]
+    fn test_heartbeats_merge_within_pulsetime() {
+        let (db, addr) = start_server();
+        let file = |name: &str| serde_json::json!({ "file": name, "project": "tracker" });
+
+        let first = heartbeat(addr, serde_json::json!({ "source": "vim", "timestamp": T, "data": file("a.rs"), "pulsetime": 30 }));
+        let merged = heartbeat(addr, serde_json::json!({ "source": "vim", "timestamp": T + 20, "data": file("a.rs"), "pulsetime": 30 }));
+        assert_eq!(merged["id"], first["id"]);
+        assert_eq!((merged["started_at"].as_i64(), merged["ended_at"].as_i64()), (Some(T), Some(T + 20)));
+
+        // Different data starts a new event
+        let other_file = heartbeat(addr, serde_json::json!({ "source": "vim", "timestamp": T + 25, "data": file("b.rs"), "pulsetime": 30 }));
+        assert_ne!(other_file["id"], first["id"]);
+
+        // Same data after a pause longer than pulsetime starts a new event
+        let late = heartbeat(addr, serde_json::json!({ "source": "vim", "timestamp": T + 100, "data": file("b.rs"), "pulsetime": 30 }));
+        assert_ne!(late["id"], other_file["id"]);
+
+        // Other sources never merge with vim events
+        heartbeat(addr, serde_json::json!({ "source": "zsh", "timestamp": T + 101, "data": file("b.rs") }));
+
+        let events = db.get_events(T, T + 200, Some("vim")).unwrap();
+        let spans: Vec<_> = events.iter().map(|e| (e.started_at, e.ended_at, e.data["file"].clone())).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (T, T + 20, serde_json::json!("a.rs")),
+                (T + 25, T + 25, serde_json::json!("b.rs")),
+                (T + 100, T + 100, serde_json::json!("b.rs")),
+            ]
+        );
+        assert_eq!(db.get_events(T, T + 200, None).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_heartbeat_is_linked_to_concurrent_activity() {
+        let (db, addr) = start_server();
+        let activity_id = db.start_activity("Code", Some("main.rs"), &Default::default(), T).unwrap();
+        db.extend_activity(activity_id, T + 60).unwrap();
+
+        let inside = heartbeat(addr, serde_json::json!({ "source": "vscode", "timestamp": T + 30, "data": { "file": "main.rs" } }));
+        assert_eq!(inside["activity_id"].as_i64(), Some(activity_id));
+
+        let outside = heartbeat(addr, serde_json::json!({ "source": "zsh", "timestamp": T + 600, "data": { "cmd": "ls" } }));
+        assert!(outside["activity_id"].is_null());
+    }
+
+    #[test]
+    fn test_invalid_requests_are_rejected() {
+        let (db, addr) = start_server();
+        let valid = serde_json::json!({ "source": "vim", "timestamp": T }).to_string();
+
+        assert_eq!(send(addr, "POST", "/api/v1/heartbeat", "text/plain", &valid).0, 415);
+        assert_eq!(send(addr, "GET", "/api/v1/heartbeat", "application/json", "").0, 405);
+        assert_eq!(send(addr, "POST", "/api/v1/other", "application/json", &valid).0, 404);
+        assert_eq!(send(addr, "POST", "/api/v1/heartbeat", "application/json", "{not json").0, 400);
+        let (status, body) = send(
+            addr,
+            "POST",
+            "/api/v1/heartbeat",
+            "application/json",
+            &serde_json::json!({ "source": " ", "timestamp": T }).to_string(),
+        );
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("source"));
+
+        for (timestamp, duration) in [(T, -5), (i64::MAX - 1, 10), (-1, 0), (T * 2, 0)] {
+            let body = serde_json::json!({ "source": "vim", "timestamp": timestamp, "duration": duration });
+            assert_eq!(send(addr, "POST", "/api/v1/heartbeat", "application/json", &body.to_string()).0, 400);
+        }
+
+        assert!(db.get_events(0, i64::MAX, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_requests_need_token_and_local_host() {
+        let (db, addr) = start_server();
+        let valid = serde_json::json!({ "source": "vim", "timestamp": T }).to_string();
+        let post = |headers: String| send_raw(addr, "POST", "/api/v1/heartbeat", &headers, &valid).0;
+
+        assert_eq!(post("Host: 127.0.0.1\r\nContent-Type: application/json".to_string()), 401);
+        assert_eq!(post(format!("Host: {}\r\nAuthorization: Bearer wrong\r\nContent-Type: application/json", addr)), 401);
+        // A rebound name resolving to 127.0.0.1 is refused even with the token
+        assert_eq!(
+            post(format!("Host: evil.example:{}\r\nAuthorization: Bearer {}\r\nContent-Type: application/json", addr.port(), TOKEN)),
+            403
+        );
+        assert!(db.get_events(0, i64::MAX, None).unwrap().is_empty());
+
+        assert_eq!(
+            post(format!("Host: localhost:{}\r\nAuthorization: Bearer {}\r\nContent-Type: application/json", addr.port(), TOKEN)),
+            200
+        );
+    }
+
+    #[test]
+    fn test_privacy_filter_applies_to_heartbeats() {
+        let (db, addr) = start_server();
+        db.set_setting(crate::privacy::IGNORED_APPS_KEY, r#"["KeePassXC"]"#).unwrap();
+        db.set_setting(crate::privacy::MASKED_APPS_KEY, r#"["Signal"]"#).unwrap();
+
+        let ignored = heartbeat(addr, serde_json::json!({ "source": "window", "timestamp": T, "data": { "app": "KeePassXC", "title": "vault" } }));
+        assert_eq!(ignored, serde_json::json!({ "ignored": true }));
+        let masked = heartbeat(addr, serde_json::json!({ "source": "window", "timestamp": T + 1, "data": { "app": "Signal", "title": "Alice" } }));
+        assert_eq!(masked["data"], serde_json::json!({ "app": "Signal", "title": crate::privacy::MASKED_TITLE }));
+
+        let stored: Vec<_> = db.get_events(0, i64::MAX, None).unwrap().into_iter().map(|e| e.data).collect();
+        assert_eq!(stored, vec![masked["data"].clone()]);
+    }
+
+    #[test]
+    fn test_token_is_created_once_and_published() {
+        let db = Database::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join(format!("tt-ingest-token-{}", std::process::id()));
+        let token = install_token(&db, &dir).unwrap();
+        assert_eq!(token.len(), 32);
+        assert_eq!(install_token(&db, &dir).unwrap(), token);
+        assert_eq!(std::fs::read_to_string(dir.join(TOKEN_FILE)).unwrap(), token);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -4,8 +4,15 @@
 mod autostart;
 mod commands;
 mod database;
+mod git;
 mod idle;
+mod ingest;
 mod plugin_system;
+mod privacy;
+mod process;
+mod schedule;
+mod sources;
+mod title_parser;
 mod tracker;
 mod tray;
 mod window;
@@ -42,7 +49,6 @@ fn main() {
     let app_state = AppState {
         db: Arc::clone(&db),
         tracker: Arc::new(Mutex::new(None)),
-        thinking_mode_entry_id: Arc::new(Mutex::new(None)),
         plugin_registry: Some(Arc::clone(&plugin_registry)),
         extension_registry: Some(Arc::clone(&extension_registry)),
         plugin_loader: Some(Arc::clone(&plugin_loader_arc)),
@@ -87,6 +93,7 @@ fn main() {
             commands::create_category,
             commands::update_category,
             commands::delete_category,
+            commands::set_category_parent,
             commands::reset_system_category,
             commands::get_rules,
             commands::add_rule,
@@ -112,17 +119,69 @@ fn main() {
             commands::get_stats,
             commands::get_daily_stats,
             commands::get_top_apps,
+            commands::get_top_projects,
             commands::get_category_usage,
             commands::get_hourly_activity,
             commands::get_productive_time,
             commands::pause_tracking,
             commands::resume_tracking,
             commands::get_tracking_status,
+            commands::start_timer,
+            commands::stop_timer,
+            commands::list_timers,
+            commands::discard_timer,
+            commands::get_clients,
+            commands::create_client,
+            commands::update_client,
+            commands::delete_client,
+            commands::get_projects,
+            commands::create_project,
+            commands::update_project,
+            commands::delete_project,
+            commands::set_activity_project,
+            commands::set_manual_entry_project,
+            commands::get_tags,
+            commands::create_tag,
+            commands::update_tag,
+            commands::delete_tag,
+            commands::tag_items,
+            commands::untag_items,
+            commands::bulk_tag_activities,
+            commands::get_tag_links,
+            commands::get_category_rates,
+            commands::set_category_rate,
+            commands::delete_category_rate,
+            commands::set_category_billable,
+            commands::get_notes,
+            commands::get_activity_notes,
+            commands::add_activity_note,
+            commands::add_span_note,
+            commands::update_note,
+            commands::delete_note,
+            commands::search_notes,
+            commands::list_changes,
+            commands::undo_last_change,
+            commands::split_activity,
+            commands::merge_activities,
+            commands::trim_activity,
+            commands::bulk_edit_activities,
+            commands::split_manual_entry,
+            commands::merge_manual_entries,
+            commands::trim_manual_entry,
+            commands::get_tag_usage,
+            commands::get_schedule,
+            commands::set_schedule_enabled,
+            commands::add_schedule_window,
+            commands::delete_schedule_window,
+            commands::add_schedule_exception,
+            commands::delete_schedule_exception,
             commands::start_thinking_mode,
             commands::stop_thinking_mode,
             commands::get_idle_time,
+            commands::get_idle_source,
             commands::check_idle_state,
             commands::classify_idle_time,
+            commands::split_idle_time,
             commands::export_to_csv,
             commands::export_to_json,
             commands::show_main_window,
@@ -130,6 +189,9 @@ fn main() {
             commands::show_idle_prompt,
             // Domain commands
             commands::get_top_domains,
+            // Watcher event commands
+            commands::get_events,
+            commands::purge_excluded_data,
             // Plugin commands
             commands::get_plugin_registry,
             commands::search_plugins,
@@ -196,6 +258,22 @@ fn main() {
                 }
             });
 
+            // Accept heartbeats from external watchers unless disabled in settings
+            if db_clone.get_setting("ingest_enabled").ok().flatten().as_deref() != Some("false") {
+                let port = db_clone
+                    .get_setting("ingest_port")
+                    .ok()
+                    .flatten()
+                    .and_then(|v| v.parse::<u16>().ok())
+                    .unwrap_or(ingest::DEFAULT_PORT);
+                match ingest::install_token(&db_clone, &data_dir)
+                    .and_then(|token| ingest::IngestServer::start(Arc::clone(&db_clone), port, token))
+                {
+                    Ok(server) => eprintln!("Heartbeat ingestion listening on {}", server.local_addr()),
+                    Err(e) => eprintln!("Warning: {}", e),
+                }
+            }
+
             // Load plugins asynchronously in background thread (non-blocking)
             let app_state = app.state::<commands::AppState>();
             if let (Some(plugin_registry), Some(extension_registry), Some(plugin_loader)) = (
@@ -0,0 +1,273 @@
+//! Privacy filter - Decides what the tracker may store about a window before it is written
+//!
+//! Configured through settings (JSON arrays unless noted):
+//!
+//! - `privacy_ignored_apps`: apps that are never recorded
+//! - `privacy_masked_apps`: apps recorded with their title replaced by `[hidden]`
+//! - `privacy_title_redactions`: regular expressions whose matches are replaced by
+//!   `[redacted]` in titles, URLs and command lines
+//! - `privacy_skip_private_windows` (`"true"`/`"false"`, default true): never record
+//!   private or incognito browser windows
+//!
+//! App patterns use the same wildcards as categorization rules. Heartbeats from external
+//! watchers go through the same filter: their app is `data.app`, `data.browser` or the
+//! source, and their title is `data.title`.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::database::{matches_pattern, Database};
+use crate::tracker::is_browser;
+
+pub const IGNORED_APPS_KEY: &str = "privacy_ignored_apps";
+pub const MASKED_APPS_KEY: &str = "privacy_masked_apps";
+pub const TITLE_REDACTIONS_KEY: &str = "privacy_title_redactions";
+pub const SKIP_PRIVATE_WINDOWS_KEY: &str = "privacy_skip_private_windows";
+
+/// Title stored for windows of masked apps
+pub const MASKED_TITLE: &str = "[hidden]";
+
+/// Replacement for redacted title fragments
+const REDACTED: &str = "[redacted]";
+
+/// Heartbeat data keys naming the app a watcher reports on, kept when the data is masked
+const EVENT_APP_KEYS: &[&str] = &["app", "browser"];
+
+/// Title fragments browsers add to private windows
+const PRIVATE_WINDOW_MARKERS: &[&str] = &[
+    "private browsing",
+    "(incognito)",
+    "incognito -",
+    "(private)",
+    "inprivate",
+];
+
+/// What may be stored about a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivacyDecision {
+    /// Record nothing
+    Ignore,
+    /// Record the app and time, but not the title or anything derived from it
+    Mask,
+    /// Record the window with redactions applied
+    Keep,
+}
+
+/// Privacy settings compiled for matching
+#[derive(Debug, Clone)]
+pub struct PrivacyFilter {
+    ignored_apps: Vec<String>,
+    masked_apps: Vec<String>,
+    redactions: Vec<Regex>,
+    skip_private_windows: bool,
+}
+
+impl Default for PrivacyFilter {
+    fn default() -> Self {
+        Self {
+            ignored_apps: Vec::new(),
+            masked_apps: Vec::new(),
+            redactions: Vec::new(),
+            skip_private_windows: true,
+        }
+    }
+}
+
+impl PrivacyFilter {
+    /// Build the filter from a settings map; fails on malformed lists or invalid regexes
+    pub fn from_settings(settings: &HashMap<String, String>) -> Result<Self, String> {
+        let list = |key: &str| -> Result<Vec<String>, String> {
+            match settings.get(key) {
+                Some(value) if !value.trim().is_empty() => serde_json::from_str::<Vec<String>>(value)
+                    .map(|items| items.into_iter().filter(|item| !item.trim().is_empty()).collect())
+                    .map_err(|e| format!("Invalid {} setting: {}", key, e)),
+                _ => Ok(Vec::new()),
+            }
+        };
+
+        let redactions = list(TITLE_REDACTIONS_KEY)?
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid redaction pattern '{}': {}", pattern, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            ignored_apps: list(IGNORED_APPS_KEY)?,
+            masked_apps: list(MASKED_APPS_KEY)?,
+            redactions,
+            skip_private_windows: settings
+                .get(SKIP_PRIVATE_WINDOWS_KEY)
+                .map(|v| v != "false")
+                .unwrap_or(true),
+        })
+    }
+
+    /// Load the filter from the database; broken settings fall back to the defaults
+    pub fn load(db: &Database) -> Self {
+        let settings = db.get_all_settings().unwrap_or_default();
+        Self::from_settings(&settings).unwrap_or_else(|e| {
+            eprintln!("Warning: {}; using default privacy settings", e);
+            Self::default()
+        })
+    }
+
+    /// Decide what may be stored about a window
+    pub fn decide(&self, app_name: &str, window_title: Option<&str>) -> PrivacyDecision {
+        if self.ignored_apps.iter().any(|pattern| matches_pattern(app_name, pattern)) {
+            return PrivacyDecision::Ignore;
+        }
+        if self.skip_private_windows && is_private_window(app_name, window_title) {
+            return PrivacyDecision::Ignore;
+        }
+        if self.masked_apps.iter().any(|pattern| matches_pattern(app_name, pattern)) {
+            return PrivacyDecision::Mask;
+        }
+        PrivacyDecision::Keep
+    }
+
+    /// Apply the redaction patterns to a title, URL or command line
+    pub fn redact(&self, text: &str) -> String {
+        self.redactions
+            .iter()
+            .fold(text.to_string(), |text, regex| regex.replace_all(&text, REDACTED).into_owned())
+    }
+
+    /// Filter the data of a heartbeat from `source` the way windows are filtered: None when
+    /// nothing may be stored, only the app and a hidden title for masked apps, and
+    /// redactions applied to every string otherwise. Incognito tab reports count as
+    /// private windows.
+    pub fn filter_event_data(&self, source: &str, data: &serde_json::Value) -> Option<serde_json::Value> {
+        let app = EVENT_APP_KEYS
+            .iter()
+            .find_map(|key| data.get(*key).and_then(|v| v.as_str()))
+            .unwrap_or(source);
+        let title = data.get("title").and_then(|v| v.as_str());
+        if self.skip_private_windows && data.get("incognito").and_then(|v| v.as_bool()) == Some(true) {
+            return None;
+        }
+        match self.decide(app, title) {
+            PrivacyDecision::Ignore => None,
+            PrivacyDecision::Mask => {
+                let mut masked = serde_json::Map::new();
+                for key in EVENT_APP_KEYS {
+                    if let Some(value) = data.get(*key) {
+                        masked.insert(key.to_string(), value.clone());
+                    }
+                }
+                masked.insert("title".to_string(), MASKED_TITLE.into());
+                Some(serde_json::Value::Object(masked))
+            }
+            PrivacyDecision::Keep => Some(self.redact_value(data)),
+        }
+    }
+
+    fn redact_value(&self, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::String(text) => self.redact(text).into(),
+            serde_json::Value::Array(items) => items.iter().map(|item| self.redact_value(item)).collect(),
+            serde_json::Value::Object(fields) => fields
+                .iter()
+                .map(|(key, value)| (key.clone(), self.redact_value(value)))
+                .collect(),
+            other => other.clone(),
+        }
+    }
+}
+
+/// Browser window in private or incognito mode, recognised by its title
+fn is_private_window(app_name: &str, window_title: Option<&str>) -> bool {
+    if !is_browser(app_name) {
+        return false;
+    }
+    let Some(title) = window_title else {
+        return false;
+    };
+    let title = title.to_lowercase();
+    PRIVATE_WINDOW_MARKERS.iter().any(|marker| title.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(settings: &[(&str, &str)]) -> PrivacyFilter {
+        let settings = settings.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        PrivacyFilter::from_settings(&settings).unwrap()
+    }
+
+    #[test]
+    fn test_decisions() {
+        let filter = filter(&[
+            (IGNORED_APPS_KEY, r#"["KeePassXC", "*banking*"]"#),
+            (MASKED_APPS_KEY, r#"["Signal", "Telegram*"]"#),
+        ]);
+        let cases = [
+            ("KeePassXC", Some("Passwords.kdbx"), PrivacyDecision::Ignore),
+            ("mybanking-app", None, PrivacyDecision::Ignore),
+            ("signal", Some("Alice"), PrivacyDecision::Mask),
+            ("TelegramDesktop", Some("Bob"), PrivacyDecision::Mask),
+            ("firefox", Some("Bank — Mozilla Firefox Private Browsing"), PrivacyDecision::Ignore),
+            ("Google-chrome", Some("New Tab - Google Chrome (Incognito)"), PrivacyDecision::Ignore),
+            ("msedge.exe", Some("Search - [InPrivate] - Microsoft Edge"), PrivacyDecision::Ignore),
+            ("firefox", Some("Mozilla Firefox"), PrivacyDecision::Keep),
+            // Only browsers have private windows
+            ("Code", Some("private browsing.md - notes - Visual Studio Code"), PrivacyDecision::Keep),
+        ];
+        for (app, title, expected) in cases {
+            assert_eq!(filter.decide(app, title), expected, "{} / {:?}", app, title);
+        }
+    }
+
+    #[test]
+    fn test_private_windows_can_be_recorded() {
+        let filter = filter(&[(SKIP_PRIVATE_WINDOWS_KEY, "false")]);
+        assert_eq!(
+            filter.decide("firefox", Some("Mozilla Firefox Private Browsing")),
+            PrivacyDecision::Keep
+        );
+    }
+
+    #[test]
+    fn test_redactions() {
+        let filter = filter(&[(TITLE_REDACTIONS_KEY, r#"["[\\w.+-]+@[\\w-]+\\.[\\w.]+", "(?i)invoice #\\d+"]"#)]);
+        assert_eq!(
+            filter.redact("Re: Invoice #4471 from billing@acme.com - Thunderbird"),
+            "Re: [redacted] from [redacted] - Thunderbird"
+        );
+        assert_eq!(filter.redact("Inbox"), "Inbox");
+    }
+
+    #[test]
+    fn test_heartbeat_data_is_filtered() {
+        let filter = filter(&[
+            (IGNORED_APPS_KEY, r#"["KeePassXC"]"#),
+            (MASKED_APPS_KEY, r#"["Signal", "vim"]"#),
+            (TITLE_REDACTIONS_KEY, r#"["token=\\w+"]"#),
+        ]);
+        let event = |source: &str, data: serde_json::Value| filter.filter_event_data(source, &data);
+
+        assert_eq!(event("watcher", serde_json::json!({ "app": "KeePassXC", "title": "vault" })), None);
+        assert_eq!(event("browser", serde_json::json!({ "browser": "firefox", "title": null, "incognito": true })), None);
+        assert_eq!(
+            event("watcher", serde_json::json!({ "app": "Signal", "title": "Alice", "url": "sgnl://alice" })),
+            Some(serde_json::json!({ "app": "Signal", "title": MASKED_TITLE }))
+        );
+        // Without an app in the data the source is the app
+        assert_eq!(
+            event("vim", serde_json::json!({ "file": "/home/me/diary.md" })),
+            Some(serde_json::json!({ "title": MASKED_TITLE }))
+        );
+        assert_eq!(
+            event("browser", serde_json::json!({ "browser": "firefox", "url": "https://x.test/?token=abc", "tags": ["token=abc"] })),
+            Some(serde_json::json!({ "browser": "firefox", "url": "https://x.test/?[redacted]", "tags": ["[redacted]"] }))
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        let settings = HashMap::from([(TITLE_REDACTIONS_KEY.to_string(), r#"["(unclosed"]"#.to_string())]);
+        assert!(PrivacyFilter::from_settings(&settings).unwrap_err().contains("(unclosed"));
+        let settings = HashMap::from([(IGNORED_APPS_KEY.to_string(), "Slack".to_string())]);
+        assert!(PrivacyFilter::from_settings(&settings).is_err());
+    }
+}
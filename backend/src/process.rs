@@ -0,0 +1,260 @@
+//! Process inspection - Executable, command line and working directory of window owners
+//!
+//! On Linux the details come from `/proc/<pid>`. The executable and command line of a
+//! process do not change, so they are cached per pid (keyed on the process start time,
+//! so a reused pid is not mistaken for the old process); the working directory is read
+//! on every lookup. For terminal emulators the working directory is taken from the
+//! program running inside the terminal, e.g. the shell or an editor.
+//!
+//! Other platforms report no details yet.
+
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+#[cfg(target_os = "linux")]
+use std::fs;
+
+/// Details of the process owning the focused window
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessDetails {
+    pub exe_path: Option<String>,
+    /// Arguments joined with spaces
+    pub cmdline: Option<String>,
+    pub cwd: Option<String>,
+}
+
+/// Terminal emulators whose working directory is not the interesting one
+#[cfg(target_os = "linux")]
+const TERMINALS: &[&str] = &[
+    "gnome-terminal-server",
+    "kgx",
+    "konsole",
+    "xfce4-terminal",
+    "mate-terminal",
+    "tilix",
+    "terminator",
+    "alacritty",
+    "kitty",
+    "wezterm-gui",
+    "foot",
+    "xterm",
+    "urxvt",
+    "st",
+];
+
+/// Number of cached processes above which exited ones are dropped
+#[cfg(target_os = "linux")]
+const CACHE_PRUNE_THRESHOLD: usize = 64;
+
+#[cfg(target_os = "linux")]
+struct CachedProcess {
+    start_time: u64,
+    exe_path: Option<String>,
+    cmdline: Option<String>,
+}
+
+/// Looks up process details, caching what does not change per pid
+#[derive(Default)]
+pub struct ProcessInspector {
+    #[cfg(target_os = "linux")]
+    cache: HashMap<u32, CachedProcess>,
+}
+
+impl ProcessInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Details of a running process; fields that cannot be read are None
+    #[cfg(target_os = "linux")]
+    pub fn inspect(&mut self, pid: u32) -> ProcessDetails {
+        let Some(start_time) = start_time(pid) else {
+            return ProcessDetails::default();
+        };
+
+        let cached_is_current = self.cache.get(&pid).is_some_and(|cached| cached.start_time == start_time);
+        if !cached_is_current {
+            if self.cache.len() >= CACHE_PRUNE_THRESHOLD {
+                self.cache.retain(|pid, cached| start_time_matches(*pid, cached.start_time));
+            }
+            self.cache.insert(
+                pid,
+                CachedProcess {
+                    start_time,
+                    exe_path: exe_path(pid),
+                    cmdline: cmdline(pid),
+                },
+            );
+        }
+        let cached = &self.cache[&pid];
+
+        let is_terminal = cached
+            .exe_path
+            .as_deref()
+            .and_then(|path| path.rsplit('/').next())
+            .is_some_and(|name| TERMINALS.contains(&name));
+        let cwd_pid = if is_terminal { foreground_descendant(pid) } else { pid };
+
+        ProcessDetails {
+            exe_path: cached.exe_path.clone(),
+            cmdline: cached.cmdline.clone(),
+            cwd: cwd(cwd_pid).or_else(|| cwd(pid)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn inspect(&mut self, _pid: u32) -> ProcessDetails {
+        ProcessDetails::default()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn exe_path(pid: u32) -> Option<String> {
+    let path = fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+    Some(path.to_string_lossy().trim_end_matches(" (deleted)").to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn cmdline(pid: u32) -> Option<String> {
+    let raw = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let args: Vec<String> = raw
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!args.is_empty()).then(|| args.join(" "))
+}
+
+#[cfg(target_os = "linux")]
+fn cwd(pid: u32) -> Option<String> {
+    let path = fs::read_link(format!("/proc/{}/cwd", pid)).ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Field of `/proc/<pid>/stat`, numbered as in proc(5) from field 3 (state) on
+#[cfg(target_os = "linux")]
+fn stat_field(pid: u32, field: usize) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name in field 2 may contain spaces and parentheses
+    let after_name = &stat[stat.rfind(')')? + 1..];
+    after_name.split_whitespace().nth(field.checked_sub(3)?)?.parse().ok()
+}
+
+/// Start time of a process in clock ticks since boot
+#[cfg(target_os = "linux")]
+fn start_time(pid: u32) -> Option<u64> {
+    stat_field(pid, 22)
+}
+
+#[cfg(target_os = "linux")]
+fn start_time_matches(pid: u32, expected: u64) -> bool {
+    start_time(pid) == Some(expected)
+}
+
+/// Most recently started leaf of the process tree below `pid`: in a terminal, the
+/// program in the foreground (or the shell of the newest tab)
+#[cfg(target_os = "linux")]
+fn foreground_descendant(pid: u32) -> u32 {
+    let mut current = pid;
+    // Bounded in case the tree changes while it is walked
+    for _ in 0..16 {
+        let newest = children(current)
+            .into_iter()
+            .filter_map(|child| Some((start_time(child)?, child)))
+            .max();
+        match newest {
+            Some((_, child)) => current = child,
+            None => break,
+        }
+    }
+    current
+}
+
+/// Direct children of a process, from every thread's `children` list, or by scanning
+/// all processes for their parent on kernels built without `CONFIG_PROC_CHILDREN`
+#[cfg(target_os = "linux")]
+fn children(pid: u32) -> Vec<u32> {
+    let lists: Vec<String> = fs::read_dir(format!("/proc/{}/task", pid))
+        .map(|tasks| {
+            tasks
+                .flatten()
+                .filter_map(|task| fs::read_to_string(task.path().join("children")).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !lists.is_empty() {
+        return lists
+            .iter()
+            .flat_map(|list| list.split_whitespace().filter_map(|child| child.parse().ok()))
+            .collect();
+    }
+
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    processes
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|&candidate| stat_field(candidate, 4) == Some(pid as u64))
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_inspect_own_process() {
+        let mut inspector = ProcessInspector::new();
+        let details = inspector.inspect(std::process::id());
+
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(details.exe_path.as_deref(), Some(exe.to_string_lossy().as_ref()));
+        let arg0 = std::env::args().next().unwrap();
+        assert!(details.cmdline.unwrap().starts_with(&arg0));
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(details.cwd.as_deref(), Some(cwd.to_string_lossy().as_ref()));
+        assert_eq!(inspector.cache.len(), 1);
+    }
+
+    #[test]
+    fn test_foreground_descendant_is_newest_leaf() {
+        // Stands in for a terminal running a shell that runs a program
+        let dir = std::env::temp_dir();
+        let mut shell = Command::new("sh")
+            .args(["-c", "sleep 30; true"])
+            .current_dir(&dir)
+            .spawn()
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut descendant = foreground_descendant(shell.id());
+        while descendant == shell.id() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+            descendant = foreground_descendant(shell.id());
+        }
+        let program = (cmdline(descendant), cwd(descendant));
+        if descendant != shell.id() {
+            kill(descendant);
+        }
+        shell.kill().ok();
+        shell.wait().unwrap();
+
+        assert_ne!(descendant, shell.id());
+        assert_eq!(program.0.as_deref(), Some("sleep 30"));
+        assert_eq!(program.1, Some(dir.to_string_lossy().trim_end_matches('/').to_string()));
+    }
+
+    fn kill(pid: u32) {
+        Command::new("kill").arg(pid.to_string()).status().ok();
+    }
+
+    #[test]
+    fn test_exited_process_has_no_details() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert_eq!(ProcessInspector::new().inspect(pid), ProcessDetails::default());
+    }
+}
@@ -0,0 +1,256 @@
+//! Work-hours schedule - Decides whether tracking should run at a given local time
+//!
+//! The schedule is enforced only while the `schedule_enabled` setting is `"true"`.
+//! Tracking then runs inside the weekly windows, except on dates with an exception:
+//! a `holiday` turns tracking off for the whole day, and `workday` exceptions replace
+//! that day's weekly windows with their own hours.
+//!
+//! Hours whose start is after their end cross midnight: they run from the start to the
+//! end of their day and on into the next day until the end. The part after midnight
+//! belongs to the day the hours are set for, so it is kept when the next day has
+//! exceptions of its own, but not when the next day is a holiday.
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+use crate::database::{Database, ScheduleException, ScheduleWindow};
+
+pub const SCHEDULE_ENABLED_KEY: &str = "schedule_enabled";
+
+pub const HOLIDAY: &str = "holiday";
+pub const WORKDAY: &str = "workday";
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// Weekly windows and exceptions loaded from the database
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    enabled: bool,
+    windows: Vec<ScheduleWindow>,
+    exceptions: Vec<ScheduleException>,
+}
+
+impl Schedule {
+    pub fn new(enabled: bool, windows: Vec<ScheduleWindow>, exceptions: Vec<ScheduleException>) -> Self {
+        Self { enabled, windows, exceptions }
+    }
+
+    /// Load the schedule from the database; read errors disable it
+    pub fn load(db: &Database) -> Self {
+        let enabled = db.get_setting(SCHEDULE_ENABLED_KEY).ok().flatten().as_deref() == Some("true");
+        match (db.get_schedule_windows(), db.get_schedule_exceptions()) {
+            (Ok(windows), Ok(exceptions)) => Self::new(enabled, windows, exceptions),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("Warning: Failed to load schedule: {}; tracking is not restricted", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether tracking may run at a local time; always true while disabled
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let minute = i64::from(at.hour() * 60 + at.minute());
+        let date = at.date();
+        if self.exceptions_on(date).any(|e| e.kind == HOLIDAY) {
+            return false;
+        }
+
+        let today = self.hours_on(date).iter().any(|&(start, end)| {
+            if start < end {
+                (start..end).contains(&minute)
+            } else {
+                minute >= start
+            }
+        });
+        let from_yesterday = date.pred_opt().is_some_and(|yesterday| {
+            self.hours_on(yesterday)
+                .iter()
+                .any(|&(start, end)| start > end && minute < end)
+        });
+        today || from_yesterday
+    }
+
+    fn exceptions_on(&self, date: NaiveDate) -> impl Iterator<Item = &ScheduleException> {
+        let date = date.format("%Y-%m-%d").to_string();
+        self.exceptions.iter().filter(move |e| e.date == date)
+    }
+
+    /// Hours set for a day as (start, end) minutes: its exceptions' hours if it has any,
+    /// its weekly windows otherwise
+    fn hours_on(&self, date: NaiveDate) -> Vec<(i64, i64)> {
+        let exceptions: Vec<&ScheduleException> = self.exceptions_on(date).collect();
+        if !exceptions.is_empty() {
+            return exceptions
+                .iter()
+                .filter_map(|e| Some((e.start_minute?, e.end_minute?)))
+                .collect();
+        }
+        let weekday = i64::from(date.weekday().num_days_from_monday());
+        self.windows
+            .iter()
+            .filter(|w| w.weekday == weekday)
+            .map(|w| (w.start_minute, w.end_minute))
+            .collect()
+    }
+
+    /// Whether tracking may run at a unix timestamp, in the local time zone
+    pub fn contains_timestamp(&self, timestamp: i64) -> bool {
+        match Local.timestamp_opt(timestamp, 0).earliest() {
+            Some(local) => self.contains(local.naive_local()),
+            None => true,
+        }
+    }
+}
+
+/// Check a weekly window before it is stored
+pub fn validate_window(weekday: i64, start_minute: i64, end_minute: i64) -> Result<(), String> {
+    if !(0..7).contains(&weekday) {
+        return Err(format!("Invalid weekday {}: expected 0 (Monday) to 6 (Sunday)", weekday));
+    }
+    validate_hours(start_minute, end_minute)
+}
+
+/// Check an exception before it is stored
+pub fn validate_exception(
+    date: &str,
+    kind: &str,
+    start_minute: Option<i64>,
+    end_minute: Option<i64>,
+) -> Result<(), String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date '{}': {}", date, e))?;
+    match (kind, start_minute, end_minute) {
+        (HOLIDAY, None, None) => Ok(()),
+        (HOLIDAY, _, _) => Err("Holidays cover the whole day and take no hours".to_string()),
+        (WORKDAY, Some(start), Some(end)) => validate_hours(start, end),
+        (WORKDAY, _, _) => Err("Workday exceptions need a start and an end".to_string()),
+        _ => Err(format!("Invalid exception kind '{}': expected '{}' or '{}'", kind, HOLIDAY, WORKDAY)),
+    }
+}
+
+/// Hours are minutes since midnight; a start after the end crosses midnight
+fn validate_hours(start_minute: i64, end_minute: i64) -> Result<(), String> {
+    if !(0..MINUTES_PER_DAY).contains(&start_minute)
+        || !(0..=MINUTES_PER_DAY).contains(&end_minute)
+        || start_minute == end_minute
+    {
+        return Err(format!(
+            "Invalid hours {}-{}: expected different start and end minutes since midnight",
+            start_minute, end_minute
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(weekday: i64, start: i64, end: i64) -> ScheduleWindow {
+        ScheduleWindow { id: 0, weekday, start_minute: start, end_minute: end }
+    }
+
+    fn exception(date: &str, kind: &str, hours: Option<(i64, i64)>) -> ScheduleException {
+        ScheduleException {
+            id: 0,
+            date: date.to_string(),
+            kind: kind.to_string(),
+            start_minute: hours.map(|h| h.0),
+            end_minute: hours.map(|h| h.1),
+            label: None,
+        }
+    }
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_windows_and_exceptions() {
+        // Monday to Friday 9:00-17:00 with a lunch break on Fridays
+        let mut windows: Vec<_> = (0..4).map(|day| window(day, 9 * 60, 17 * 60)).collect();
+        windows.push(window(4, 9 * 60, 12 * 60));
+        windows.push(window(4, 13 * 60, 17 * 60));
+        let schedule = Schedule::new(
+            true,
+            windows,
+            vec![
+                exception("2024-12-25", HOLIDAY, None),
+                exception("2024-12-28", WORKDAY, Some((10 * 60, 14 * 60))),
+            ],
+        );
+
+        let cases = [
+            ("2024-12-23 09:00", true), // Monday, start is inclusive
+            ("2024-12-23 16:59", true),
+            ("2024-12-23 17:00", false), // end is exclusive
+            ("2024-12-23 21:30", false),
+            ("2024-12-27 12:30", false), // Friday lunch
+            ("2024-12-27 13:00", true),
+            ("2024-12-25 10:00", false), // holiday on a Wednesday
+            ("2024-12-28 11:00", true),  // Saturday turned into a workday
+            ("2024-12-28 15:00", false),
+            ("2024-12-29 11:00", false), // Sunday
+        ];
+        for (time, expected) in cases {
+            assert_eq!(schedule.contains(at(time)), expected, "{}", time);
+        }
+    }
+
+    #[test]
+    fn test_windows_crossing_midnight() {
+        // Friday night shift 22:00-06:00, a Sunday holiday and a Monday night workday
+        let schedule = Schedule::new(
+            true,
+            vec![window(4, 22 * 60, 6 * 60), window(5, 22 * 60, 2 * 60)],
+            vec![
+                exception("2024-12-29", HOLIDAY, None),
+                exception("2024-12-30", WORKDAY, Some((23 * 60, 60))),
+            ],
+        );
+
+        let cases = [
+            ("2024-12-27 21:59", false),
+            ("2024-12-27 22:00", true), // Friday
+            ("2024-12-28 05:59", true), // Saturday morning, still Friday's shift
+            ("2024-12-28 06:00", false),
+            ("2024-12-28 23:00", true),
+            ("2024-12-29 01:00", false), // Saturday's shift does not run into the holiday
+            ("2024-12-30 23:30", true),
+            ("2024-12-31 00:30", true),
+            ("2024-12-31 01:00", false),
+        ];
+        for (time, expected) in cases {
+            assert_eq!(schedule.contains(at(time)), expected, "{}", time);
+        }
+    }
+
+    #[test]
+    fn test_disabled_schedule_allows_everything() {
+        let schedule = Schedule::new(false, vec![window(0, 9 * 60, 17 * 60)], vec![]);
+        assert!(schedule.contains(at("2024-12-29 03:00")));
+        assert!(Schedule::default().contains_timestamp(0));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(validate_window(6, 0, 1440).is_ok());
+        assert!(validate_window(7, 0, 60).is_err());
+        assert!(validate_window(0, 600, 600).is_err());
+        assert!(validate_window(4, 22 * 60, 6 * 60).is_ok());
+        assert!(validate_window(4, 1440, 60).is_err());
+        assert!(validate_window(4, 600, 1441).is_err());
+        assert!(validate_exception("2024-12-28", WORKDAY, Some(1380), Some(60)).is_ok());
+        assert!(validate_exception("2024-12-25", HOLIDAY, None, None).is_ok());
+        assert!(validate_exception("2024-12-25", HOLIDAY, Some(0), Some(60)).is_err());
+        assert!(validate_exception("2024-12-28", WORKDAY, Some(600), None).is_err());
+        assert!(validate_exception("28.12.2024", WORKDAY, Some(600), Some(840)).is_err());
+        assert!(validate_exception("2024-12-28", "vacation", None, None).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Tracker input sources - Abstractions over window detection, idle detection and time
+//!
+//! The tracking loop only talks to these traits, so the native implementations
+//! can be swapped for the scripted replay source in tests.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::idle::IdleMonitor;
+use crate::window::{WindowInfo, WindowTracker};
+
+#[cfg(target_os = "linux")]
+mod inhibit;
+#[cfg(target_os = "linux")]
+mod logind;
+#[cfg(test)]
+pub mod replay;
+#[cfg(all(test, target_os = "linux"))]
+pub mod test_bus;
+
+/// Source of the currently focused window
+pub trait WindowSource: Send + Sync {
+    /// Get the currently active window, if any
+    fn active_window(&self) -> Option<WindowInfo>;
+}
+
+/// Source of user idle time
+pub trait IdleSource: Send + Sync {
+    /// Seconds since the last user input
+    fn idle_seconds(&self) -> u64;
+
+    /// Name of the backend answering idle queries
+    fn name(&self) -> &'static str;
+}
+
+/// Source of reasons to keep counting time while there is no input,
+/// such as media playback or an application inhibiting the screensaver
+pub trait IdleInhibitSource: Send + Sync {
+    /// Why idle detection is suppressed right now (e.g. `media:vlc`), or None
+    fn active_inhibitor(&self) -> Option<String>;
+}
+
+/// Time source for the tracking loop
+pub trait Clock: Send + Sync {
+    /// Current Unix timestamp in seconds
+    fn now(&self) -> i64;
+
+    /// Time elapsed on a clock that does not advance while the machine is suspended
+    fn monotonic(&self) -> Duration;
+
+    /// Block until the next tick
+    fn sleep(&self, duration: Duration);
+}
+
+impl WindowSource for WindowTracker {
+    fn active_window(&self) -> Option<WindowInfo> {
+        self.get_active_window()
+    }
+}
+
+impl IdleSource for IdleMonitor {
+    fn idle_seconds(&self) -> u64 {
+        self.get_idle_time()
+    }
+
+    fn name(&self) -> &'static str {
+        self.source_name()
+    }
+}
+
+/// Desktop session change that interrupts tracking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEvent {
+    Suspend,
+    Resume,
+    Lock,
+    Unlock,
+}
+
+/// Source of suspend/resume and lock/unlock notifications
+pub trait SessionEventSource: Send + Sync {
+    /// Events received since the last call, with the Unix timestamp each one happened at
+    fn take_events(&self) -> Vec<(i64, SessionEvent)>;
+}
+
+/// Session event source for platforms without a notification service
+pub struct NoSessionEvents;
+
+impl SessionEventSource for NoSessionEvents {
+    fn take_events(&self) -> Vec<(i64, SessionEvent)> {
+        Vec::new()
+    }
+}
+
+/// Session events from the platform's session manager (logind on Linux)
+pub fn native_session_events() -> Arc<dyn SessionEventSource> {
+    #[cfg(target_os = "linux")]
+    if let Some(events) = logind::LogindEvents::connect() {
+        return Arc::new(events);
+    }
+    Arc::new(NoSessionEvents)
+}
+
+/// Idle inhibitor source for platforms without one
+pub struct NoIdleInhibitors;
+
+impl IdleInhibitSource for NoIdleInhibitors {
+    fn active_inhibitor(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Media players and inhibit requests on the desktop session bus (Linux)
+pub fn native_idle_inhibitors() -> Arc<dyn IdleInhibitSource> {
+    #[cfg(target_os = "linux")]
+    if let Some(inhibitors) = inhibit::DesktopInhibitors::connect() {
+        return Arc::new(inhibitors);
+    }
+    Arc::new(NoIdleInhibitors)
+}
+
+/// Wall clock backed by the system time
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    /// `Instant` uses CLOCK_MONOTONIC on Linux and CLOCK_UPTIME_RAW on macOS,
+    /// neither of which counts time spent suspended
+    fn monotonic(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
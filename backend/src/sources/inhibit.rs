@@ -0,0 +1,172 @@
+//! Desktop idle inhibitors - Media playback and inhibit requests on the session bus
+//!
+//! - MPRIS players (`org.mpris.MediaPlayer2.*`) whose `PlaybackStatus` is `Playing`
+//! - Idle inhibits held by the desktop's power or session manager, asked through
+//!   `org.freedesktop.PowerManagement.Inhibit.HasInhibit` (KDE, Xfce) and
+//!   `org.gnome.SessionManager.IsInhibited` with the idle flag (GNOME)
+//!
+//! `org.freedesktop.ScreenSaver` itself cannot be queried: the spec only has `Inhibit`
+//! and `UnInhibit`, with no way to list or count the cookies handed out. Its inhibits
+//! (video players, browsers playing video, presentation and call apps) are therefore
+//! only seen on desktops that forward them to one of the services above, as KDE and
+//! GNOME do; elsewhere they go unnoticed.
+
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::{proxy, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+
+use super::IdleInhibitSource;
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// `GsmInhibitorFlag` for inhibiting the session being marked idle
+const GNOME_INHIBIT_IDLE: u32 = 8;
+
+/// Inhibitors queried on the session bus at every call
+pub struct DesktopInhibitors {
+    conn: Connection,
+}
+
+impl DesktopInhibitors {
+    /// Use the session bus; None if there is none
+    pub fn connect() -> Option<Self> {
+        Some(Self::with_connection(Connection::session().ok()?))
+    }
+
+    pub fn with_connection(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    /// Name of the first MPRIS player that is playing, e.g. `vlc` or `firefox`
+    fn playing_player(&self) -> Option<String> {
+        let names = DBusProxy::new(&self.conn).ok()?.list_names().ok()?;
+        names.iter().map(|name| name.as_str()).find_map(|name| {
+            let player = name.strip_prefix(MPRIS_PREFIX)?;
+            let proxy = self.proxy(name, "/org/mpris/MediaPlayer2", "org.mpris.MediaPlayer2.Player")?;
+            let status: String = proxy.get_property("PlaybackStatus").ok()?;
+            // Browsers and some players register `name.instance1234`
+            (status == "Playing").then(|| player.split('.').next().unwrap_or(player).to_string())
+        })
+    }
+
+    /// Whether any application holds a screensaver or idle inhibit
+    fn inhibited(&self) -> bool {
+        let kde = self
+            .proxy(
+                "org.freedesktop.PowerManagement",
+                "/org/freedesktop/PowerManagement/Inhibit",
+                "org.freedesktop.PowerManagement.Inhibit",
+            )
+            .and_then(|proxy| proxy.call::<_, _, bool>("HasInhibit", &()).ok());
+        if kde == Some(true) {
+            return true;
+        }
+        self.proxy("org.gnome.SessionManager", "/org/gnome/SessionManager", "org.gnome.SessionManager")
+            .and_then(|proxy| proxy.call::<_, _, bool>("IsInhibited", &(GNOME_INHIBIT_IDLE,)).ok())
+            .unwrap_or(false)
+    }
+
+    /// Proxy that reads properties on every call instead of caching them
+    fn proxy<'a>(&'a self, destination: &'a str, path: &'a str, interface: &'a str) -> Option<Proxy<'a>> {
+        proxy::Builder::<Proxy>::new(&self.conn)
+            .destination(destination)
+            .ok()?
+            .path(path)
+            .ok()?
+            .interface(interface)
+            .ok()?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .ok()
+    }
+}
+
+impl IdleInhibitSource for DesktopInhibitors {
+    fn active_inhibitor(&self) -> Option<String> {
+        if let Some(player) = self.playing_player() {
+            return Some(format!("media:{}", player));
+        }
+        self.inhibited().then(|| "inhibit".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::interface;
+
+    use crate::sources::test_bus::TestBus;
+
+    struct MockPlayer {
+        status: &'static str,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl MockPlayer {
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.to_string()
+        }
+    }
+
+    struct MockPowerManagement {
+        inhibited: bool,
+    }
+
+    #[interface(name = "org.freedesktop.PowerManagement.Inhibit")]
+    impl MockPowerManagement {
+        fn has_inhibit(&self) -> bool {
+            self.inhibited
+        }
+    }
+
+    /// Only idle inhibits are held
+    struct MockSessionManager;
+
+    #[interface(name = "org.gnome.SessionManager")]
+    impl MockSessionManager {
+        fn is_inhibited(&self, flags: u32) -> bool {
+            flags & GNOME_INHIBIT_IDLE != 0
+        }
+    }
+
+    #[test]
+    fn test_gnome_idle_inhibit() {
+        let Some(bus) = TestBus::start() else {
+            eprintln!("Skipping D-Bus test: dbus-daemon is not available");
+            return;
+        };
+        let inhibitors = DesktopInhibitors::with_connection(bus.connect());
+        assert!(!inhibitors.inhibited());
+        let _session = bus.serve("org.gnome.SessionManager", "/org/gnome/SessionManager", MockSessionManager);
+        assert_eq!(inhibitors.active_inhibitor().as_deref(), Some("inhibit"));
+    }
+
+    #[test]
+    fn test_playing_player_and_inhibit() {
+        let Some(bus) = TestBus::start() else {
+            eprintln!("Skipping D-Bus test: dbus-daemon is not available");
+            return;
+        };
+        let inhibitors = DesktopInhibitors::with_connection(bus.connect());
+        assert_eq!(inhibitors.active_inhibitor(), None);
+
+        let _paused = bus.serve("org.mpris.MediaPlayer2.spotify", "/org/mpris/MediaPlayer2", MockPlayer { status: "Paused" });
+        assert_eq!(inhibitors.active_inhibitor(), None);
+
+        let _playing = bus.serve(
+            "org.mpris.MediaPlayer2.firefox.instance_1_42",
+            "/org/mpris/MediaPlayer2",
+            MockPlayer { status: "Playing" },
+        );
+        assert_eq!(inhibitors.active_inhibitor().as_deref(), Some("media:firefox"));
+
+        assert!(!inhibitors.inhibited());
+        let _inhibited = bus.serve(
+            "org.freedesktop.PowerManagement",
+            "/org/freedesktop/PowerManagement/Inhibit",
+            MockPowerManagement { inhibited: true },
+        );
+        assert!(inhibitors.inhibited());
+    }
+}
@@ -0,0 +1,170 @@
+//! logind session events - Suspend, resume, lock and unlock notifications from systemd-logind
+//!
+//! Listens on the system bus for `Manager.PrepareForSleep`, the `Lock`/`Unlock`
+//! signals of the caller's session and changes of its `LockedHint` property
+//! (desktops that lock the screen themselves only update the hint).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use zbus::blocking::{Connection, MessageIterator, Proxy};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::MatchRule;
+
+use super::{SessionEvent, SessionEventSource};
+
+const LOGIND: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+
+/// Session events collected by a background listener thread
+pub struct LogindEvents {
+    events: Arc<Mutex<Vec<(i64, SessionEvent)>>>,
+}
+
+impl LogindEvents {
+    /// Subscribe on the system bus; None if logind is not reachable
+    pub fn connect() -> Option<Self> {
+        Self::connect_to(Connection::system().ok()?)
+    }
+
+    /// Subscribe to the logind instance on the given bus
+    pub fn connect_to(conn: Connection) -> Option<Self> {
+        let manager = Proxy::new(&conn, LOGIND, MANAGER_PATH, "org.freedesktop.login1.Manager").ok()?;
+        let session_path: OwnedObjectPath = manager.call("GetSession", &("auto",)).ok()?;
+
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender(LOGIND)
+            .ok()?
+            .build();
+        let messages = MessageIterator::for_match_rule(rule, &conn, None).ok()?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        thread::spawn(move || {
+            for message in messages.flatten() {
+                if let Some(event) = parse_event(&message, session_path.as_str()) {
+                    sink.lock().unwrap().push((chrono::Utc::now().timestamp(), event));
+                }
+            }
+        });
+
+        Some(Self { events })
+    }
+}
+
+impl SessionEventSource for LogindEvents {
+    fn take_events(&self) -> Vec<(i64, SessionEvent)> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+/// Map a logind signal to a session event
+fn parse_event(message: &zbus::Message, session_path: &str) -> Option<SessionEvent> {
+    let header = message.header();
+    let path = header.path()?.as_str();
+    let member = header.member()?.as_str();
+
+    match member {
+        "PrepareForSleep" if path == MANAGER_PATH => {
+            let going_to_sleep: bool = message.body().deserialize().ok()?;
+            Some(if going_to_sleep {
+                SessionEvent::Suspend
+            } else {
+                SessionEvent::Resume
+            })
+        }
+        "Lock" if path == session_path => Some(SessionEvent::Lock),
+        "Unlock" if path == session_path => Some(SessionEvent::Unlock),
+        "PropertiesChanged" if path == session_path => {
+            let (_interface, changed, _invalidated): (String, HashMap<String, OwnedValue>, Vec<String>) =
+                message.body().deserialize().ok()?;
+            let locked = bool::try_from(changed.get("LockedHint")?).ok()?;
+            Some(if locked {
+                SessionEvent::Lock
+            } else {
+                SessionEvent::Unlock
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use zbus::interface;
+
+    use crate::sources::test_bus::TestBus;
+
+    const SESSION_PATH: &str = "/org/freedesktop/login1/session/_31";
+
+    struct MockManager;
+
+    #[interface(name = "org.freedesktop.login1.Manager")]
+    impl MockManager {
+        fn get_session(&self, _id: &str) -> OwnedObjectPath {
+            OwnedObjectPath::try_from(SESSION_PATH).unwrap()
+        }
+    }
+
+    /// Wait until the listener has collected `count` events
+    fn wait_for_events(source: &LogindEvents, count: usize) -> Vec<SessionEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut events = Vec::new();
+        while events.len() < count && Instant::now() < deadline {
+            events.extend(source.take_events().into_iter().map(|(_, event)| event));
+            thread::sleep(Duration::from_millis(20));
+        }
+        events
+    }
+
+    #[test]
+    fn test_logind_signals_become_session_events() {
+        let Some(bus) = TestBus::start() else {
+            eprintln!("Skipping D-Bus test: dbus-daemon is not available");
+            return;
+        };
+        let logind = bus.serve(LOGIND, MANAGER_PATH, MockManager);
+
+        let source = LogindEvents::connect_to(bus.connect()).expect("logind events");
+
+        logind
+            .emit_signal(None::<&str>, MANAGER_PATH, "org.freedesktop.login1.Manager", "PrepareForSleep", &true)
+            .unwrap();
+        logind
+            .emit_signal(None::<&str>, MANAGER_PATH, "org.freedesktop.login1.Manager", "PrepareForSleep", &false)
+            .unwrap();
+        logind
+            .emit_signal(None::<&str>, SESSION_PATH, "org.freedesktop.login1.Session", "Lock", &())
+            .unwrap();
+        // Another session being unlocked must be ignored
+        logind
+            .emit_signal(None::<&str>, "/org/freedesktop/login1/session/_7", "org.freedesktop.login1.Session", "Unlock", &())
+            .unwrap();
+        let changed: HashMap<&str, zbus::zvariant::Value> =
+            HashMap::from([("LockedHint", zbus::zvariant::Value::from(false))]);
+        logind
+            .emit_signal(
+                None::<&str>,
+                SESSION_PATH,
+                "org.freedesktop.DBus.Properties",
+                "PropertiesChanged",
+                &("org.freedesktop.login1.Session", changed, Vec::<&str>::new()),
+            )
+            .unwrap();
+
+        assert_eq!(
+            wait_for_events(&source, 4),
+            vec![
+                SessionEvent::Suspend,
+                SessionEvent::Resume,
+                SessionEvent::Lock,
+                SessionEvent::Unlock,
+            ]
+        );
+    }
+}
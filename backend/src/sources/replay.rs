@@ -0,0 +1,163 @@
+//! Replay source - Drives the tracking loop from a scripted JSON timeline
+//!
+//! A timeline is a list of samples; each sample describes what the desktop
+//! looked like from its timestamp until the next sample:
+//!
+//! ```json
+//! [
+//!   { "timestamp": 1000, "app": "Code", "title": "main.rs", "idle_secs": 0 },
+//!   { "timestamp": 1060, "app": null, "idle_secs": 150 },
+//!   { "timestamp": 1300, "app": "Code", "title": "main.rs", "session": "lock" },
+//!   { "timestamp": 1400, "app": "mpv", "idle_secs": 200, "inhibitor": "media:mpv" }
+//! ]
+//! ```
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{Clock, IdleInhibitSource, IdleSource, SessionEvent, SessionEventSource, WindowSource};
+use crate::window::WindowInfo;
+
+/// One point of a replay timeline
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplaySample {
+    pub timestamp: i64,
+    #[serde(default)]
+    pub app: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Process owning the window
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub idle_secs: u64,
+    /// Session event delivered when the clock passes this sample
+    #[serde(default)]
+    pub session: Option<SessionEvent>,
+    /// Active idle inhibitor while the sample is in effect
+    #[serde(default)]
+    pub inhibitor: Option<String>,
+}
+
+/// Manually advanced clock; `sleep` moves time forward instantly
+pub struct ReplayClock {
+    start: i64,
+    now: AtomicI64,
+    suspended: AtomicI64,
+}
+
+impl ReplayClock {
+    pub fn new(start: i64) -> Self {
+        Self {
+            start,
+            now: AtomicI64::new(start),
+            suspended: AtomicI64::new(0),
+        }
+    }
+
+    /// Simulate a system suspend: wall time advances, the monotonic clock does not
+    pub fn suspend(&self, secs: i64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+        self.suspended.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    fn monotonic(&self) -> Duration {
+        let awake = self.now() - self.start - self.suspended.load(Ordering::SeqCst);
+        Duration::from_secs(awake as u64)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.now.fetch_add(duration.as_secs() as i64, Ordering::SeqCst);
+    }
+}
+
+/// Window and idle source that answers from a timeline at the clock's current time
+pub struct ReplaySource {
+    samples: Vec<ReplaySample>,
+    clock: Arc<ReplayClock>,
+    /// Clock time up to which session events have been delivered
+    delivered: Mutex<i64>,
+}
+
+impl ReplaySource {
+    /// Build a replay source from a JSON timeline; samples are sorted by timestamp
+    pub fn from_json(json: &str, clock: Arc<ReplayClock>) -> Result<Self, String> {
+        let mut samples: Vec<ReplaySample> =
+            serde_json::from_str(json).map_err(|e| format!("Invalid replay timeline: {}", e))?;
+        samples.sort_by_key(|s| s.timestamp);
+        Ok(Self {
+            samples,
+            clock,
+            delivered: Mutex::new(i64::MIN),
+        })
+    }
+
+    /// Timestamp of the last sample
+    pub fn end(&self) -> i64 {
+        self.samples.last().map(|s| s.timestamp).unwrap_or(0)
+    }
+
+    fn current(&self) -> Option<&ReplaySample> {
+        let now = self.clock.now();
+        self.samples.iter().rev().find(|s| s.timestamp <= now)
+    }
+}
+
+impl WindowSource for ReplaySource {
+    fn active_window(&self) -> Option<WindowInfo> {
+        let sample = self.current()?;
+        Some(WindowInfo {
+            app_name: sample.app.clone()?,
+            title: sample.title.clone(),
+            process_id: sample.pid,
+        })
+    }
+}
+
+impl IdleSource for ReplaySource {
+    fn idle_seconds(&self) -> u64 {
+        let Some(sample) = self.current() else {
+            return 0;
+        };
+        // Idle time keeps growing while the sample is in effect
+        if sample.idle_secs > 0 {
+            sample.idle_secs + (self.clock.now() - sample.timestamp) as u64
+        } else {
+            0
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+}
+
+impl IdleInhibitSource for ReplaySource {
+    fn active_inhibitor(&self) -> Option<String> {
+        self.current()?.inhibitor.clone()
+    }
+}
+
+impl SessionEventSource for ReplaySource {
+    fn take_events(&self) -> Vec<(i64, SessionEvent)> {
+        let now = self.clock.now();
+        let mut delivered = self.delivered.lock().unwrap();
+        let events = self
+            .samples
+            .iter()
+            .filter(|s| s.timestamp > *delivered && s.timestamp <= now)
+            .filter_map(|s| s.session.map(|event| (s.timestamp, event)))
+            .collect();
+        *delivered = now;
+        events
+    }
+}
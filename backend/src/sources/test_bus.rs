@@ -0,0 +1,86 @@
+//! Private D-Bus daemon for tests of the D-Bus backed sources
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+
+/// Private session bus run by a throwaway dbus-daemon
+pub struct TestBus {
+    daemon: Child,
+    address: String,
+    dir: std::path::PathBuf,
+}
+
+impl TestBus {
+    /// Start a bus, or None if dbus-daemon is not installed
+    pub fn start() -> Option<Self> {
+        static NEXT_BUS: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "timetracker-dbus-{}-{}",
+            std::process::id(),
+            NEXT_BUS.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&dir).ok()?;
+        let config = dir.join("bus.conf");
+        std::fs::write(
+            &config,
+            format!(
+                r#"<busconfig>
+  <type>session</type>
+  <listen>unix:dir={}</listen>
+  <policy context="default">
+<allow send_destination="*" eavesdrop="true"/>
+<allow eavesdrop="true"/>
+<allow own="*"/>
+  </policy>
+</busconfig>"#,
+                dir.display()
+            ),
+        )
+        .ok()?;
+
+        let mut daemon = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+
+        Some(Self {
+            daemon,
+            address: address.trim().to_string(),
+            dir,
+        })
+    }
+
+    pub fn connect(&self) -> Connection {
+        Builder::address(self.address.as_str()).unwrap().build().unwrap()
+    }
+
+    /// Connection that owns `name` and serves `iface` at `path`
+    pub fn serve<I: zbus::object_server::Interface>(&self, name: &str, path: &str, iface: I) -> Connection {
+        Builder::address(self.address.as_str())
+            .unwrap()
+            .name(name.to_string())
+            .unwrap()
+            .serve_at(path.to_string(), iface)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        self.daemon.kill().ok();
+        self.daemon.wait().ok();
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
@@ -0,0 +1,366 @@
+//! Title parsers - Recognise the project and open document in editor window titles
+//!
+//! Editors put both in their window title using well-known default patterns:
+//!
+//! | Editor | Title |
+//! |--------|-------|
+//! | VS Code (and VSCodium, Cursor) | `● tracker.rs — time-tracker-app — Visual Studio Code` |
+//! | JetBrains IDEs | `time-tracker-app – tracker.rs` |
+//! | Vim / Neovim | `tracker.rs + (~/src/time-tracker-app/backend/src) - NVIM` |
+//!
+//! Parsers are tried in registry order; the first one that applies to the window and
+//! recognises its title wins. Vim usually runs inside a terminal, so it is recognised
+//! by its title alone.
+
+use std::path::PathBuf;
+
+use crate::git;
+
+/// Project and document recognised in a window title
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedTitle {
+    pub project: Option<String>,
+    pub document: Option<String>,
+}
+
+/// Parser for the window titles of one editor family
+struct TitleParser {
+    /// Whether the parser applies to a window (lowercase app name, raw title)
+    applies: fn(app: &str, title: &str) -> bool,
+    parse: fn(title: &str) -> Option<ParsedTitle>,
+}
+
+/// Registered parsers, most specific first
+const PARSERS: &[TitleParser] = &[
+    TitleParser { applies: is_vscode, parse: parse_vscode },
+    TitleParser { applies: is_jetbrains, parse: parse_jetbrains },
+    TitleParser { applies: is_vim, parse: parse_vim },
+];
+
+/// Recognise the project and document of an editor window
+pub fn parse_title(app_name: &str, window_title: Option<&str>) -> Option<ParsedTitle> {
+    let title = window_title?.trim();
+    if title.is_empty() {
+        return None;
+    }
+    let app = normalize_app_name(app_name);
+
+    PARSERS
+        .iter()
+        .filter(|parser| (parser.applies)(&app, title))
+        .find_map(|parser| (parser.parse)(title))
+        .filter(|parsed| parsed.project.is_some() || parsed.document.is_some())
+}
+
+/// Lowercase app name without the Windows ".exe" and "64" suffixes ("idea64.exe" -> "idea")
+fn normalize_app_name(app_name: &str) -> String {
+    let app = app_name.trim().to_lowercase();
+    let app = app.strip_suffix(".exe").unwrap_or(&app);
+    app.strip_suffix("64").unwrap_or(app).to_string()
+}
+
+/// Non-empty trimmed string
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Drop a trailing " [...]" annotation such as a module name or remote host
+fn strip_bracket_suffix(s: &str) -> &str {
+    match s.trim_end().strip_suffix(']').and_then(|rest| rest.rfind(" [").map(|i| &rest[..i])) {
+        Some(stripped) => stripped.trim_end(),
+        None => s.trim(),
+    }
+}
+
+// --- VS Code ---
+
+/// App names VS Code and its forks append to the title
+const VSCODE_TITLE_NAMES: &[&str] = &[
+    "Visual Studio Code",
+    "Visual Studio Code - Insiders",
+    "Code - OSS",
+    "VSCodium",
+    "Cursor",
+];
+
+const VSCODE_APPS: &[&str] = &[
+    "code",
+    "code-insiders",
+    "code - insiders",
+    "code-oss",
+    "code - oss",
+    "visual studio code",
+    "codium",
+    "vscodium",
+    "cursor",
+];
+
+/// Editor tabs that are not files, shown as the only title part when no folder is open
+const VSCODE_PAGES: &[&str] = &["Welcome", "Get Started", "Settings", "Keyboard Shortcuts", "Release Notes"];
+
+fn is_vscode(app: &str, title: &str) -> bool {
+    VSCODE_APPS.contains(&app) || VSCODE_TITLE_NAMES.iter().any(|name| title.ends_with(name))
+}
+
+/// `${dirty}${activeEditorShort}${separator}${rootName}${separator}${appName}`;
+/// the separator is " - " on Windows and Linux and " — " on macOS
+fn parse_vscode(title: &str) -> Option<ParsedTitle> {
+    let title = title.trim_start_matches(['●', '•', ' ']);
+    let title = VSCODE_TITLE_NAMES
+        .iter()
+        .rev()
+        .find_map(|name| {
+            title
+                .strip_suffix(name)
+                .and_then(|rest| rest.strip_suffix(" — ").or_else(|| rest.strip_suffix(" - ")))
+        })
+        .unwrap_or(title);
+    let separator = if title.contains(" — ") { " — " } else { " - " };
+    let parts: Vec<&str> = title.split(separator).map(str::trim).collect();
+
+    match parts.as_slice() {
+        // The root name is followed by e.g. "[SSH: host]" in remote windows
+        [document, project, ..] => Some(ParsedTitle {
+            project: non_empty(strip_bracket_suffix(project)),
+            document: non_empty(document),
+        }),
+        // Either a folder without an open editor, or a file without a folder
+        [single] if looks_like_file(single) || VSCODE_PAGES.contains(single) => Some(ParsedTitle {
+            project: None,
+            document: non_empty(single),
+        }),
+        [single] => Some(ParsedTitle {
+            project: non_empty(strip_bracket_suffix(single)),
+            document: None,
+        }),
+        [] => None,
+    }
+}
+
+/// File names have an extension or a well-known extensionless name
+fn looks_like_file(name: &str) -> bool {
+    let name = name.trim();
+    name.starts_with("Untitled-")
+        || ["Makefile", "Dockerfile", "LICENSE", "README"].contains(&name)
+        || name
+            .rsplit_once('.')
+            .is_some_and(|(stem, ext)| !stem.is_empty() && !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+// --- JetBrains ---
+
+/// Product names of the JetBrains IDEs, as they appear in app names and old-style titles
+const JETBRAINS_PRODUCTS: &[&str] = &[
+    "intellij idea",
+    "pycharm",
+    "webstorm",
+    "phpstorm",
+    "clion",
+    "goland",
+    "rubymine",
+    "rider",
+    "datagrip",
+    "dataspell",
+    "rustrover",
+    "android studio",
+    "appcode",
+];
+
+fn is_jetbrains(app: &str, _title: &str) -> bool {
+    app == "idea"
+        || app.contains("jetbrains")
+        || app.contains("intellij")
+        || app == "studio"
+        || JETBRAINS_PRODUCTS.iter().any(|product| app.contains(product))
+}
+
+/// Current IDEs show `project – file [module]` (en dash); releases before 2020.1 showed
+/// `project [path] - path/to/file - Product`
+fn parse_jetbrains(title: &str) -> Option<ParsedTitle> {
+    if let Some((project, document)) = title.split_once(" – ") {
+        return Some(ParsedTitle {
+            project: non_empty(strip_bracket_suffix(project)),
+            document: non_empty(file_name(strip_bracket_suffix(document))),
+        });
+    }
+
+    let mut parts: Vec<&str> = title.split(" - ").map(str::trim).collect();
+    if parts.len() > 1 {
+        let last = parts[parts.len() - 1].to_lowercase();
+        if JETBRAINS_PRODUCTS.iter().any(|product| last.starts_with(product)) {
+            parts.pop();
+        }
+    }
+    match parts.as_slice() {
+        [project, document, ..] => Some(ParsedTitle {
+            project: non_empty(strip_bracket_suffix(project)),
+            document: non_empty(file_name(strip_bracket_suffix(document))),
+        }),
+        [project] => Some(ParsedTitle {
+            project: non_empty(strip_bracket_suffix(project)),
+            document: None,
+        }),
+        [] => None,
+    }
+}
+
+/// Last component of a path shown in a title ("…/src/main.rs" -> "main.rs")
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+// --- Vim ---
+
+fn is_vim(_app: &str, title: &str) -> bool {
+    vim_title_body(title).is_some()
+}
+
+/// Title without the " - VIM" / " - NVIM" / " - GVIM" suffix (optionally followed by a
+/// server number such as "GVIM1"), or None if the title was not set by Vim
+fn vim_title_body(title: &str) -> Option<&str> {
+    let (body, suffix) = title.rsplit_once(" - ")?;
+    let name = suffix.trim_end_matches(|c: char| c.is_ascii_digit()).to_uppercase();
+    matches!(name.as_str(), "VIM" | "NVIM" | "GVIM").then_some(body)
+}
+
+/// `file [flags] (directory) - VIM`, where flags are "+" (modified), "-" (not modifiable)
+/// and "=" (read-only). The directory is the file's own; the project is the enclosing
+/// git repository when there is one, otherwise the directory itself.
+fn parse_vim(title: &str) -> Option<ParsedTitle> {
+    let body = vim_title_body(title)?;
+    let (file, directory) = match body.strip_suffix(')').and_then(|rest| rest.rsplit_once(" (")) {
+        Some((file, directory)) => (file, Some(directory)),
+        None => (body, None),
+    };
+
+    let file = file.trim_end_matches([' ', '+', '-', '=']);
+    let document = if file.starts_with('[') {
+        // "[No Name]", "[Scratch]" and other unnamed buffers
+        None
+    } else {
+        non_empty(file)
+    };
+    let project = directory.and_then(project_for_directory);
+
+    Some(ParsedTitle { project, document })
+}
+
+/// Name of the git repository containing a directory shown in a title, falling back
+/// to the directory's own name. The home directory itself is no project.
+fn project_for_directory(directory: &str) -> Option<String> {
+    let directory = directory.trim().trim_end_matches(['/', '\\']);
+    if directory.is_empty() || directory == "~" {
+        return None;
+    }
+
+    let path = match directory.strip_prefix("~/") {
+        Some(relative) => home_dir()?.join(relative),
+        None => PathBuf::from(directory),
+    };
+    let repository = git::find_repository(&path);
+    let root = repository.as_ref().map(|repo| repo.root.as_path()).unwrap_or(&path);
+    if home_dir().as_deref() == Some(root) {
+        return None;
+    }
+    root.file_name().and_then(|name| non_empty(&name.to_string_lossy()))
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (app name, window title, expected project, expected document)
+    type Case = (&'static str, &'static str, Option<&'static str>, Option<&'static str>);
+
+    fn check(cases: &[Case]) {
+        for &(app, title, project, document) in cases {
+            let parsed = parse_title(app, Some(title)).unwrap_or_default();
+            assert_eq!(
+                (parsed.project.as_deref(), parsed.document.as_deref()),
+                (project, document),
+                "{} / {:?}",
+                app,
+                title
+            );
+        }
+    }
+
+    #[test]
+    fn test_vscode_titles() {
+        check(&[
+            ("Code", "tracker.rs - time-tracker-app - Visual Studio Code", Some("time-tracker-app"), Some("tracker.rs")),
+            ("Code.exe", "● main.rs - time-tracker-app - Visual Studio Code", Some("time-tracker-app"), Some("main.rs")),
+            ("Code", "README.md — time-tracker-app — Visual Studio Code", Some("time-tracker-app"), Some("README.md")),
+            ("Code", "lib.rs - api [SSH: build-box] - Visual Studio Code", Some("api"), Some("lib.rs")),
+            ("Code", "index.ts - web - Visual Studio Code - Insiders", Some("web"), Some("index.ts")),
+            ("VSCodium", "Cargo.toml - time-tracker-app - VSCodium", Some("time-tracker-app"), Some("Cargo.toml")),
+            ("Cursor", "App.tsx - frontend - Cursor", Some("frontend"), Some("App.tsx")),
+            ("Code", "time-tracker-app - Visual Studio Code", Some("time-tracker-app"), None),
+            ("Code", "notes.txt - Visual Studio Code", None, Some("notes.txt")),
+            ("Code", "Untitled-1 - Visual Studio Code", None, Some("Untitled-1")),
+            ("Code", "Welcome - Visual Studio Code", None, Some("Welcome")),
+            ("Code", "Settings - time-tracker-app - Visual Studio Code", Some("time-tracker-app"), Some("Settings")),
+            // macOS titles without the app name
+            ("Code", "tracker.rs — time-tracker-app", Some("time-tracker-app"), Some("tracker.rs")),
+        ]);
+    }
+
+    #[test]
+    fn test_jetbrains_titles() {
+        check(&[
+            ("jetbrains-idea", "time-tracker – Main.java", Some("time-tracker"), Some("Main.java")),
+            ("idea64.exe", "intellij-community – ActionManagerImpl.java [intellij.platform.ide.impl]", Some("intellij-community"), Some("ActionManagerImpl.java")),
+            ("PyCharm", "scraper – spiders/news.py", Some("scraper"), Some("news.py")),
+            ("RustRover", "time-tracker-app – tracker.rs", Some("time-tracker-app"), Some("tracker.rs")),
+            ("WebStorm", "frontend", Some("frontend"), None),
+            ("Android Studio", "MyApplication – MainActivity.kt [MyApplication.app.main]", Some("MyApplication"), Some("MainActivity.kt")),
+            // Releases before 2020.1
+            ("IntelliJ IDEA", "shop [~/src/shop] - .../src/main/java/Cart.java - IntelliJ IDEA", Some("shop"), Some("Cart.java")),
+            ("pycharm64.exe", "scraper [C:\\src\\scraper] - ...\\spiders\\news.py - PyCharm", Some("scraper"), Some("news.py")),
+        ]);
+    }
+
+    #[test]
+    fn test_vim_titles() {
+        check(&[
+            ("Alacritty", "tracker.rs (/nonexistent/time-tracker-app) - NVIM", Some("time-tracker-app"), Some("tracker.rs")),
+            ("gnome-terminal-server", "main.rs + (/nonexistent/api) - VIM", Some("api"), Some("main.rs")),
+            ("kitty", "init.lua (/nonexistent/nvim) - Nvim", Some("nvim"), Some("init.lua")),
+            ("Gvim", "notes.md = (/nonexistent/notes) - GVIM1", Some("notes"), Some("notes.md")),
+            ("Alacritty", "todo.txt (~) - VIM", None, Some("todo.txt")),
+            ("Alacritty", "[No Name] - NVIM", None, None),
+            ("kitty", "vim - ssh build-box", None, None),
+        ]);
+    }
+
+    #[test]
+    fn test_vim_project_is_enclosing_git_repository() {
+        let repo = std::env::temp_dir().join(format!("title-parser-{}", std::process::id())).join("shop");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src/cart")).unwrap();
+
+        let title = format!("cart.rs ({}) - NVIM", repo.join("src/cart").display());
+        let parsed = parse_title("Alacritty", Some(&title)).unwrap();
+        assert_eq!(parsed.project.as_deref(), Some("shop"));
+        assert_eq!(parsed.document.as_deref(), Some("cart.rs"));
+
+        std::fs::remove_dir_all(repo.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_other_windows_are_not_parsed() {
+        check(&[
+            ("firefox", "GitHub - Mozilla Firefox", None, None),
+            ("Slack", "general - Acme - Slack", None, None),
+            ("Xcode", "main.swift - App", None, None),
+        ]);
+        assert_eq!(parse_title("Code", None), None);
+    }
+}
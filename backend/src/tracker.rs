@@ -1,20 +1,64 @@
 //! Tracker module - Core tracking loop for monitoring active windows
 
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-use crate::database::Database;
+use crate::database::{matches_pattern, Database, WindowContext};
+use crate::git;
 use crate::idle::IdleMonitor;
 use crate::plugin_system::ExtensionRegistry;
+use crate::privacy::{PrivacyDecision, PrivacyFilter, MASKED_TITLE};
+use crate::process::ProcessInspector;
+use crate::schedule::Schedule;
+use crate::sources::{
+    self, Clock, IdleInhibitSource, IdleSource, SessionEvent, SessionEventSource, SystemClock, WindowSource,
+};
+use crate::title_parser;
 use crate::window::WindowTracker;
 
+/// Event source used by the browser native messaging host
+const BROWSER_EVENT_SOURCE: &str = "browser";
+
+/// Setting with apps (JSON array of patterns) that never count as idle while focused
+pub const NEVER_IDLE_APPS_KEY: &str = "never_idle_apps";
+
+/// How long a tab report from the browser extension stays usable
+const BROWSER_TAB_MAX_AGE_SECS: i64 = 60;
+
+/// Check if an application is a web browser
+pub(crate) fn is_browser(app_name: &str) -> bool {
+    let browser_apps = ["chrome", "firefox", "edge", "safari", "opera", "brave", "vivaldi"];
+    let app_lower = app_name.to_lowercase();
+    browser_apps.iter().any(|&browser| app_lower.contains(browser))
+}
+
+/// URL of the tab shown in a browser window, as last reported by the native messaging host.
+/// The tab title must appear in the window title, so reports from another browser window
+/// are not picked up; a window without a title matches no report. Incognito tabs are
+/// reported without a URL or title and never match.
+fn reported_tab_url(db: &Database, window_title: Option<&str>, now: i64) -> Option<String> {
+    let window_title = window_title.filter(|title| !title.is_empty())?;
+    let events = db
+        .get_events(now - BROWSER_TAB_MAX_AGE_SECS, now, Some(BROWSER_EVENT_SOURCE))
+        .ok()?;
+    events
+        .iter()
+        .filter(|event| {
+            let tab_title = event.data.get("title").and_then(|t| t.as_str()).unwrap_or_default();
+            !tab_title.is_empty() && window_title.contains(tab_title)
+        })
+        .filter(|event| event.data.get("incognito").and_then(|i| i.as_bool()) != Some(true))
+        .max_by_key(|event| (event.ended_at, event.id))
+        .and_then(|event| event.data.get("url")?.as_str().map(str::to_string))
+}
+
 /// Extract domain from browser window title
 fn extract_domain(app_name: &str, window_title: Option<&str>) -> Option<String> {
     // Only process browser windows
-    let browser_apps = ["chrome", "firefox", "edge", "safari", "opera", "brave", "vivaldi"];
-    if !browser_apps.iter().any(|&browser| app_name.to_lowercase().contains(browser)) {
+    if !is_browser(app_name) {
         return None;
     }
 
@@ -112,31 +156,160 @@ fn is_valid_domain(s: &str) -> bool {
     s.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-')
 }
 
+/// Interval between tracking loop iterations
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wall-clock time that may pass between ticks without the monotonic clock
+/// before the difference is treated as a suspend gap
+const SUSPEND_GAP_SECS: i64 = 30;
+
+/// Why the tracker is not recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    /// Paused from the tray or the app
+    Manual,
+    /// Outside the work-hours schedule
+    OutsideSchedule,
+}
+
+/// Setting holding the current manual pause as JSON, so it survives restarts
+const PAUSE_STATE_KEY: &str = "pause_state";
+
+/// Manual pause, optionally ending by itself
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ManualPause {
+    pub started_at: i64,
+    /// Tracking resumes by itself at this time; None pauses until resumed
+    pub resume_at: Option<i64>,
+    pub reason: Option<String>,
+    /// Category of the manual entry that records the paused interval when it ends
+    pub category_id: Option<i64>,
+}
+
+impl ManualPause {
+    /// Pause persisted by a previous run, if any
+    fn load(db: &Database) -> Option<Self> {
+        let value = db.get_setting(PAUSE_STATE_KEY).ok().flatten()?;
+        serde_json::from_str(&value)
+            .map_err(|e| eprintln!("Warning: Ignoring invalid pause state: {}", e))
+            .ok()
+    }
+
+    fn save(db: &Database, pause: Option<&Self>) {
+        let result = match pause {
+            Some(pause) => db.set_setting(PAUSE_STATE_KEY, &serde_json::to_string(pause).unwrap_or_default()),
+            None => db.delete_setting(PAUSE_STATE_KEY),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to save pause state: {}", e);
+        }
+    }
+
+    fn is_active(&self, now: i64) -> bool {
+        self.resume_at.is_none_or(|resume_at| now < resume_at)
+    }
+
+    /// Record the paused interval as a manual entry if a category was chosen
+    fn record(&self, db: &Database, ended_at: i64) {
+        let Some(category_id) = self.category_id else {
+            return;
+        };
+        if ended_at <= self.started_at {
+            return;
+        }
+        let description = self.reason.as_deref().unwrap_or("Paused");
+        if let Err(e) = db.add_manual_entry(Some(description), Some(category_id), self.started_at, ended_at) {
+            eprintln!("Failed to record pause: {}", e);
+        }
+    }
+}
+
+/// Reasons that keep the tracker from recording at `now`; empty while tracking
+fn pause_reasons(paused: &Mutex<Option<ManualPause>>, schedule: &Mutex<Schedule>, now: i64) -> Vec<PauseReason> {
+    let mut reasons = Vec::new();
+    if paused.lock().unwrap().as_ref().is_some_and(|pause| pause.is_active(now)) {
+        reasons.push(PauseReason::Manual);
+    }
+    if !schedule.lock().unwrap().contains_timestamp(now) {
+        reasons.push(PauseReason::OutsideSchedule);
+    }
+    reasons
+}
+
+/// Load the never-idle app patterns; a malformed setting disables them
+fn load_never_idle_apps(db: &Database) -> Vec<String> {
+    let Some(value) = db.get_setting(NEVER_IDLE_APPS_KEY).ok().flatten() else {
+        return Vec::new();
+    };
+    serde_json::from_str(&value).unwrap_or_else(|e| {
+        eprintln!("Warning: Invalid {} setting: {}", NEVER_IDLE_APPS_KEY, e);
+        Vec::new()
+    })
+}
+
 /// Tracker service that runs the main tracking loop
 pub struct Tracker {
     db: Arc<Database>,
     extension_registry: Option<Arc<ExtensionRegistry>>,
-    window_tracker: WindowTracker,
-    idle_monitor: Arc<IdleMonitor>,
+    window_source: Arc<dyn WindowSource>,
+    idle_source: Arc<dyn IdleSource>,
+    session_events: Arc<dyn SessionEventSource>,
+    idle_inhibitors: Arc<dyn IdleInhibitSource>,
+    clock: Arc<dyn Clock>,
     running: Arc<AtomicBool>,
-    paused: Arc<AtomicBool>,
+    paused: Arc<Mutex<Option<ManualPause>>>,
+    never_idle_apps: Arc<Mutex<Vec<String>>>,
     idle_threshold_secs: Arc<Mutex<u64>>,
     prompt_threshold_secs: Arc<Mutex<u64>>,
+    privacy: Arc<Mutex<PrivacyFilter>>,
+    schedule: Arc<Mutex<Schedule>>,
 }
 
 impl Tracker {
-    /// Create a new tracker instance.
-    /// If `extension_registry` is provided, plugin data hooks will be applied after each activity upsert.
+    /// Create a new tracker instance backed by the native window and idle APIs.
+    /// If `extension_registry` is provided, plugin data hooks will be applied each time an activity is recorded.
     pub fn new(db: Arc<Database>, extension_registry: Option<Arc<ExtensionRegistry>>) -> Self {
+        Self::with_sources(
+            db,
+            extension_registry,
+            Arc::new(WindowTracker::new()),
+            IdleMonitor::shared(),
+            sources::native_session_events(),
+            sources::native_idle_inhibitors(),
+            Arc::new(SystemClock::new()),
+        )
+    }
+
+    /// Create a tracker with explicit window, idle, session event, idle inhibitor and clock sources
+    pub fn with_sources(
+        db: Arc<Database>,
+        extension_registry: Option<Arc<ExtensionRegistry>>,
+        window_source: Arc<dyn WindowSource>,
+        idle_source: Arc<dyn IdleSource>,
+        session_events: Arc<dyn SessionEventSource>,
+        idle_inhibitors: Arc<dyn IdleInhibitSource>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let privacy = Arc::new(Mutex::new(PrivacyFilter::load(&db)));
+        let schedule = Arc::new(Mutex::new(Schedule::load(&db)));
+        let paused = Arc::new(Mutex::new(ManualPause::load(&db)));
+        let never_idle_apps = Arc::new(Mutex::new(load_never_idle_apps(&db)));
         Self {
             db,
             extension_registry,
-            window_tracker: WindowTracker::new(),
-            idle_monitor: Arc::new(IdleMonitor::new()),
+            window_source,
+            idle_source,
+            session_events,
+            idle_inhibitors,
+            clock,
             running: Arc::new(AtomicBool::new(false)),
-            paused: Arc::new(AtomicBool::new(false)),
+            paused,
+            never_idle_apps,
             idle_threshold_secs: Arc::new(Mutex::new(120)), // 2 minutes default
             prompt_threshold_secs: Arc::new(Mutex::new(300)), // 5 minutes default
+            privacy,
+            schedule,
         }
     }
 
@@ -150,32 +323,85 @@ impl Tracker {
         *self.prompt_threshold_secs.lock().unwrap() = secs;
     }
 
+    /// Replace the privacy filter applied to windows from the next tick on
+    pub fn set_privacy_filter(&self, filter: PrivacyFilter) {
+        *self.privacy.lock().unwrap() = filter;
+    }
+
+    /// Replace the apps that never count as idle while focused
+    pub fn set_never_idle_apps(&self, apps: Vec<String>) {
+        *self.never_idle_apps.lock().unwrap() = apps;
+    }
+
+    /// Replace the work-hours schedule consulted from the next tick on
+    pub fn set_schedule(&self, schedule: Schedule) {
+        *self.schedule.lock().unwrap() = schedule;
+    }
+
+    /// Whether the current time is inside the schedule; None while no schedule is enabled
+    pub fn in_schedule(&self) -> Option<bool> {
+        let schedule = self.schedule.lock().unwrap();
+        schedule
+            .is_enabled()
+            .then(|| schedule.contains_timestamp(self.clock.now()))
+    }
+
+    /// Name of the idle source the tracker is using
+    pub fn idle_source_name(&self) -> &'static str {
+        self.idle_source.name()
+    }
+
     /// Check if tracker is running
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
 
-    /// Pause tracking
-    pub fn pause(&self) {
-        self.paused.store(true, Ordering::SeqCst);
+    /// Pause tracking manually until `resume_at`, or until resumed when None.
+    /// A pause already in effect ends first. The schedule may keep tracking paused after `resume`.
+    pub fn pause(&self, resume_at: Option<i64>, reason: Option<String>, category_id: Option<i64>) {
+        self.resume();
+        let pause = ManualPause {
+            started_at: self.clock.now(),
+            resume_at,
+            reason,
+            category_id,
+        };
+        ManualPause::save(&self.db, Some(&pause));
+        *self.paused.lock().unwrap() = Some(pause);
     }
 
-    /// Resume tracking
+    /// End the manual pause, recording it if a category was chosen
     pub fn resume(&self) {
-        self.paused.store(false, Ordering::SeqCst);
+        let Some(pause) = self.paused.lock().unwrap().take() else {
+            return;
+        };
+        let now = self.clock.now();
+        pause.record(&self.db, pause.resume_at.map_or(now, |resume_at| resume_at.min(now)));
+        ManualPause::save(&self.db, None);
     }
 
-    /// Check if tracking is paused
+    /// Manual pause in effect, if any
+    pub fn manual_pause(&self) -> Option<ManualPause> {
+        let now = self.clock.now();
+        self.paused.lock().unwrap().clone().filter(|pause| pause.is_active(now))
+    }
+
+    /// Check if tracking is paused for any reason
     pub fn is_paused(&self) -> bool {
-        self.paused.load(Ordering::SeqCst)
+        !self.pause_reasons().is_empty()
+    }
+
+    /// Reasons the tracker is currently not recording
+    pub fn pause_reasons(&self) -> Vec<PauseReason> {
+        pause_reasons(&self.paused, &self.schedule, self.clock.now())
     }
 
     /// Get current active app name
     pub fn get_current_app(&self) -> Option<String> {
-        if self.paused.load(Ordering::SeqCst) {
+        if self.is_paused() {
             return None;
         }
-        self.window_tracker.get_active_window()
+        self.window_source.active_window()
             .map(|info| info.app_name)
     }
 
@@ -192,92 +418,910 @@ impl Tracker {
     {
         self.running.store(true, Ordering::SeqCst);
 
-        let running = Arc::clone(&self.running);
-        let paused = Arc::clone(&self.paused);
-        let db = Arc::clone(&self.db);
-        let extension_registry = self.extension_registry.clone();
-        let idle_threshold = Arc::clone(&self.idle_threshold_secs);
-        let idle_monitor = Arc::clone(&self.idle_monitor);
-
+        let mut tracking_loop = self.tracking_loop(on_idle_return);
         thread::spawn(move || {
-            let window_tracker = WindowTracker::new();
-            
-            let mut is_idle_mode = false;
-            let mut idle_start_time: Option<i64> = None;
-
-            while running.load(Ordering::SeqCst) {
-                // Sleep for 5 seconds between checks
-                thread::sleep(Duration::from_secs(5));
-
-                // Skip if paused
-                if paused.load(Ordering::SeqCst) {
-                    continue;
+            while tracking_loop.is_running() {
+                tracking_loop.step();
+            }
+        });
+    }
+
+    /// Build the loop state that `start` runs on a background thread
+    fn tracking_loop<F>(&self, on_idle_return: F) -> TrackingLoop<F>
+    where
+        F: Fn(u64, i64) + Send + 'static,
+    {
+        TrackingLoop {
+            db: Arc::clone(&self.db),
+            extension_registry: self.extension_registry.clone(),
+            window_source: Arc::clone(&self.window_source),
+            idle_source: Arc::clone(&self.idle_source),
+            session_events: Arc::clone(&self.session_events),
+            idle_inhibitors: Arc::clone(&self.idle_inhibitors),
+            clock: Arc::clone(&self.clock),
+            running: Arc::clone(&self.running),
+            paused: Arc::clone(&self.paused),
+            never_idle_apps: Arc::clone(&self.never_idle_apps),
+            idle_threshold: Arc::clone(&self.idle_threshold_secs),
+            privacy: Arc::clone(&self.privacy),
+            schedule: Arc::clone(&self.schedule),
+            on_idle_return,
+            is_idle_mode: false,
+            idle_start_time: None,
+            session: None,
+            processes: ProcessInspector::new(),
+            last_tick: None,
+            suspended: false,
+            locked: false,
+            last_suppressed: None,
+        }
+    }
+}
+
+/// Focus session the loop is currently extending
+struct OpenSession {
+    activity_id: i64,
+    app_name: String,
+    title: Option<String>,
+    /// Browser session still waiting for the extension to report its URL
+    awaiting_url: bool,
+}
+
+/// State of the tracking loop, owned by the tracker thread
+struct TrackingLoop<F> {
+    db: Arc<Database>,
+    extension_registry: Option<Arc<ExtensionRegistry>>,
+    window_source: Arc<dyn WindowSource>,
+    idle_source: Arc<dyn IdleSource>,
+    session_events: Arc<dyn SessionEventSource>,
+    idle_inhibitors: Arc<dyn IdleInhibitSource>,
+    clock: Arc<dyn Clock>,
+    running: Arc<AtomicBool>,
+    paused: Arc<Mutex<Option<ManualPause>>>,
+    never_idle_apps: Arc<Mutex<Vec<String>>>,
+    idle_threshold: Arc<Mutex<u64>>,
+    privacy: Arc<Mutex<PrivacyFilter>>,
+    schedule: Arc<Mutex<Schedule>>,
+    on_idle_return: F,
+    is_idle_mode: bool,
+    idle_start_time: Option<i64>,
+    session: Option<OpenSession>,
+    processes: ProcessInspector,
+    /// Wall and monotonic time of the previous tick
+    last_tick: Option<(i64, Duration)>,
+    suspended: bool,
+    locked: bool,
+    /// Last tick at which idle detection was suppressed despite missing input
+    last_suppressed: Option<i64>,
+}
+
+impl<F> TrackingLoop<F>
+where
+    F: Fn(u64, i64) + Send + 'static,
+{
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Wait for the next poll interval, then sample the sources once
+    fn step(&mut self) {
+        // Sleep for 5 seconds between checks
+        self.clock.sleep(POLL_INTERVAL);
+        self.tick();
+    }
+
+    fn tick(&mut self) {
+        let now = self.clock.now();
+        let previous_tick = self.last_tick.map(|(wall, _)| wall);
+        let away_start = self.away_start(now);
+
+        self.end_expired_pause(now);
+
+        // Skip if paused or outside the schedule; the open session ends at the last tick before
+        if !pause_reasons(&self.paused, &self.schedule, now).is_empty() {
+            self.session = None;
+            return;
+        }
+
+        let idle_time = self.idle_source.idle_seconds();
+
+        // Suspend, lock or a clock jump: the machine was away since `start`
+        if let Some(start) = away_start {
+            if !self.is_idle_mode {
+                self.enter_idle(start, previous_tick);
+            }
+        }
+
+        // Media playback, inhibit requests and never-idle apps override the missing input
+        let idle_threshold_value = *self.idle_threshold.lock().unwrap();
+        let away = self.suspended || self.locked;
+        let suppression = if !away && idle_time > idle_threshold_value {
+            self.idle_suppression()
+        } else {
+            None
+        };
+        if suppression.is_some() {
+            self.last_suppressed = Some(now);
+        }
+
+        // Check for idle state
+        if away || (idle_time > idle_threshold_value && suppression.is_none()) {
+            if !self.is_idle_mode {
+                // Input stopped `idle_time` ago, not when the threshold was crossed,
+                // unless idle detection was suppressed after that
+                let since = if away {
+                    now
+                } else {
+                    let input_stopped = now - idle_time as i64;
+                    self.last_suppressed.map_or(input_stopped, |at| at.max(input_stopped))
+                };
+                self.enter_idle(since, previous_tick);
+            } else {
+                self.update_idle(now);
+            }
+            return;
+        }
+
+        // Exiting idle mode
+        if self.is_idle_mode {
+            self.update_idle(now);
+            self.is_idle_mode = false;
+
+            if let Some(start) = self.idle_start_time {
+                let idle_duration = (now - start) as u64;
+
+                // Always send idle return event, let frontend decide whether to show prompt
+                // Frontend will filter based on prompt_threshold and user preferences
+                (self.on_idle_return)(idle_duration / 60, start); // Convert to minutes, pass started_at
+            }
+
+            self.idle_start_time = None;
+        }
+
+        let db = &self.db;
+
+        // Get active window info
+        let Some(mut window_info) = self.window_source.active_window() else {
+            self.session = None;
+            return;
+        };
+
+        // Nothing about the window is stored before the privacy filter has seen it
+        let privacy = self.privacy.lock().unwrap().clone();
+        let masked = match privacy.decide(&window_info.app_name, window_info.title.as_deref()) {
+            PrivacyDecision::Ignore => {
+                // Focus moved to a window that must not be recorded: close the session now
+                if let Some(previous) = self.session.take() {
+                    if let Err(e) = self.db.extend_activity(previous.activity_id, now) {
+                        eprintln!("Failed to close activity: {}", e);
+                    }
                 }
+                return;
+            }
+            PrivacyDecision::Mask => {
+                window_info.title = Some(MASKED_TITLE.to_string());
+                true
+            }
+            PrivacyDecision::Keep => {
+                window_info.title = window_info.title.map(|title| privacy.redact(&title));
+                false
+            }
+        };
 
-                let idle_time = idle_monitor.get_idle_time();
-                let now = chrono::Utc::now().timestamp();
-
-                // Check for idle state
-                let idle_threshold_value = *idle_threshold.lock().unwrap();
-                if idle_time > idle_threshold_value {
-                    if !is_idle_mode {
-                        // Entering idle mode
-                        is_idle_mode = true;
-                        idle_start_time = Some(now);
-                        
-                        if let Err(e) = db.record_idle_start(now) {
-                            eprintln!("Failed to record idle start: {}", e);
-                        }
-                    } else if let Some(start) = idle_start_time {
-                        // Update idle duration
-                        let duration = now - start;
-                        if let Err(e) = db.update_idle_duration(start, duration) {
-                            eprintln!("Failed to update idle duration: {}", e);
+        let activity_id = match &mut self.session {
+            Some(session)
+                if session.app_name == window_info.app_name && session.title == window_info.title =>
+            {
+                // Same window still focused: extend the open session
+                if let Err(e) = db.extend_activity(session.activity_id, now) {
+                    eprintln!("Failed to extend activity: {}", e);
+                }
+                // The extension may report the tab a moment after the window title changed
+                if session.awaiting_url {
+                    if let Some(url) = reported_tab_url(db, session.title.as_deref(), now) {
+                        session.awaiting_url = false;
+                        let url = privacy.redact(&url);
+                        let domain = extract_domain_from_url(&url);
+                        if let Err(e) = db.set_activity_url(session.activity_id, &url, domain.as_deref()) {
+                            eprintln!("Failed to set activity URL: {}", e);
                         }
                     }
-                    continue;
                 }
-
-                // Exiting idle mode
-                if is_idle_mode {
-                    is_idle_mode = false;
-                    
-                    if let Some(start) = idle_start_time {
-                        let idle_duration = (now - start) as u64;
-                        
-                        // Always send idle return event, let frontend decide whether to show prompt
-                        // Frontend will filter based on prompt_threshold and user preferences
-                        on_idle_return(idle_duration / 60, start); // Convert to minutes, pass started_at
+                session.activity_id
+            }
+            _ => {
+                // Focus changed: close the previous session now and open a new one
+                if let Some(previous) = self.session.take() {
+                    if let Err(e) = db.extend_activity(previous.activity_id, now) {
+                        eprintln!("Failed to close activity: {}", e);
                     }
-                    
-                    idle_start_time = None;
                 }
 
-                // Get active window info
-                if let Some(window_info) = window_tracker.get_active_window() {
-                    let domain = extract_domain(&window_info.app_name, window_info.title.as_deref());
-                    match db.upsert_activity(
-                        &window_info.app_name,
-                        window_info.title.as_deref(),
-                        domain.as_deref(),
-                        now,
-                    ) {
-                        Ok(activity_id) => {
-                            // Apply plugin data hooks if extension registry is available
-                            if let Some(reg) = &extension_registry {
-                                if let Ok(Some(mut activity)) = db.get_activity_by_id(activity_id) {
-                                    if let Err(e) = reg.apply_activity_hooks(&mut activity, &db) {
-                                        eprintln!("Warning: Failed to apply activity hooks: {}", e);
-                                    } else if let Err(e) = db.update_activity_row(&activity) {
-                                        eprintln!("Warning: Failed to persist activity after hooks: {}", e);
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => eprintln!("Failed to record activity: {}", e),
+                // Prefer the exact URL from the browser extension; guess from the title otherwise
+                let browser = is_browser(&window_info.app_name) && !masked;
+                let url = if browser {
+                    reported_tab_url(db, window_info.title.as_deref(), now).map(|url| privacy.redact(&url))
+                } else {
+                    None
+                };
+                let domain = url
+                    .as_deref()
+                    .and_then(extract_domain_from_url)
+                    .or_else(|| extract_domain(&window_info.app_name, window_info.title.as_deref()));
+                let parsed = if masked {
+                    Default::default()
+                } else {
+                    title_parser::parse_title(&window_info.app_name, window_info.title.as_deref()).unwrap_or_default()
+                };
+                // Masked apps keep nothing that identifies what was open in them
+                let process = window_info
+                    .process_id
+                    .filter(|_| !masked)
+                    .map(|pid| self.processes.inspect(pid))
+                    .unwrap_or_default();
+                let repository = process.cwd.as_deref().and_then(|cwd| git::find_repository(Path::new(cwd)));
+                let context = WindowContext {
+                    domain,
+                    url,
+                    project: parsed.project,
+                    document: parsed.document,
+                    exe_path: process.exe_path,
+                    cmdline: process.cmdline.map(|cmdline| privacy.redact(&cmdline)),
+                    cwd: process.cwd,
+                    git_repo: repository.as_ref().map(|repo| repo.root.to_string_lossy().into_owned()),
+                    git_branch: repository.and_then(|repo| repo.branch),
+                };
+                match db.start_activity(&window_info.app_name, window_info.title.as_deref(), &context, now) {
+                    Ok(activity_id) => {
+                        self.session = Some(OpenSession {
+                            activity_id,
+                            app_name: window_info.app_name,
+                            title: window_info.title,
+                            awaiting_url: browser && context.url.is_none(),
+                        });
+                        activity_id
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to record activity: {}", e);
+                        return;
                     }
                 }
             }
-        });
+        };
+
+        if let Some(reason) = &suppression {
+            if let Err(e) = db.set_activity_suppression(activity_id, reason) {
+                eprintln!("Failed to record idle suppression: {}", e);
+            }
+        }
+
+        // Apply plugin data hooks if extension registry is available
+        if let Some(reg) = &self.extension_registry {
+            if let Ok(Some(mut activity)) = db.get_activity_by_id(activity_id) {
+                if let Err(e) = reg.apply_activity_hooks(&mut activity, db) {
+                    eprintln!("Warning: Failed to apply activity hooks: {}", e);
+                } else if let Err(e) = db.update_activity_row(&activity) {
+                    eprintln!("Warning: Failed to persist activity after hooks: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Why missing input should not count as idle right now: the focused app is
+    /// configured as never idle (`app:<name>`), or an inhibitor source is active
+    fn idle_suppression(&self) -> Option<String> {
+        let never_idle_apps = self.never_idle_apps.lock().unwrap().clone();
+        if !never_idle_apps.is_empty() {
+            if let Some(window) = self.window_source.active_window() {
+                if never_idle_apps.iter().any(|pattern| matches_pattern(&window.app_name, pattern)) {
+                    return Some(format!("app:{}", window.app_name));
+                }
+            }
+        }
+        self.idle_inhibitors.active_inhibitor()
+    }
+
+    /// Resume by itself once a timed pause has run out, also after a restart
+    fn end_expired_pause(&self, now: i64) {
+        let mut paused = self.paused.lock().unwrap();
+        if paused.as_ref().is_none_or(|pause| pause.is_active(now)) {
+            return;
+        }
+        if let Some(pause) = paused.take() {
+            pause.record(&self.db, pause.resume_at.unwrap_or(now));
+            ManualPause::save(&self.db, None);
+        }
+    }
+
+    /// Start of an away period that began since the previous tick, if any.
+    /// Drains session events and compares the wall clock against the monotonic clock,
+    /// which stops while the machine is suspended.
+    fn away_start(&mut self, now: i64) -> Option<i64> {
+        let mut away_start: Option<i64> = None;
+
+        for (timestamp, event) in self.session_events.take_events() {
+            match event {
+                SessionEvent::Suspend => self.suspended = true,
+                SessionEvent::Lock => self.locked = true,
+                SessionEvent::Resume => self.suspended = false,
+                SessionEvent::Unlock => self.locked = false,
+            }
+            if matches!(event, SessionEvent::Suspend | SessionEvent::Lock) {
+                away_start = Some(away_start.map_or(timestamp, |start| start.min(timestamp)));
+            }
+        }
+
+        let monotonic = self.clock.monotonic();
+        if let Some((last_wall, last_monotonic)) = self.last_tick.replace((now, monotonic)) {
+            let awake = monotonic.saturating_sub(last_monotonic).as_secs() as i64;
+            if now - last_wall - awake > SUSPEND_GAP_SECS {
+                away_start = Some(away_start.map_or(last_wall, |start| start.min(last_wall)));
+            }
+        }
+
+        away_start
+    }
+
+    /// Close the open focus session at `start` and open an idle record there. The idle
+    /// record never starts before the open session, or before the previous tick when no
+    /// session is open, so time recorded by earlier ticks is left alone.
+    fn enter_idle(&mut self, start: i64, previous_tick: Option<i64>) {
+        self.is_idle_mode = true;
+        let open_activity = self.session.take().map(|session| session.activity_id);
+        let start = match (open_activity, previous_tick) {
+            (None, Some(tick)) => start.max(tick),
+            _ => start,
+        };
+        self.idle_start_time = Some(start);
+
+        // Extends the session to `start`; recording the idle start clips it if it ran past
+        if let Some(activity_id) = open_activity {
+            if let Err(e) = self.db.extend_activity(activity_id, start) {
+                eprintln!("Failed to close activity: {}", e);
+            }
+        }
+        match self.db.record_idle_start(start, open_activity) {
+            Ok(recorded) => self.idle_start_time = Some(recorded),
+            Err(e) => eprintln!("Failed to record idle start: {}", e),
+        }
+    }
+
+    /// Extend the open idle record up to `now`
+    fn update_idle(&self, now: i64) {
+        if let Some(start) = self.idle_start_time {
+            if let Err(e) = self.db.update_idle_duration(start, now - start) {
+                eprintln!("Failed to update idle duration: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_system::extensions::{ActivityHook, EntityType, Extension, ExtensionType};
+    use crate::sources::replay::{ReplayClock, ReplaySource};
+    use std::collections::HashMap;
+
+    const START: i64 = 1_700_000_000;
+
+    /// Build a tracker driven by a replay timeline (timestamps relative to START)
+    fn replay_tracker(
+        timeline: serde_json::Value,
+        extension_registry: Option<Arc<ExtensionRegistry>>,
+    ) -> (Tracker, Arc<ReplayClock>, i64) {
+        let mut samples = timeline.as_array().unwrap().clone();
+        for sample in &mut samples {
+            let offset = sample["timestamp"].as_i64().unwrap();
+            sample["timestamp"] = serde_json::json!(START + offset);
+        }
+        let clock = Arc::new(ReplayClock::new(START));
+        let source = Arc::new(
+            ReplaySource::from_json(&serde_json::Value::Array(samples).to_string(), Arc::clone(&clock)).unwrap(),
+        );
+        let end = source.end();
+        let db = Arc::new(Database::open_in_memory().unwrap());
+        let tracker = Tracker::with_sources(
+            db,
+            extension_registry,
+            source.clone(),
+            source.clone(),
+            source.clone(),
+            source,
+            clock.clone(),
+        );
+        (tracker, clock, end)
+    }
+
+    /// Run the loop until the clock reaches `end`
+    fn run_until<F: Fn(u64, i64) + Send + 'static>(tracker: &Tracker, clock: &ReplayClock, end: i64, on_idle_return: F) {
+        tracker.running.store(true, Ordering::SeqCst);
+        let mut tracking_loop = tracker.tracking_loop(on_idle_return);
+        while clock.now() < end {
+            tracking_loop.step();
+        }
+    }
+
+    fn activities(tracker: &Tracker) -> Vec<crate::database::Activity> {
+        tracker.db.get_activities(0, i64::MAX, None, None, None, None).unwrap()
+    }
+
+    #[test]
+    fn test_focus_changes_create_activities() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                { "timestamp": 60, "app": "Firefox", "title": "Docs" },
+                { "timestamp": 90, "app": "Firefox", "title": "Docs" },
+            ]),
+            None,
+        );
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].app_name, "Code");
+        assert_eq!(rows[0].window_title.as_deref(), Some("main.rs"));
+        assert_eq!((rows[0].started_at, rows[0].ended_at), (START + 5, START + 60));
+        assert_eq!(rows[1].app_name, "Firefox");
+        assert_eq!((rows[1].started_at, rows[1].ended_at), (START + 60, START + 90));
+        assert!(rows.iter().all(|a| !a.is_idle && a.duration_sec == a.ended_at - a.started_at));
+    }
+
+    #[test]
+    fn test_interleaved_windows_never_exceed_wall_clock() {
+        let mut timeline = Vec::new();
+        for i in 0..24 {
+            let (app, title) = if i % 2 == 0 { ("Slack", "general") } else { ("Code", "main.rs") };
+            timeline.push(serde_json::json!({ "timestamp": i * 5, "app": app, "title": title }));
+        }
+        let (tracker, clock, end) = replay_tracker(serde_json::Value::Array(timeline), None);
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        assert_eq!(rows.len(), 23);
+        let total: i64 = rows.iter().map(|a| a.duration_sec).sum();
+        assert_eq!(total, end - (START + 5));
+
+        let stats = tracker.db.get_stats_for_range(START, end).unwrap();
+        assert_eq!(stats.total_seconds, total);
+        let half = tracker.db.get_stats_for_range(START + 30, START + 60).unwrap();
+        assert_eq!(half.total_seconds, 30);
+    }
+
+    #[test]
+    fn test_idle_is_recorded_and_reported_on_return() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                // Last input at 79: the threshold is crossed at 200
+                { "timestamp": 200, "app": "Code", "title": "main.rs", "idle_secs": 121 },
+                { "timestamp": 630, "app": "Code", "title": "main.rs" },
+                { "timestamp": 650, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        let (returns, on_idle_return) = idle_returns();
+        run_until(&tracker, &clock, end, on_idle_return);
+
+        let rows = activities(&tracker);
+        let idle: Vec<_> = rows.iter().filter(|a| a.is_idle).collect();
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].app_name, "Idle");
+        assert_eq!((idle[0].started_at, idle[0].ended_at), (START + 79, START + 630));
+        // The threshold is no longer credited to the app
+        assert_eq!((rows[0].started_at, rows[0].ended_at), (START + 5, START + 79));
+
+        let returns = returns.lock().unwrap();
+        assert_eq!(returns.as_slice(), &[(9, START + 79)]);
+    }
+
+    #[test]
+    fn test_backdated_idle_clips_sessions_and_totals_add_up() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                // A notification takes focus while nobody is at the machine; last input at 60.
+                // Idle is backdated no further than the open Slack session, which it replaces
+                { "timestamp": 100, "app": "Slack", "title": "general", "idle_secs": 40 },
+                { "timestamp": 400, "app": "Code", "title": "main.rs" },
+                { "timestamp": 460, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        let spans: Vec<_> = rows.iter().map(|a| (a.app_name.as_str(), a.started_at - START, a.ended_at - START)).collect();
+        assert_eq!(spans, vec![("Code", 5, 100), ("Idle", 100, 400), ("Code", 400, 460)]);
+        let total: i64 = rows.iter().map(|a| a.duration_sec).sum();
+        assert_eq!(total, end - (START + 5));
+        // Stats count only the time at the machine
+        let active: i64 = rows.iter().filter(|a| !a.is_idle).map(|a| a.duration_sec).sum();
+        assert_eq!(active, 155);
+        assert_eq!(tracker.db.get_stats_for_range(START, end).unwrap().total_seconds, active);
+    }
+
+    #[test]
+    fn test_media_and_never_idle_apps_suppress_idle() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                // No input from here on until 600
+                { "timestamp": 100, "app": "mpv", "title": "talk.mkv", "idle_secs": 1, "inhibitor": "media:mpv" },
+                { "timestamp": 400, "app": "Zoom", "title": "Standup", "idle_secs": 301 },
+                { "timestamp": 500, "app": "Code", "title": "main.rs", "idle_secs": 401 },
+                { "timestamp": 600, "app": "Code", "title": "main.rs" },
+                { "timestamp": 620, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        tracker.set_never_idle_apps(vec!["Zoom".to_string()]);
+        let (returns, on_idle_return) = idle_returns();
+        run_until(&tracker, &clock, end, on_idle_return);
+
+        let rows = activities(&tracker);
+        let spans: Vec<_> = rows
+            .iter()
+            .map(|a| (a.app_name.as_str(), a.started_at - START, a.ended_at - START, a.suppression_reason.as_deref()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Code", 5, 100, None),
+                ("mpv", 100, 400, Some("media:mpv")),
+                // Idle starts at the last suppressed tick, not when input stopped
+                ("Zoom", 400, 495, Some("app:Zoom")),
+                ("Idle", 495, 600, None),
+                ("Code", 600, 620, None),
+            ]
+        );
+        assert_eq!(*returns.lock().unwrap(), vec![(1, START + 495)]);
+    }
+
+    type IdleReturns = Arc<Mutex<Vec<(u64, i64)>>>;
+
+    /// Collects `on_idle_return` calls
+    fn idle_returns() -> (IdleReturns, impl Fn(u64, i64) + Send + 'static) {
+        let returns = Arc::new(Mutex::new(Vec::new()));
+        let returns_clone = Arc::clone(&returns);
+        (returns, move |minutes, started_at| {
+            returns_clone.lock().unwrap().push((minutes, started_at));
+        })
+    }
+
+    #[test]
+    fn test_suspend_gap_is_recorded_as_away_time() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                { "timestamp": 3700, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        let (returns, on_idle_return) = idle_returns();
+        tracker.running.store(true, Ordering::SeqCst);
+        let mut tracking_loop = tracker.tracking_loop(on_idle_return);
+        while clock.now() < START + 60 {
+            tracking_loop.step();
+        }
+        clock.suspend(3600);
+        while clock.now() < end {
+            tracking_loop.step();
+        }
+
+        let rows = activities(&tracker);
+        let spans: Vec<_> = rows.iter().map(|a| (a.app_name.as_str(), a.started_at, a.ended_at)).collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Code", START + 5, START + 60),
+                ("Idle", START + 60, START + 3665),
+                ("Code", START + 3665, end),
+            ]
+        );
+        assert_eq!(returns.lock().unwrap().as_slice(), &[(60, START + 60)]);
+    }
+
+    #[test]
+    fn test_screen_lock_is_recorded_as_away_time() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                { "timestamp": 32, "app": "Code", "title": "main.rs", "session": "lock" },
+                { "timestamp": 332, "app": "Code", "title": "main.rs", "session": "unlock" },
+                { "timestamp": 360, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        let (returns, on_idle_return) = idle_returns();
+        run_until(&tracker, &clock, end, on_idle_return);
+
+        let rows = activities(&tracker);
+        let spans: Vec<_> = rows.iter().map(|a| (a.app_name.as_str(), a.started_at, a.ended_at)).collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Code", START + 5, START + 32),
+                ("Idle", START + 32, START + 335),
+                ("Code", START + 335, end),
+            ]
+        );
+        assert_eq!(returns.lock().unwrap().as_slice(), &[(5, START + 32)]);
+    }
+
+    #[test]
+    fn test_browser_url_comes_from_native_host_report() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Firefox", "title": "Pull requests — Mozilla Firefox" },
+                { "timestamp": 30, "app": "Firefox", "title": "Private tab — Mozilla Firefox" },
+                { "timestamp": 60, "app": "Firefox", "title": "reddit.com - Mozilla Firefox" },
+                { "timestamp": 90, "app": "Firefox", "title": "reddit.com - Mozilla Firefox" },
+            ]),
+            None,
+        );
+        let tab = |title: &str, url: Option<&str>, incognito: bool| {
+            serde_json::json!({ "title": title, "url": url, "incognito": incognito, "browser": "firefox" })
+        };
+        tracker.running.store(true, Ordering::SeqCst);
+        let mut tracking_loop = tracker.tracking_loop(|_, _| {});
+
+        // The extension reports the first tab a few seconds after the window title changed
+        while clock.now() < START + 12 {
+            tracking_loop.step();
+        }
+        let report = tab("Pull requests", Some("https://github.com/bthos/time-tracker-app/pulls"), false);
+        tracker.db.insert_heartbeat("browser", START + 11, 0, &report, 30).unwrap();
+        let private = serde_json::json!({ "title": null, "url": null, "incognito": true, "browser": "firefox" });
+        tracker.db.insert_heartbeat("browser", START + 30, 0, &private, 30).unwrap();
+        while clock.now() < end {
+            tracking_loop.step();
+        }
+
+        let rows = activities(&tracker);
+        let urls: Vec<_> = rows.iter().map(|a| (a.url.as_deref(), a.domain.as_deref())).collect();
+        assert_eq!(
+            urls,
+            vec![
+                (Some("https://github.com/bthos/time-tracker-app/pulls"), Some("github.com")),
+                (None, None),
+                // No report for this tab: the domain is still guessed from the title
+                (None, Some("reddit.com")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_editor_titles_set_project_and_document() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "tracker.rs - time-tracker-app - Visual Studio Code" },
+                { "timestamp": 60, "app": "Alacritty", "title": "notes.md + (/nonexistent/side-project) - NVIM" },
+                { "timestamp": 90, "app": "Slack", "title": "general - Acme - Slack" },
+                { "timestamp": 120, "app": "Slack", "title": "general - Acme - Slack" },
+            ]),
+            None,
+        );
+        let personal = tracker.db.get_categories().unwrap().into_iter().find(|c| c.name == "Personal").unwrap();
+        tracker.db.add_rule("project", "side-*", personal.id, 50).unwrap();
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        let parsed: Vec<_> = rows.iter().map(|a| (a.project.as_deref(), a.document.as_deref())).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (Some("time-tracker-app"), Some("tracker.rs")),
+                (Some("side-project"), Some("notes.md")),
+                (None, None),
+            ]
+        );
+        assert_eq!(rows[1].category_id, Some(personal.id));
+
+        let projects = tracker.db.get_top_projects(START, end, 10).unwrap();
+        let totals: Vec<_> = projects.iter().map(|p| (p.project.as_str(), p.duration_sec)).collect();
+        assert_eq!(totals, vec![("time-tracker-app", 55), ("side-project", 30)]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_process_details_are_recorded() {
+        let pid = std::process::id();
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "tracker-tests", "title": "cargo test", "pid": pid },
+                { "timestamp": 30, "app": "tracker-tests", "title": "cargo test", "pid": pid },
+            ]),
+            None,
+        );
+        let exe = std::env::current_exe().unwrap().to_string_lossy().into_owned();
+        let personal = tracker.db.get_categories().unwrap().into_iter().find(|c| c.name == "Personal").unwrap();
+        let exe_name = exe.rsplit('/').next().unwrap();
+        tracker.db.add_rule("exe_path", &format!("*/{}", exe_name), personal.id, 50).unwrap();
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].exe_path.as_deref(), Some(exe.as_str()));
+        assert_eq!(rows[0].category_id, Some(personal.id));
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(rows[0].cwd.as_deref(), Some(cwd.to_string_lossy().as_ref()));
+        let repository = git::find_repository(&cwd);
+        assert_eq!(
+            rows[0].git_repo.as_deref(),
+            repository.as_ref().map(|repo| repo.root.to_string_lossy()).as_deref()
+        );
+        assert_eq!(rows[0].git_branch, repository.and_then(|repo| repo.branch));
+    }
+
+    #[test]
+    fn test_privacy_filter_runs_before_storage() {
+        let pid = std::process::id();
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "KeePassXC", "title": "Passwords.kdbx - KeePassXC" },
+                { "timestamp": 30, "app": "Signal", "title": "Alice: see you at 8", "pid": pid },
+                { "timestamp": 60, "app": "Firefox", "title": "Statement — Mozilla Firefox Private Browsing" },
+                { "timestamp": 90, "app": "Thunderbird", "title": "Invoice from billing@acme.com - Thunderbird" },
+                { "timestamp": 120, "app": "Thunderbird", "title": "Invoice from billing@acme.com - Thunderbird" },
+            ]),
+            None,
+        );
+        let settings = HashMap::from([
+            (crate::privacy::IGNORED_APPS_KEY.to_string(), r#"["KeePassXC"]"#.to_string()),
+            (crate::privacy::MASKED_APPS_KEY.to_string(), r#"["Signal"]"#.to_string()),
+            (crate::privacy::TITLE_REDACTIONS_KEY.to_string(), r#"["\\S+@\\S+"]"#.to_string()),
+        ]);
+        tracker.db.set_settings(&settings).unwrap();
+        tracker.set_privacy_filter(PrivacyFilter::load(&tracker.db));
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        let stored: Vec<_> = rows.iter().map(|a| (a.app_name.as_str(), a.window_title.as_deref())).collect();
+        assert_eq!(
+            stored,
+            vec![
+                ("Signal", Some(MASKED_TITLE)),
+                ("Thunderbird", Some("Invoice from [redacted] - Thunderbird")),
+            ]
+        );
+        assert_eq!((rows[0].started_at, rows[0].ended_at), (START + 30, START + 60));
+        let masked = &rows[0];
+        assert_eq!(
+            (&masked.exe_path, &masked.cmdline, &masked.cwd, &masked.git_repo, &masked.git_branch),
+            (&None, &None, &None, &None, &None)
+        );
+    }
+
+    #[test]
+    fn test_paused_tracker_records_nothing() {
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                { "timestamp": 60, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        tracker.pause(None, None, None);
+        run_until(&tracker, &clock, end, |_, _| {});
+        assert!(activities(&tracker).is_empty());
+    }
+
+    #[test]
+    fn test_timed_pause_survives_restart_and_is_recorded() {
+        use crate::database::common::SYSTEM_CATEGORY_BREAK;
+
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                { "timestamp": 120, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        tracker.pause(Some(START + 60), Some("Lunch".to_string()), Some(SYSTEM_CATEGORY_BREAK));
+        let pause = tracker.manual_pause().unwrap();
+        assert_eq!((pause.started_at, pause.resume_at), (START, Some(START + 60)));
+
+        // A new tracker on the same database picks the pause up
+        let restarted = Tracker::with_sources(
+            Arc::clone(&tracker.db),
+            None,
+            Arc::clone(&tracker.window_source),
+            Arc::clone(&tracker.idle_source),
+            Arc::clone(&tracker.session_events),
+            Arc::clone(&tracker.idle_inhibitors),
+            Arc::clone(&tracker.clock),
+        );
+        drop(tracker);
+        assert_eq!(restarted.manual_pause(), Some(pause));
+        assert_eq!(restarted.pause_reasons(), vec![PauseReason::Manual]);
+
+        run_until(&restarted, &clock, end, |_, _| {});
+
+        let rows = activities(&restarted);
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].started_at, rows[0].ended_at), (START + 60, START + 120));
+        let entries = restarted.db.get_manual_entries(0, i64::MAX).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].started_at, entries[0].ended_at), (START, START + 60));
+        assert_eq!(entries[0].category_id, Some(SYSTEM_CATEGORY_BREAK));
+        assert_eq!(entries[0].description.as_deref(), Some("Lunch"));
+        assert_eq!(restarted.manual_pause(), None);
+        assert_eq!(restarted.db.get_setting(PAUSE_STATE_KEY).unwrap(), None);
+    }
+
+    #[test]
+    fn test_tracking_stops_outside_schedule() {
+        use chrono::{Datelike, Local, TimeZone, Timelike};
+        use crate::database::ScheduleWindow;
+
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Code", "title": "main.rs" },
+                { "timestamp": 300, "app": "Code", "title": "main.rs" },
+            ]),
+            None,
+        );
+        // The schedule ends two minutes into the replay, at the start of a local minute
+        let local = Local.timestamp_opt(START, 0).unwrap();
+        let minute = i64::from(local.hour() * 60 + local.minute());
+        let schedule_end = START - i64::from(local.second()) + 120;
+        let window = ScheduleWindow {
+            id: 0,
+            weekday: i64::from(local.weekday().num_days_from_monday()),
+            start_minute: minute,
+            end_minute: minute + 2,
+        };
+        tracker.set_schedule(Schedule::new(true, vec![window], vec![]));
+        assert_eq!(tracker.in_schedule(), Some(true));
+
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].ended_at < schedule_end && rows[0].ended_at >= schedule_end - 5);
+        assert_eq!(tracker.in_schedule(), Some(false));
+        assert_eq!(tracker.pause_reasons(), vec![PauseReason::OutsideSchedule]);
+        tracker.pause(None, None, None);
+        assert_eq!(tracker.pause_reasons(), vec![PauseReason::Manual, PauseReason::OutsideSchedule]);
+        tracker.set_schedule(Schedule::default());
+        tracker.resume();
+        assert!(!tracker.is_paused());
+        assert_eq!(tracker.in_schedule(), None);
+    }
+
+    #[test]
+    fn test_activity_hooks_are_persisted() {
+        let registry = Arc::new(ExtensionRegistry::new());
+        registry
+            .register(Extension {
+                plugin_id: "test".to_string(),
+                entity_type: EntityType::Activity,
+                extension_type: ExtensionType::DataHook,
+                schema_changes: vec![],
+                model_fields: vec![],
+                hook: Some(ActivityHook {
+                    on_upsert: Box::new(|activity, _db| {
+                        activity.domain = Some("hooked.example".to_string());
+                        Ok(())
+                    }),
+                }),
+                query_filters: vec![],
+            })
+            .unwrap();
+
+        let (tracker, clock, end) = replay_tracker(
+            serde_json::json!([
+                { "timestamp": 0, "app": "Terminal", "title": "bash" },
+                { "timestamp": 20, "app": "Terminal", "title": "bash" },
+            ]),
+            Some(registry),
+        );
+        run_until(&tracker, &clock, end, |_, _| {});
+
+        let rows = activities(&tracker);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].domain.as_deref(), Some("hooked.example"));
     }
 }
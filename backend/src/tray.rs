@@ -1,5 +1,6 @@
 //! System tray module - Manages the system tray icon and menu
 
+use crate::database::RunningTimer;
 use tauri::{
     AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu,
     SystemTrayMenuItem,
@@ -7,12 +8,21 @@ use tauri::{
 
 /// Create the system tray
 pub fn create_tray() -> SystemTray {
-    let menu = create_tray_menu("0h 0m");
+    let menu = create_tray_menu("0h 0m", None, &[]);
     SystemTray::new().with_menu(menu)
 }
 
-/// Create the tray menu with current time
-pub fn create_tray_menu(today_time: &str) -> SystemTrayMenu {
+/// Prefix of the menu item ids that stop a running timer
+const STOP_TIMER_PREFIX: &str = "stop_timer:";
+
+/// Format seconds as "Xh Ym"
+fn format_duration(total_seconds: i64) -> String {
+    format!("{}h {}m", total_seconds / 3600, (total_seconds % 3600) / 60)
+}
+
+/// Create the tray menu with current time, running timers and, when a schedule is
+/// enabled, whether the current time is inside it
+pub fn create_tray_menu(today_time: &str, in_schedule: Option<bool>, timers: &[RunningTimer]) -> SystemTrayMenu {
     let today = CustomMenuItem::new("today", format!("Today: {}", today_time)).disabled();
     let separator1 = SystemTrayMenuItem::Separator;
     
@@ -30,8 +40,18 @@ pub fn create_tray_menu(today_time: &str) -> SystemTrayMenu {
     
     let quit = CustomMenuItem::new("quit", "❌ Quit");
 
-    SystemTrayMenu::new()
-        .add_item(today)
+    let mut menu = SystemTrayMenu::new().add_item(today);
+    if let Some(in_schedule) = in_schedule {
+        let label = if in_schedule { "🕘 Inside work hours" } else { "🌙 Outside work hours" };
+        menu = menu.add_item(CustomMenuItem::new("schedule", label).disabled());
+    }
+    let now = chrono::Utc::now().timestamp();
+    for timer in timers {
+        let label = format!("⏹️  Stop {} ({})", timer.name, format_duration(now - timer.started_at));
+        menu = menu.add_item(CustomMenuItem::new(format!("{}{}", STOP_TIMER_PREFIX, timer.id), label));
+    }
+
+    menu
         .add_native_item(separator1)
         .add_item(start_activity)
         .add_item(thinking_mode)
@@ -46,11 +66,18 @@ pub fn create_tray_menu(today_time: &str) -> SystemTrayMenu {
 
 /// Update tray menu with new time
 pub fn update_tray_time(app: &AppHandle, total_seconds: i64) {
-    let hours = total_seconds / 3600;
-    let minutes = (total_seconds % 3600) / 60;
-    let time_str = format!("{}h {}m", hours, minutes);
+    let time_str = format_duration(total_seconds);
+
+    let state = app.try_state::<crate::commands::AppState>();
+    let in_schedule = state
+        .as_ref()
+        .and_then(|state| state.tracker.lock().ok()?.as_ref().and_then(|t| t.in_schedule()));
+    let timers = state
+        .as_ref()
+        .and_then(|state| state.db.get_running_timers().ok())
+        .unwrap_or_default();
     
-    let new_menu = create_tray_menu(&time_str);
+    let new_menu = create_tray_menu(&time_str, in_schedule, &timers);
     if let Err(e) = app.tray_handle().set_menu(new_menu) {
         eprintln!("Failed to update tray menu: {}", e);
     }
@@ -131,6 +158,27 @@ fn handle_menu_click(app: &AppHandle, id: &str) {
                 window.emit("toggle-pause", ()).ok();
             }
         }
-        _ => {}
+        _ => {
+            if let Some(timer_id) = id.strip_prefix(STOP_TIMER_PREFIX).and_then(|id| id.parse::<i64>().ok()) {
+                stop_timer(app, timer_id);
+            }
+        }
+    }
+}
+
+/// Stop a running timer from the tray and refresh the menu; stopping the thinking mode
+/// timer resumes tracking as `stop_thinking_mode` does
+fn stop_timer(app: &AppHandle, timer_id: i64) {
+    let Some(state) = app.try_state::<crate::commands::AppState>() else {
+        return;
+    };
+    if let Err(e) = crate::commands::timers::stop_running_timer(&state, timer_id) {
+        eprintln!("Failed to stop timer: {}", e);
+    }
+    if let Some(window) = app.get_window("main") {
+        window.emit("timers-changed", ()).ok();
+    }
+    if let Ok(total) = state.db.get_today_total() {
+        update_tray_time(app, total);
     }
 }
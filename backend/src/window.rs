@@ -17,26 +17,44 @@ use std::os::windows::ffi::OsStringExt;
 
 use active_win_pos_rs::get_active_window;
 
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+
 /// Information about the active window
 #[derive(Debug, Clone)]
 pub struct WindowInfo {
     pub app_name: String,
     pub title: Option<String>,
-    #[allow(dead_code)]
     pub process_id: Option<u32>,
 }
 
 /// Window tracker for detecting active windows
-pub struct WindowTracker;
+pub struct WindowTracker {
+    /// Native X11 connection; None when no X server is available (e.g. pure Wayland)
+    #[cfg(target_os = "linux")]
+    x11: Mutex<Option<x11::X11Backend>>,
+}
 
 impl WindowTracker {
     /// Create a new window tracker
     pub fn new() -> Self {
-        Self
+        Self {
+            #[cfg(target_os = "linux")]
+            x11: Mutex::new(x11::X11Backend::connect()),
+        }
     }
 
     /// Get the currently active window using native APIs
     pub fn get_active_window(&self) -> Option<WindowInfo> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(info) = self.get_active_window_x11() {
+                return Some(info);
+            }
+        }
+
         match get_active_window() {
             Ok(active_window) => {
                 #[cfg(windows)]
@@ -108,6 +126,16 @@ impl WindowTracker {
         }
     }
 
+    /// Query the X11 backend, reconnecting once if the server connection was lost
+    #[cfg(target_os = "linux")]
+    fn get_active_window_x11(&self) -> Option<WindowInfo> {
+        let mut backend = self.x11.lock().ok()?;
+        if backend.as_ref().is_some_and(|b| !b.is_connected()) {
+            *backend = x11::X11Backend::connect();
+        }
+        backend.as_ref()?.active_window()
+    }
+
     /// Fallback implementation for platforms where native API might fail
     fn get_active_window_fallback(&self) -> Option<WindowInfo> {
         // If active-win-pos-rs fails, return None
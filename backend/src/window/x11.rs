@@ -0,0 +1,267 @@
+//! X11 backend - Reads the focused window from EWMH properties on the root window
+//!
+//! Uses `_NET_ACTIVE_WINDOW` to find the focused client, then `_NET_WM_NAME`
+//! (falling back to `WM_NAME`) for the title, `WM_CLASS` for the application
+//! name and `_NET_WM_PID` for the owning process.
+
+use xcb::{x, Xid};
+
+use super::WindowInfo;
+
+/// Maximum property length requested from the server (in 32-bit units)
+const PROPERTY_LENGTH: u32 = 1024;
+
+/// Atoms resolved once per connection
+struct Atoms {
+    net_active_window: x::Atom,
+    net_wm_name: x::Atom,
+    net_wm_pid: x::Atom,
+    utf8_string: x::Atom,
+}
+
+/// Connection to an X server that supports EWMH
+pub struct X11Backend {
+    conn: xcb::Connection,
+    root: x::Window,
+    atoms: Atoms,
+}
+
+impl X11Backend {
+    /// Connect to the X server from `$DISPLAY`.
+    /// Returns None if there is no X server or the window manager does not support EWMH.
+    pub fn connect() -> Option<Self> {
+        Self::connect_to(None)
+    }
+
+    /// Connect to a specific display (e.g. ":99" for an Xvfb instance)
+    pub fn connect_to(display: Option<&str>) -> Option<Self> {
+        let (conn, screen_num) = xcb::Connection::connect(display).ok()?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen_num as usize)?
+            .root();
+
+        let net_active_window = intern_atom(&conn, b"_NET_ACTIVE_WINDOW").ok()?;
+        if net_active_window == x::ATOM_NONE {
+            return None;
+        }
+
+        let atoms = Atoms {
+            net_active_window,
+            net_wm_name: intern_atom(&conn, b"_NET_WM_NAME").ok()?,
+            net_wm_pid: intern_atom(&conn, b"_NET_WM_PID").ok()?,
+            utf8_string: intern_atom(&conn, b"UTF8_STRING").ok()?,
+        };
+
+        Some(Self { conn, root, atoms })
+    }
+
+    /// Check if the connection to the X server is still usable
+    pub fn is_connected(&self) -> bool {
+        self.conn.has_error().is_ok()
+    }
+
+    /// Get information about the currently focused window
+    pub fn active_window(&self) -> Option<WindowInfo> {
+        let window = self.active_window_id()?;
+
+        let title = self.window_title(window);
+        let process_id = self.window_pid(window);
+        let app_name = self
+            .window_class(window)
+            .or_else(|| process_id.and_then(process_name))
+            .unwrap_or_else(|| format!("Window_{}", window.resource_id()));
+
+        Some(WindowInfo {
+            app_name,
+            title,
+            process_id,
+        })
+    }
+
+    fn active_window_id(&self) -> Option<x::Window> {
+        let reply = self
+            .get_property(self.root, self.atoms.net_active_window, x::ATOM_WINDOW, 1)
+            .ok()?;
+        let window = *reply.value::<x::Window>().first()?;
+        if window.is_none() {
+            None
+        } else {
+            Some(window)
+        }
+    }
+
+    /// Read `_NET_WM_NAME` (UTF-8), falling back to the legacy `WM_NAME`
+    fn window_title(&self, window: x::Window) -> Option<String> {
+        let ewmh = self
+            .get_property(window, self.atoms.net_wm_name, self.atoms.utf8_string, PROPERTY_LENGTH)
+            .ok()
+            .and_then(|reply| non_empty(String::from_utf8_lossy(reply.value::<u8>()).into_owned()));
+
+        ewmh.or_else(|| {
+            self.get_property(window, x::ATOM_WM_NAME, x::ATOM_ANY, PROPERTY_LENGTH)
+                .ok()
+                .and_then(|reply| non_empty(String::from_utf8_lossy(reply.value::<u8>()).into_owned()))
+        })
+    }
+
+    /// Read the class part of `WM_CLASS` ("instance\0Class\0")
+    fn window_class(&self, window: x::Window) -> Option<String> {
+        let reply = self
+            .get_property(window, x::ATOM_WM_CLASS, x::ATOM_STRING, PROPERTY_LENGTH)
+            .ok()?;
+        parse_wm_class(reply.value::<u8>())
+    }
+
+    fn window_pid(&self, window: x::Window) -> Option<u32> {
+        let reply = self
+            .get_property(window, self.atoms.net_wm_pid, x::ATOM_CARDINAL, 1)
+            .ok()?;
+        reply.value::<u32>().first().copied().filter(|pid| *pid != 0)
+    }
+
+    fn get_property(
+        &self,
+        window: x::Window,
+        property: x::Atom,
+        r#type: x::Atom,
+        long_length: u32,
+    ) -> xcb::Result<x::GetPropertyReply> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property,
+            r#type,
+            long_offset: 0,
+            long_length,
+        });
+        self.conn.wait_for_reply(cookie)
+    }
+}
+
+fn intern_atom(conn: &xcb::Connection, name: &[u8]) -> xcb::Result<x::Atom> {
+    let cookie = conn.send_request(&x::InternAtom {
+        only_if_exists: true,
+        name,
+    });
+    Ok(conn.wait_for_reply(cookie)?.atom())
+}
+
+/// Extract the class name from a raw `WM_CLASS` value, preferring the class over the instance
+fn parse_wm_class(raw: &[u8]) -> Option<String> {
+    let mut parts = raw
+        .split(|b| *b == 0)
+        .filter(|part| !part.is_empty())
+        .map(|part| String::from_utf8_lossy(part).into_owned());
+    let instance = parts.next();
+    parts.next().or(instance)
+}
+
+/// Resolve a process name from /proc when the window has no WM_CLASS
+fn process_name(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .and_then(|name| non_empty(name.trim().to_string()))
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wm_class() {
+        assert_eq!(parse_wm_class(b"navigator\0Firefox\0"), Some("Firefox".to_string()));
+        assert_eq!(parse_wm_class(b"code\0"), Some("code".to_string()));
+        assert_eq!(parse_wm_class(b""), None);
+    }
+
+    /// Runs against the display in `$DISPLAY`, e.g. `xvfb-run cargo test`.
+    /// Xvfb has no window manager, so the test publishes `_NET_ACTIVE_WINDOW` itself.
+    #[test]
+    fn test_active_window_on_display() {
+        if std::env::var("DISPLAY").is_err() {
+            eprintln!("Skipping X11 test: DISPLAY is not set");
+            return;
+        }
+
+        let (conn, screen_num) = xcb::Connection::connect(None).expect("X server");
+        let screen = conn.get_setup().roots().nth(screen_num as usize).unwrap();
+        let root = screen.root();
+
+        let window: x::Window = conn.generate_id();
+        conn.send_and_check_request(&x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: window,
+            parent: root,
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+            border_width: 0,
+            class: x::WindowClass::InputOutput,
+            visual: screen.root_visual(),
+            value_list: &[],
+        })
+        .unwrap();
+
+        let intern = |name: &[u8]| {
+            let cookie = conn.send_request(&x::InternAtom { only_if_exists: false, name });
+            conn.wait_for_reply(cookie).unwrap().atom()
+        };
+        let net_active_window = intern(b"_NET_ACTIVE_WINDOW");
+        let net_wm_name = intern(b"_NET_WM_NAME");
+        let net_wm_pid = intern(b"_NET_WM_PID");
+        let utf8_string = intern(b"UTF8_STRING");
+
+        conn.send_and_check_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: net_wm_name,
+            r#type: utf8_string,
+            data: "main.rs — Visual Studio Code".as_bytes(),
+        })
+        .unwrap();
+        conn.send_and_check_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: x::ATOM_WM_CLASS,
+            r#type: x::ATOM_STRING,
+            data: b"code\0Code\0",
+        })
+        .unwrap();
+        conn.send_and_check_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: net_wm_pid,
+            r#type: x::ATOM_CARDINAL,
+            data: &[std::process::id()],
+        })
+        .unwrap();
+        conn.send_and_check_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: root,
+            property: net_active_window,
+            r#type: x::ATOM_WINDOW,
+            data: &[window],
+        })
+        .unwrap();
+
+        let backend = X11Backend::connect().expect("EWMH atoms should exist");
+        let info = backend.active_window().expect("active window");
+        assert_eq!(info.app_name, "Code");
+        assert_eq!(info.title.as_deref(), Some("main.rs — Visual Studio Code"));
+        assert_eq!(info.process_id, Some(std::process::id()));
+
+        conn.send_request(&x::DestroyWindow { window });
+        conn.flush().ok();
+    }
+}
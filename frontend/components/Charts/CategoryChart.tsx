@@ -39,10 +39,10 @@ const CategoryChart: React.FC<CategoryChartProps> = ({ data, totalTime }) => {
 
   const chartData = data.map(item => ({
     name: item.category.name,
-    value: item.duration_sec,
+    value: item.total_duration_sec,
     color: item.category.color,
     icon: item.category.icon,
-    percentage: totalTime > 0 ? ((item.duration_sec / totalTime) * 100).toFixed(1) : 0,
+    percentage: totalTime > 0 ? ((item.total_duration_sec / totalTime) * 100).toFixed(1) : 0,
   }));
 
   const CustomTooltip = ({ active, payload }: TooltipProps<number, string>) => {
@@ -16,6 +16,8 @@ interface HeaderProps {
 export default function Header({ onAddEntry, onMenuClick }: HeaderProps) {
   const isTrackingPaused = useStore((state) => state.isTrackingPaused);
   const setIsTrackingPaused = useStore((state) => state.setIsTrackingPaused);
+  const pauseTimerId = useStore((state) => state.pauseTimerId);
+  const setPauseTimerId = useStore((state) => state.setPauseTimerId);
   const isThinkingMode = useStore((state) => state.isThinkingMode);
   const setIsThinkingMode = useStore((state) => state.setIsThinkingMode);
   const getDateRange = useStore((state) => state.getDateRange);
@@ -35,6 +37,18 @@ export default function Header({ onAddEntry, onMenuClick }: HeaderProps) {
     return breakCategory?.id;
   }, [categories]);
 
+  // Останавливаем таймер паузы, если он ещё идёт
+  const stopPauseTimer = async () => {
+    if (pauseTimerId !== null) {
+      try {
+        await manualEntriesApi.stopManualEntry(pauseTimerId);
+      } catch (error) {
+        // Игнорируем ошибку, если таймер уже остановлен
+      }
+      setPauseTimerId(null);
+    }
+  };
+
   const handleThinkingMode = async () => {
     try {
       if (isThinkingMode) {
@@ -47,11 +61,7 @@ export default function Header({ onAddEntry, onMenuClick }: HeaderProps) {
       } else {
         // Если Pause активен, выключаем его перед включением Thinking mode
         if (isTrackingPaused) {
-          try {
-            await manualEntriesApi.stopManualEntry();
-          } catch (error) {
-            // Игнорируем ошибку, если нет активной manual entry
-          }
+          await stopPauseTimer();
           await resumeTracking.mutateAsync();
           setIsTrackingPaused(false);
         }
@@ -77,11 +87,7 @@ export default function Header({ onAddEntry, onMenuClick }: HeaderProps) {
           showSuccess('Thinking mode stopped, tracking resumed');
         } else {
           // Останавливаем break entry и возобновляем трекинг
-          try {
-            await manualEntriesApi.stopManualEntry();
-          } catch (error) {
-            // Игнорируем ошибку, если нет активной manual entry
-          }
+          await stopPauseTimer();
           await resumeTracking.mutateAsync();
           setIsTrackingPaused(false);
           showSuccess('Tracking resumed');
@@ -94,7 +100,7 @@ export default function Header({ onAddEntry, onMenuClick }: HeaderProps) {
         }
         // Создаем break entry для отслеживания времени паузы
         if (breakCategoryId) {
-          await manualEntriesApi.startManualEntry(breakCategoryId, 'Pause');
+          setPauseTimerId(await manualEntriesApi.startManualEntry(breakCategoryId, 'Pause'));
           await pauseTracking.mutateAsync();
           setIsTrackingPaused(true);
           showSuccess('Tracking paused');
@@ -40,6 +40,8 @@ export const Rules: React.FC = () => {
       case 'app_name': return '📱 Application';
       case 'window_title': return '🪟 Window Title';
       case 'domain': return '🌐 Domain';
+      case 'project': return '📁 Project';
+      case 'exe_path': return '⚙️ Executable';
       default: return type;
     }
   };
@@ -92,6 +94,8 @@ export const Rules: React.FC = () => {
                 <option value="app_name">By Application Name</option>
                 <option value="window_title">By Window Title</option>
                 <option value="domain">By Domain</option>
+                <option value="project">By Project</option>
+                <option value="exe_path">By Executable Path</option>
               </select>
             </div>
 
@@ -123,6 +127,8 @@ export const Rules: React.FC = () => {
                 placeholder={
                   newRule.rule_type === 'app_name' ? 'Example: *Code*, Slack, Chrome' :
                   newRule.rule_type === 'window_title' ? 'Example: *jira*, *github*' :
+                  newRule.rule_type === 'project' ? 'Example: time-tracker*, *-client' :
+                  newRule.rule_type === 'exe_path' ? 'Example: /usr/bin/firefox, */google-chrome*' :
                   'Example: youtube.com, *.google.com'
                 }
                 className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
@@ -1,11 +1,12 @@
 import React, { useState, useEffect } from 'react';
-import { Rule, Category } from '../../types';
+import { Rule, Category, ReapplyProgress } from '../../types';
 import Button from '../Common/Button';
 import { Check, X, Trash2, Edit2, RotateCcw } from 'lucide-react';
 import { useRules } from '../../hooks/useRules';
 import { useStore } from '../../store';
 import { activitiesApi } from '../../services/api/activities';
 import { useQueryClient } from '@tanstack/react-query';
+import { listen } from '@tauri-apps/api/event';
 
 export const RulesSettings: React.FC = () => {
   const { rules, isLoading: rulesLoading, createRule, updateRule, deleteRule } = useRules();
@@ -17,6 +18,7 @@ export const RulesSettings: React.FC = () => {
   const [editingRuleId, setEditingRuleId] = useState<number | null>(null);
   const [isCreatingRule, setIsCreatingRule] = useState(false);
   const [isReapplyingRules, setIsReapplyingRules] = useState(false);
+  const [reapplyProgress, setReapplyProgress] = useState<ReapplyProgress | null>(null);
   const [newRule, setNewRule] = useState<Partial<Rule>>({
     rule_type: 'app_name',
     pattern: '',
@@ -175,12 +177,15 @@ export const RulesSettings: React.FC = () => {
   };
 
   const handleReapplyRules = async () => {
-    if (confirm('Reapply rules to all existing records? This will update categories for all activities.')) {
+    if (confirm('Reapply rules to all existing records? Categories you set by hand are kept.')) {
+      const unlisten = await listen<ReapplyProgress>('reapply-progress', (event) => {
+        setReapplyProgress(event.payload);
+      });
       try {
         setIsReapplyingRules(true);
-        await activitiesApi.reapplyCategorizationRules();
+        const result = await activitiesApi.reapplyCategorizationRules();
         const { showSuccess } = await import('../../utils/toast');
-        showSuccess('Rules successfully applied to all records');
+        showSuccess(`Rules applied: ${result.updated} activities updated, ${result.skipped_manual} kept as set by hand`);
         queryClient.invalidateQueries({ queryKey: ['activities'] });
         queryClient.invalidateQueries({ queryKey: ['dailyStats'] });
         queryClient.invalidateQueries({ queryKey: ['timeline'] });
@@ -189,7 +194,9 @@ export const RulesSettings: React.FC = () => {
         const { handleApiError } = await import('../../utils/toast');
         handleApiError(error, 'Failed to reapply rules');
       } finally {
+        unlisten();
         setIsReapplyingRules(false);
+        setReapplyProgress(null);
       }
     }
   };
@@ -199,6 +206,8 @@ export const RulesSettings: React.FC = () => {
       case 'app_name': return '📱 Application';
       case 'window_title': return '🪟 Window Title';
       case 'domain': return '🌐 Domain';
+      case 'project': return '📁 Project';
+      case 'exe_path': return '⚙️ Executable';
       default: return type;
     }
   };
@@ -220,7 +229,9 @@ export const RulesSettings: React.FC = () => {
             disabled={isReapplyingRules}
           >
             {isReapplyingRules ? (
-              'Applying...'
+              reapplyProgress && reapplyProgress.total > 0
+                ? `Applying... ${Math.round((reapplyProgress.processed / reapplyProgress.total) * 100)}%`
+                : 'Applying...'
             ) : (
               <>
                 <RotateCcw className="w-4 h-4 mr-1" />
@@ -261,6 +272,8 @@ export const RulesSettings: React.FC = () => {
                   <option value="app_name">By Application Name</option>
                   <option value="window_title">By Window Title</option>
                   <option value="domain">By Domain</option>
+                  <option value="project">By Project</option>
+                  <option value="exe_path">By Executable Path</option>
                 </select>
               </div>
 
@@ -301,6 +314,8 @@ export const RulesSettings: React.FC = () => {
                   placeholder={
                     newRule.rule_type === 'app_name' ? 'Example: *Code*, Slack, Chrome' :
                     newRule.rule_type === 'window_title' ? 'Example: *jira*, *github*' :
+                    newRule.rule_type === 'project' ? 'Example: time-tracker*, *-client' :
+                    newRule.rule_type === 'exe_path' ? 'Example: /usr/bin/firefox, */google-chrome*' :
                     'Example: youtube.com, *.google.com'
                   }
                   className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
@@ -388,6 +403,8 @@ export const RulesSettings: React.FC = () => {
                             <option value="app_name">By Application Name</option>
                             <option value="window_title">By Window Title</option>
                             <option value="domain">By Domain</option>
+                            <option value="project">By Project</option>
+                            <option value="exe_path">By Executable Path</option>
                           </select>
                         </div>
 
@@ -424,6 +441,8 @@ export const RulesSettings: React.FC = () => {
                             placeholder={
                               newRule.rule_type === 'app_name' ? 'Example: *Code*, Slack, Chrome' :
                               newRule.rule_type === 'window_title' ? 'Example: *jira*, *github*' :
+                              newRule.rule_type === 'project' ? 'Example: time-tracker*, *-client' :
+                              newRule.rule_type === 'exe_path' ? 'Example: /usr/bin/firefox, */google-chrome*' :
                               'Example: youtube.com, *.google.com'
                             }
                             className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
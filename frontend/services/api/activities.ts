@@ -1,4 +1,4 @@
-import { Activity, DateRange } from '../../types';
+import { Activity, ActivityFilter, BulkAction, BulkResult, DateRange, ReapplyOptions, ReapplyProgress } from '../../types';
 import { invoke, dateRangeToParams } from './utils';
 
 export const activitiesApi = {
@@ -7,6 +7,7 @@ export const activitiesApi = {
    * @param range - Date range to query
    * @param limit - Optional: maximum number of activities to return
    * @param offset - Optional: number of activities to skip (requires limit)
+   * @param filter - Optional: idle, category and tag include/exclude filters
    * @returns Promise resolving to array of activities
    * 
    * Examples:
@@ -14,7 +15,7 @@ export const activitiesApi = {
    * - getActivities(range, 100) - returns first 100 activities
    * - getActivities(range, 100, 50) - returns 100 activities starting from 50th
    */
-  getActivities: (range: DateRange, limit?: number, offset?: number): Promise<Activity[]> => {
+  getActivities: (range: DateRange, limit?: number, offset?: number, filter?: ActivityFilter): Promise<Activity[]> => {
     const params: Record<string, unknown> = {
       ...dateRangeToParams(range),
     };
@@ -22,6 +23,7 @@ export const activitiesApi = {
     // This allows the backend to handle: limit only, limit+offset, or neither
     if (limit !== undefined) params.limit = limit;
     if (offset !== undefined) params.offset = offset;
+    if (filter !== undefined) params.filter = filter;
     return invoke('get_activities', params);
   },
   
@@ -37,7 +39,33 @@ export const activitiesApi = {
     return invoke('delete_activity', { id });
   },
   
-  reapplyCategorizationRules: (): Promise<void> => {
-    return invoke('reapply_categorization_rules');
+  // Returns the earlier and the later part; the later part keeps the id
+  splitActivity: (id: number, at: number): Promise<Activity[]> => {
+    return invoke('split_activity', { id, at });
+  },
+  
+  // Same app only, with nothing tracked in between; the latest activity is kept
+  mergeActivities: (ids: number[]): Promise<Activity> => {
+    return invoke('merge_activities', { ids });
+  },
+  
+  trimActivity: (id: number, startedAt: number, endedAt: number): Promise<Activity> => {
+    return invoke('trim_activity', { id, startedAt, endedAt });
+  },
+  
+  /**
+   * Apply an action to every activity in the range matching the filter, in one transaction.
+   * With dryRun nothing is saved; the result shows what would change.
+   */
+  bulkEdit: (range: DateRange, filter: ActivityFilter, action: BulkAction, dryRun = false): Promise<BulkResult> => {
+    return invoke('bulk_edit_activities', { ...dateRangeToParams(range), filter, action, dryRun });
+  },
+  
+  /**
+   * Recategorize activities with the current rules. Categories set by hand are kept unless options.force is set.
+   * Progress is reported through `reapply-progress` events.
+   */
+  reapplyCategorizationRules: (options?: ReapplyOptions): Promise<ReapplyProgress> => {
+    return invoke('reapply_categorization_rules', { options });
   },
 };
@@ -0,0 +1,26 @@
+import { CategoryRate } from '../../types';
+import { invoke } from './utils';
+
+export const billingApi = {
+  getCategoryRates: (categoryId?: number): Promise<CategoryRate[]> => {
+    return invoke('get_category_rates', { categoryId: categoryId ?? null });
+  },
+
+  setCategoryRate: (
+    categoryId: number,
+    hourlyRate: number,
+    currency: string,
+    effectiveFrom: number
+  ): Promise<CategoryRate> => {
+    return invoke('set_category_rate', { categoryId, hourlyRate, currency, effectiveFrom });
+  },
+
+  deleteCategoryRate: (id: number): Promise<void> => {
+    return invoke('delete_category_rate', { id });
+  },
+
+  // null makes the category follow its parent
+  setCategoryBillable: (id: number, isBillable: boolean | null): Promise<void> => {
+    return invoke('set_category_billable', { id, isBillable });
+  },
+};
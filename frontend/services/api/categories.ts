@@ -36,6 +36,10 @@ export const categoriesApi = {
     return invoke('delete_category', { id });
   },
   
+  setCategoryParent: (id: number, parentId: number | null): Promise<void> => {
+    return invoke('set_category_parent', { id, parentId });
+  },
+  
   resetSystemCategory: (id: number): Promise<Category> => {
     return invoke('reset_system_category', { id });
   },
@@ -0,0 +1,11 @@
+import { WatcherEvent, DateRange } from '../../types';
+import { invoke, dateRangeToParams } from './utils';
+
+export const eventsApi = {
+  getEvents: (range: DateRange, source?: string): Promise<WatcherEvent[]> => {
+    return invoke('get_events', {
+      ...dateRangeToParams(range),
+      source,
+    });
+  },
+};
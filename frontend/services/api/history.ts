@@ -0,0 +1,13 @@
+import { Change } from '../../types';
+import { invoke } from './utils';
+
+export const historyApi = {
+  listChanges: (limit?: number): Promise<Change[]> => {
+    return invoke('list_changes', { limit });
+  },
+
+  /** Undo the most recent change; resolves to null when there is nothing left to undo */
+  undoLastChange: (): Promise<Change | null> => {
+    return invoke('undo_last_change');
+  },
+};
@@ -1,9 +1,14 @@
+import { IdleSegment, ManualEntry } from '../../types';
 import { invoke, dateToTimestamp } from './utils';
 
 export const idleApi = {
   getIdleTime: (): Promise<number> => {
     return invoke('get_idle_time');
   },
+
+  getIdleSource: (): Promise<string> => {
+    return invoke('get_idle_source');
+  },
   
   classifyIdleTime: (
     idleStart: Date,
@@ -18,4 +23,9 @@ export const idleApi = {
       description,
     });
   },
+
+  /** Replace an idle period with one manual entry per segment; uncovered time stays idle */
+  splitIdleTime: (idleStartedAt: number, segments: IdleSegment[]): Promise<ManualEntry[]> => {
+    return invoke('split_idle_time', { idleStartedAt, segments });
+  },
 };
@@ -10,6 +10,14 @@ export * from './idle';
 export * from './export';
 export * from './window';
 export * from './domains';
+export * from './events';
+export * from './schedule';
+export * from './timers';
+export * from './projects';
+export * from './tags';
+export * from './billing';
+export * from './notes';
+export * from './history';
 export * from './utils';
 
 // Import individual APIs
@@ -24,6 +32,14 @@ import { idleApi } from './idle';
 import { exportApi } from './export';
 import { windowApi } from './window';
 import { domainsApi } from './domains';
+import { eventsApi } from './events';
+import { scheduleApi } from './schedule';
+import { timersApi } from './timers';
+import { projectsApi } from './projects';
+import { tagsApi } from './tags';
+import { billingApi } from './billing';
+import { notesApi } from './notes';
+import { historyApi } from './history';
 
 // Combined API object for backward compatibility
 export const api: {
@@ -38,6 +54,14 @@ export const api: {
   export: typeof exportApi;
   window: typeof windowApi;
   domains: typeof domainsApi;
+  events: typeof eventsApi;
+  schedule: typeof scheduleApi;
+  timers: typeof timersApi;
+  projects: typeof projectsApi;
+  tags: typeof tagsApi;
+  billing: typeof billingApi;
+  notes: typeof notesApi;
+  history: typeof historyApi;
   getRules: typeof rulesApi.getRules;
   createRule: typeof rulesApi.createRule;
   updateRule: typeof rulesApi.updateRule;
@@ -54,6 +78,14 @@ export const api: {
   export: exportApi,
   window: windowApi,
   domains: domainsApi,
+  events: eventsApi,
+  schedule: scheduleApi,
+  timers: timersApi,
+  projects: projectsApi,
+  tags: tagsApi,
+  billing: billingApi,
+  notes: notesApi,
+  history: historyApi,
   // Convenience methods for backward compatibility
   getRules: rulesApi.getRules,
   createRule: rulesApi.createRule,
@@ -42,11 +42,26 @@ export const manualEntriesApi = {
     return invoke('delete_manual_entry', { id });
   },
   
+  // Returns the earlier and the later part; the later part keeps the id
+  splitManualEntry: (id: number, at: number): Promise<ManualEntry[]> => {
+    return invoke('split_manual_entry', { id, at });
+  },
+  
+  // Same category and project only; the latest entry is kept
+  mergeManualEntries: (ids: number[]): Promise<ManualEntry> => {
+    return invoke('merge_manual_entries', { ids });
+  },
+  
+  trimManualEntry: (id: number, startedAt: number, endedAt: number): Promise<ManualEntry> => {
+    return invoke('trim_manual_entry', { id, startedAt, endedAt });
+  },
+  
   startManualEntry: (categoryId: number, description?: string): Promise<number> => {
     return invoke('start_manual_entry', { categoryId, description });
   },
   
-  stopManualEntry: (): Promise<ManualEntry> => {
-    return invoke('stop_manual_entry');
+  // Stops the timer startManualEntry returned
+  stopManualEntry: (id: number): Promise<ManualEntry> => {
+    return invoke('stop_manual_entry', { id });
   },
 };
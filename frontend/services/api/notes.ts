@@ -0,0 +1,32 @@
+import { DateRange, Note } from '../../types';
+import { invoke, dateRangeToParams } from './utils';
+
+export const notesApi = {
+  getNotes: (range: DateRange): Promise<Note[]> => {
+    return invoke('get_notes', dateRangeToParams(range));
+  },
+
+  getActivityNotes: (activityId: number): Promise<Note[]> => {
+    return invoke('get_activity_notes', { activityId });
+  },
+
+  addActivityNote: (activityId: number, body: string): Promise<Note> => {
+    return invoke('add_activity_note', { activityId, body });
+  },
+
+  addSpanNote: (startedAt: number, endedAt: number, body: string): Promise<Note> => {
+    return invoke('add_span_note', { startedAt, endedAt, body });
+  },
+
+  updateNote: (id: number, body: string): Promise<Note> => {
+    return invoke('update_note', { id, body });
+  },
+
+  deleteNote: (id: number): Promise<void> => {
+    return invoke('delete_note', { id });
+  },
+
+  searchNotes: (query: string, limit?: number): Promise<Note[]> => {
+    return invoke('search_notes', { query, limit: limit ?? null });
+  },
+};
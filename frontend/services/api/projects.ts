@@ -0,0 +1,55 @@
+import { Client, Project } from '../../types';
+import { invoke } from './utils';
+
+export const projectsApi = {
+  getClients: (includeArchived = false): Promise<Client[]> => {
+    return invoke('get_clients', { includeArchived });
+  },
+
+  createClient: (name: string, color?: string): Promise<Client> => {
+    return invoke('create_client', { name, color });
+  },
+
+  updateClient: (client: Client): Promise<Client> => {
+    return invoke('update_client', {
+      id: client.id,
+      name: client.name,
+      color: client.color,
+      archived: client.archived,
+    });
+  },
+
+  deleteClient: (id: number): Promise<void> => {
+    return invoke('delete_client', { id });
+  },
+
+  getProjects: (includeArchived = false): Promise<Project[]> => {
+    return invoke('get_projects', { includeArchived });
+  },
+
+  createProject: (name: string, clientId?: number | null, color?: string): Promise<Project> => {
+    return invoke('create_project', { name, clientId: clientId ?? null, color });
+  },
+
+  updateProject: (project: Project): Promise<Project> => {
+    return invoke('update_project', {
+      id: project.id,
+      name: project.name,
+      clientId: project.client_id,
+      color: project.color,
+      archived: project.archived,
+    });
+  },
+
+  deleteProject: (id: number): Promise<void> => {
+    return invoke('delete_project', { id });
+  },
+
+  setActivityProject: (id: number, projectId: number | null): Promise<void> => {
+    return invoke('set_activity_project', { id, projectId });
+  },
+
+  setManualEntryProject: (id: number, projectId: number | null): Promise<void> => {
+    return invoke('set_manual_entry_project', { id, projectId });
+  },
+};
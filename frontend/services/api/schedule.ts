@@ -0,0 +1,34 @@
+import { Schedule, ScheduleExceptionKind } from '../../types';
+import { invoke } from './utils';
+
+export const scheduleApi = {
+  getSchedule: (): Promise<Schedule> => {
+    return invoke('get_schedule');
+  },
+
+  setScheduleEnabled: (enabled: boolean): Promise<void> => {
+    return invoke('set_schedule_enabled', { enabled });
+  },
+
+  addScheduleWindow: (weekday: number, startMinute: number, endMinute: number): Promise<number> => {
+    return invoke('add_schedule_window', { weekday, startMinute, endMinute });
+  },
+
+  deleteScheduleWindow: (id: number): Promise<void> => {
+    return invoke('delete_schedule_window', { id });
+  },
+
+  addScheduleException: (
+    date: string,
+    kind: ScheduleExceptionKind,
+    startMinute?: number,
+    endMinute?: number,
+    label?: string
+  ): Promise<number> => {
+    return invoke('add_schedule_exception', { date, kind, startMinute, endMinute, label });
+  },
+
+  deleteScheduleException: (id: number): Promise<void> => {
+    return invoke('delete_schedule_exception', { id });
+  },
+};
@@ -1,4 +1,4 @@
-import { Settings } from '../../types';
+import { PurgeSummary, Settings } from '../../types';
 import { invoke } from './utils';
 
 export const settingsApi = {
@@ -21,4 +21,8 @@ export const settingsApi = {
   isAutostartEnabled: (): Promise<boolean> => {
     return invoke('is_autostart_enabled');
   },
+
+  purgeExcludedData: (): Promise<PurgeSummary> => {
+    return invoke('purge_excluded_data');
+  },
 };
@@ -1,4 +1,4 @@
-import { DailyStats, AppUsage, CategoryUsage, HourlyActivity, DateRange, StatsResponse } from '../../types';
+import { DailyStats, AppUsage, CategoryUsage, HourlyActivity, DateRange, StatsResponse, ProjectStat } from '../../types';
 import { invoke, dateRangeToParams, dateToTimestamp } from './utils';
 
 export const statsApi = {
@@ -16,6 +16,13 @@ export const statsApi = {
     });
   },
   
+  getTopProjects: (range: DateRange, limit?: number): Promise<ProjectStat[]> => {
+    return invoke('get_top_projects', {
+      ...dateRangeToParams(range),
+      limit: limit || 10,
+    });
+  },
+
   getCategoryUsage: (range: DateRange): Promise<CategoryUsage[]> => {
     return invoke('get_category_usage', dateRangeToParams(range));
   },
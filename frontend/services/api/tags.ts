@@ -0,0 +1,42 @@
+import { ActivityFilter, DateRange, Tag, TagLinks, TagUsage } from '../../types';
+import { invoke, dateRangeToParams } from './utils';
+
+export const tagsApi = {
+  getTags: (): Promise<Tag[]> => {
+    return invoke('get_tags');
+  },
+
+  createTag: (name: string, color?: string): Promise<Tag> => {
+    return invoke('create_tag', { name, color });
+  },
+
+  updateTag: (tag: Tag): Promise<Tag> => {
+    return invoke('update_tag', { id: tag.id, name: tag.name, color: tag.color });
+  },
+
+  deleteTag: (id: number): Promise<void> => {
+    return invoke('delete_tag', { id });
+  },
+
+  /** Returns how many activities and entries were newly tagged */
+  tagItems: (tagId: number, activityIds: number[] = [], manualEntryIds: number[] = []): Promise<number> => {
+    return invoke('tag_items', { tagId, activityIds, manualEntryIds });
+  },
+
+  untagItems: (tagId: number, activityIds: number[] = [], manualEntryIds: number[] = []): Promise<number> => {
+    return invoke('untag_items', { tagId, activityIds, manualEntryIds });
+  },
+
+  /** Tag every activity in the range matching the filter */
+  bulkTagActivities: (tagId: number, range: DateRange, filter?: ActivityFilter): Promise<number> => {
+    return invoke('bulk_tag_activities', { tagId, ...dateRangeToParams(range), filter });
+  },
+
+  getTagLinks: (range: DateRange): Promise<TagLinks> => {
+    return invoke('get_tag_links', dateRangeToParams(range));
+  },
+
+  getTagUsage: (range: DateRange): Promise<TagUsage[]> => {
+    return invoke('get_tag_usage', dateRangeToParams(range));
+  },
+};
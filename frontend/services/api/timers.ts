@@ -0,0 +1,20 @@
+import { ManualEntry, RunningTimer } from '../../types';
+import { invoke } from './utils';
+
+export const timersApi = {
+  startTimer: (name: string, categoryId?: number, description?: string): Promise<RunningTimer> => {
+    return invoke('start_timer', { name, categoryId, description });
+  },
+
+  stopTimer: (id: number): Promise<ManualEntry> => {
+    return invoke('stop_timer', { id });
+  },
+
+  listTimers: (): Promise<RunningTimer[]> => {
+    return invoke('list_timers');
+  },
+
+  discardTimer: (id: number): Promise<void> => {
+    return invoke('discard_timer', { id });
+  },
+};
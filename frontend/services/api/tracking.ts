@@ -1,3 +1,4 @@
+import { PauseOptions, TrackingStatus } from '../../types';
 import { invoke } from './utils';
 
 export const trackingApi = {
@@ -5,15 +6,20 @@ export const trackingApi = {
     return invoke('get_today_total');
   },
   
-  pauseTracking: (): Promise<void> => {
-    return invoke('pause_tracking');
+  pauseTracking: (options: PauseOptions = {}): Promise<void> => {
+    return invoke('pause_tracking', {
+      durationSecs: options.durationSecs,
+      resumeAt: options.resumeAt,
+      reason: options.reason,
+      categoryId: options.categoryId,
+    });
   },
   
   resumeTracking: (): Promise<void> => {
     return invoke('resume_tracking');
   },
   
-  getTrackingStatus: (): Promise<{ isTracking: boolean; isPaused: boolean; currentApp: string | null }> => {
+  getTrackingStatus: (): Promise<TrackingStatus> => {
     return invoke('get_tracking_status');
   },
   
@@ -34,6 +34,7 @@ interface AppState {
   selectedDateRange: DateRange;
   dateRangePreset: DateRangePreset;
   isTrackingPaused: boolean;
+  pauseTimerId: number | null; // Timer recording the current pause
   isThinkingMode: boolean;
 
   // Data
@@ -54,6 +55,7 @@ interface AppState {
   setDateRangePreset: (preset: DateRangePreset) => void;
   getDateRange: () => DateRange;
   setIsTrackingPaused: (paused: boolean) => void;
+  setPauseTimerId: (id: number | null) => void;
   setIsThinkingMode: (thinking: boolean) => void;
   setActivities: (activities: Activity[]) => void;
   setCategories: (categories: Category[]) => void;
@@ -94,6 +96,7 @@ const store = create<AppState>()(
       },
       dateRangePreset: 'today',
       isTrackingPaused: false,
+      pauseTimerId: null,
       isThinkingMode: false,
 
       // Initial Data
@@ -136,6 +139,8 @@ const store = create<AppState>()(
 
       setIsTrackingPaused: (paused) => set({ isTrackingPaused: paused }),
 
+      setPauseTimerId: (id) => set({ pauseTimerId: id }),
+
       setIsThinkingMode: (thinking) => set({ isThinkingMode: thinking }),
 
       setActivities: (activities) => set({ activities }),
@@ -163,6 +168,7 @@ const store = create<AppState>()(
       partialize: (state) => ({
         settings: state.settings,
         isTrackingPaused: state.isTrackingPaused,
+        pauseTimerId: state.pauseTimerId,
         dateRangePreset: state.dateRangePreset,
         selectedDateRange: state.selectedDateRange,
         // Don't persist consecutive counters - they should reset on app restart
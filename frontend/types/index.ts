@@ -4,8 +4,21 @@ export interface Activity {
   app_name: string;
   window_title: string | null;
   domain: string | null;
+  url: string | null;
+  project: string | null; // Project recognised from an editor window title
+  document: string | null; // File open in the editor
+  exe_path: string | null; // Executable of the window's process (Linux)
+  cmdline: string | null;
+  cwd: string | null; // Working directory; for terminals, of the program running inside
+  git_repo: string | null; // Root of the git repository enclosing cwd
+  git_branch: string | null;
+  suppression_reason: string | null; // Why idle detection was suppressed: media:<player>, inhibit or app:<name>
   category_id: number | null;
+  category_source: CategorySource | null; // null for activities recorded before sources were tracked
+  category_rule_id: number | null; // Rule that set the category, when the source is 'rule'
+  project_id: number | null; // Project the activity is assigned to (see Project)
   started_at: number; // Unix timestamp
+  ended_at: number; // Unix timestamp
   duration_sec: number;
   is_idle: boolean;
 }
@@ -24,10 +37,55 @@ export interface Category {
   sort_order: number;
   is_system?: boolean;
   is_pinned?: boolean;
+  /** Category this one is nested under */
+  parent_id?: number | null;
+  /** Time in this category is billed at its hourly rate; null follows the parent */
+  is_billable?: boolean | null;
+}
+
+/** Free-text note on one activity or on a time span */
+export interface Note {
+  id: number;
+  activity_id: number | null; // set for notes on an activity, whose span they share
+  started_at: number;
+  ended_at: number;
+  body: string;
+  created_at: number;
+  updated_at: number;
+}
+
+/** A recorded change to activities, manual entries, categories or rules */
+export interface Change {
+  id: number;
+  summary: string;
+  created_at: number;
+  undone_at: number | null;
+  rows: number; // Row changes recorded, including tags, notes and events that followed the rows
+  tables: string[];
+}
+
+/** Hourly rate of a category from effective_from until its next rate */
+export interface CategoryRate {
+  id: number;
+  category_id: number;
+  hourly_rate: number;
+  currency: string;
+  effective_from: number;
+}
+
+/** Billed time and amount of a category at one rate */
+export interface Earning {
+  category_id: number;
+  category_name: string;
+  hourly_rate: number | null; // null if no rate is in effect
+  currency: string | null;
+  seconds: number;
+  billed_seconds: number; // after rounding
+  amount: number;
 }
 
 // Rule types
-export type RuleType = 'app_name' | 'window_title' | 'domain';
+export type RuleType = 'app_name' | 'window_title' | 'domain' | 'project' | 'exe_path';
 
 export interface Rule {
   id: number;
@@ -42,10 +100,36 @@ export interface ManualEntry {
   id: number;
   description: string | null;
   category_id: number | null;
+  project_id: number | null;
   started_at: number;
   ended_at: number;
 }
 
+// Clients and projects
+export interface Client {
+  id: number;
+  name: string;
+  color: string;
+  archived: boolean;
+}
+
+export interface Project {
+  id: number;
+  name: string;
+  client_id: number | null;
+  color: string;
+  archived: boolean;
+}
+
+// Part of an idle period classified from the idle-return prompt
+export interface IdleSegment {
+  started_at: number; // Unix timestamp, within the idle period
+  ended_at: number;
+  category_id: number;
+  description?: string | null;
+  project_id?: number | null;
+}
+
 // Settings types
 export interface Settings {
   idle_threshold_minutes: number;
@@ -58,6 +142,14 @@ export interface Settings {
   // Exact seconds for precision (optional, calculated from minutes if not provided)
   idle_threshold_seconds?: number;
   idle_prompt_threshold_seconds?: number;
+  // Privacy filter applied before anything is stored
+  privacy_ignored_apps?: string[]; // Apps never recorded (supports * wildcards)
+  privacy_masked_apps?: string[]; // Apps recorded with the title replaced by "[hidden]"
+  privacy_title_redactions?: string[]; // Regular expressions replaced by "[redacted]"
+  privacy_skip_private_windows?: boolean; // Skip private/incognito browser windows (default true)
+  never_idle_apps?: string[]; // Apps that never count as idle while focused (wildcards as in rules)
+  billing_rounding_minutes?: number; // Round billed time up to this increment (0 = exact)
+  billing_rounding_scope?: 'entry' | 'day'; // Round every activity or each day's total
   // Frontend-only properties (not synced with backend)
   idleThreshold?: number; // Convenience property for UI (calculated from idle_threshold_seconds/minutes)
   pollingInterval?: number; // Frontend-only setting for UI
@@ -69,12 +161,96 @@ export interface Settings {
   plugin_registry_urls?: string[];
 }
 
+// Result of applying the privacy settings to recorded activities
+export interface PurgeSummary {
+  deleted: number;
+  masked: number;
+  redacted: number;
+  events_deleted: number;
+  events_filtered: number;
+}
+
+// Work-hours schedule; weekday 0 is Monday, times are minutes since local midnight
+export interface ScheduleWindow {
+  id: number;
+  weekday: number;
+  start_minute: number;
+  end_minute: number;
+}
+
+export type ScheduleExceptionKind = 'holiday' | 'workday';
+
+export interface ScheduleException {
+  id: number;
+  date: string; // YYYY-MM-DD
+  kind: ScheduleExceptionKind;
+  start_minute: number | null;
+  end_minute: number | null;
+  label: string | null;
+}
+
+export interface Schedule {
+  enabled: boolean;
+  windows: ScheduleWindow[];
+  exceptions: ScheduleException[];
+  in_schedule: boolean | null;
+}
+
+export type PauseReason = 'manual' | 'outside_schedule';
+
+// Timer that becomes a manual entry when stopped; keeps running across restarts
+export interface RunningTimer {
+  id: number;
+  name: string;
+  category_id: number | null;
+  description: string | null;
+  started_at: number;
+}
+
+// Manual pause; resume_at is null for a pause that lasts until resumed
+export interface ManualPause {
+  started_at: number;
+  resume_at: number | null;
+  reason: string | null;
+  category_id: number | null;
+}
+
+// Pass either durationSecs or resumeAt (unix seconds); categoryId records the pause as a manual entry
+export interface PauseOptions {
+  durationSecs?: number;
+  resumeAt?: number;
+  reason?: string;
+  categoryId?: number;
+}
+
+export interface TrackingStatus {
+  isTracking: boolean;
+  isPaused: boolean;
+  pauseReasons: PauseReason[];
+  manualPause: ManualPause | null;
+  inSchedule: boolean | null; // null while no schedule is enabled
+  currentApp: string | null;
+  activeSessionDuration: number | null;
+  runningTimers: RunningTimer[];
+}
+
 // Statistics types
 export interface DailyStats {
   total_duration_sec: number;
   productive_duration_sec: number;
   categories: CategoryStats[];
   top_apps: AppStats[];
+  category_tree: CategoryTreeNode[];
+}
+
+/** Category with its own time and the time including its subcategories */
+export interface CategoryTreeNode {
+  category_id: number;
+  name: string;
+  color: string;
+  self_seconds: number;
+  total_seconds: number;
+  children: CategoryTreeNode[];
 }
 
 export interface CategoryStats {
@@ -95,6 +271,9 @@ export interface StatsResponse {
   productive_seconds: number;
   category_breakdown: { category_id: number; category_name: string; color: string; seconds: number }[];
   app_breakdown: { app_name: string; seconds: number }[];
+  category_tree: CategoryTreeNode[];
+  earnings: Earning[];
+  project_breakdown: { project_id: number; project_name: string; color: string; seconds: number }[];
 }
 
 // Timeline types
@@ -132,12 +311,90 @@ export interface TrackerStatus {
 
 
 // Domain statistics
+// Event reported by an external watcher (merged heartbeats)
+export interface WatcherEvent {
+  id: number;
+  source: string;
+  started_at: number;
+  ended_at: number;
+  data: Record<string, unknown>;
+  activity_id: number | null;
+}
+
 export interface DomainStat {
   domain: string;
   duration_sec: number;
   count: number;
 }
 
+export interface ProjectStat {
+  project: string;
+  duration_sec: number;
+}
+
+// Tags: free-form labels on activities and manual entries
+export interface Tag {
+  id: number;
+  name: string;
+  color: string;
+}
+
+export interface TagUsage {
+  tag: Tag;
+  duration_sec: number;
+}
+
+/** [activity or manual entry id, tag id] pairs */
+export interface TagLinks {
+  activities: [number, number][];
+  manual_entries: [number, number][];
+}
+
+/** Filters for get_activities; empty lists do not filter */
+export interface ActivityFilter {
+  exclude_idle?: boolean;
+  category_ids?: number[];
+  include_tags?: number[]; // Activities with at least one of these tags
+  exclude_tags?: number[]; // Leave out activities with any of these tags
+  only_idle?: boolean;
+  app_pattern?: string; // Wildcards as in rules: "*code*", "code*", "*code"
+  title_pattern?: string;
+  domain_pattern?: string;
+}
+
+export type CategorySource = 'rule' | 'manual' | 'plugin' | 'default';
+
+export interface ReapplyOptions {
+  start?: number; // Only activities starting in [start, end]
+  end?: number;
+  rule_ids?: number[]; // Only recategorize activities where one of these rules wins over all the others
+  force?: boolean; // Also recategorize activities categorized by hand
+}
+
+/** Result of a rule reapplication, also sent as `reapply-progress` events while it runs */
+export interface ReapplyProgress {
+  processed: number;
+  total: number;
+  updated: number;
+  skipped_manual: number;
+}
+
+export type BulkAction =
+  | { type: 'set_category'; category_id: number | null }
+  | { type: 'set_project'; project_id: number | null }
+  | { type: 'add_tags'; tag_ids: number[] }
+  | { type: 'remove_tags'; tag_ids: number[] }
+  | { type: 'set_idle'; is_idle: boolean }
+  | { type: 'delete' };
+
+export interface BulkResult {
+  dry_run: boolean;
+  matched: number;
+  affected: number; // Activities changed, or tag links added/removed
+  matched_duration_sec: number;
+  preview: Activity[]; // First matching activities, before the change
+}
+
 // Statistics types (additional)
 export interface AppUsage {
   app_name: string;
@@ -148,8 +405,13 @@ export interface AppUsage {
 
 export interface CategoryUsage {
   category: Category;
+  /** Assigned to the category itself */
   duration_sec: number;
+  /** Of all time in the range, including subcategories */
   percentage: number;
+  /** Including subcategories */
+  total_duration_sec: number;
+  children: CategoryUsage[];
 }
 
 export interface HourlyActivity {
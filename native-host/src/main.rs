@@ -0,0 +1,267 @@
+//! Native messaging host - Forwards the active browser tab to Time Tracker
+//!
+//! Browsers start this binary and talk to it over stdin/stdout using the
+//! Chrome/Firefox native messaging protocol: every message is a UTF-8 JSON
+//! document prefixed with its length as a 32-bit unsigned integer in native
+//! byte order. The companion extension sends one message per tab change:
+//!
+//! ```json
+//! { "url": "https://github.com/", "title": "GitHub", "incognito": false, "browser": "firefox" }
+//! ```
+//!
+//! Each message becomes a heartbeat on the app's local ingestion endpoint
+//! (`POST /api/v1/heartbeat`, source `browser`); the tracker matches it to the
+//! focused browser window by title. Incognito tabs are forwarded without URL or title.
+//!
+//! Requests authenticate with the token the app writes to `ingest-token` in its data
+//! directory, or with `TIMETRACKER_INGEST_TOKEN` when set.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+/// Event source the tracker looks up browser reports under
+const SOURCE: &str = "browser";
+
+/// Port of the app's ingestion endpoint unless `TIMETRACKER_INGEST_PORT` is set
+const DEFAULT_PORT: u16 = 5670;
+
+/// File in the app's data directory holding the ingestion token
+const TOKEN_FILE: &str = "ingest-token";
+
+/// Heartbeats for the same tab within this many seconds merge into one event
+const PULSETIME: i64 = 30;
+
+/// Browsers cap messages to the host at 4 GB; anything this large is a broken stream
+const MAX_MESSAGE_BYTES: u32 = 1024 * 1024;
+
+/// Tab report sent by the extension
+#[derive(Debug, Deserialize)]
+struct TabMessage {
+    url: Option<String>,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    incognito: bool,
+    browser: Option<String>,
+    /// Unix timestamp in seconds; defaults to the time the message is received
+    timestamp: Option<i64>,
+}
+
+fn main() {
+    let port = std::env::var("TIMETRACKER_INGEST_PORT")
+        .ok()
+        .and_then(|v| v.parse::<u16>().ok())
+        .unwrap_or(DEFAULT_PORT);
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let token = std::env::var("TIMETRACKER_INGEST_TOKEN")
+        .ok()
+        .or_else(|| {
+            let path = dirs::data_dir()?.join("timetracker").join(TOKEN_FILE);
+            std::fs::read_to_string(path).ok()
+        })
+        .map(|token| token.trim().to_string())
+        .unwrap_or_default();
+
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+
+    loop {
+        let message = match read_message(&mut stdin) {
+            Ok(Some(message)) => message,
+            // The browser closes stdin when the extension disconnects
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("time-tracker-native-host: {}", e);
+                break;
+            }
+        };
+
+        let reply = match handle_message(&message, addr, &token) {
+            Ok(()) => serde_json::json!({ "ok": true }),
+            Err(e) => serde_json::json!({ "ok": false, "error": e }),
+        };
+        if write_message(&mut stdout, &reply).is_err() {
+            break;
+        }
+    }
+}
+
+/// Forward one extension message to the app
+fn handle_message(message: &[u8], addr: SocketAddr, token: &str) -> Result<(), String> {
+    let tab: TabMessage =
+        serde_json::from_slice(message).map_err(|e| format!("Invalid tab message: {}", e))?;
+    let heartbeat = heartbeat_for(tab, now());
+    post_heartbeat(addr, token, &heartbeat)
+}
+
+/// Read one length-prefixed message; None on a clean end of stream
+fn read_message(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match input.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let length = u32::from_ne_bytes(length);
+    if length > MAX_MESSAGE_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes exceeds the limit", length),
+        ));
+    }
+
+    let mut message = vec![0u8; length as usize];
+    input.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+/// Write one length-prefixed message
+fn write_message(output: &mut impl Write, message: &serde_json::Value) -> io::Result<()> {
+    let bytes = serde_json::to_vec(message)?;
+    output.write_all(&(bytes.len() as u32).to_ne_bytes())?;
+    output.write_all(&bytes)?;
+    output.flush()
+}
+
+/// Build the ingestion heartbeat for a tab report. Incognito tabs only report that a
+/// private tab is active: URL and title are left out.
+fn heartbeat_for(tab: TabMessage, received_at: i64) -> serde_json::Value {
+    let (url, title) = if tab.incognito { (None, None) } else { (tab.url, Some(tab.title)) };
+    serde_json::json!({
+        "source": SOURCE,
+        "timestamp": tab.timestamp.unwrap_or(received_at),
+        "duration": 0,
+        "pulsetime": PULSETIME,
+        "data": {
+            "url": url,
+            "title": title,
+            "incognito": tab.incognito,
+            "browser": tab.browser,
+        },
+    })
+}
+
+/// POST a heartbeat to the ingestion endpoint
+fn post_heartbeat(addr: SocketAddr, token: &str, heartbeat: &serde_json::Value) -> Result<(), String> {
+    let body = heartbeat.to_string();
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_secs(2))
+        .map_err(|e| format!("Time Tracker is not reachable on {}: {}", addr, e))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5))).ok();
+
+    write!(
+        stream,
+        "POST /api/v1/heartbeat HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        addr,
+        token,
+        body.len(),
+        body
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+    let status = response.split(' ').nth(1).unwrap_or_default();
+    if status == "200" {
+        Ok(())
+    } else {
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+        Err(format!("Time Tracker rejected the heartbeat ({}): {}", status, body))
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Cursor};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_message_framing_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &serde_json::json!({ "ok": true })).unwrap();
+        write_message(&mut buffer, &serde_json::json!({ "ok": false })).unwrap();
+        assert_eq!(&buffer[..4], &11u32.to_ne_bytes());
+
+        let mut input = Cursor::new(buffer);
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), br#"{"ok":true}"#);
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), br#"{"ok":false}"#);
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_oversized_message_is_rejected() {
+        let mut input = Cursor::new((MAX_MESSAGE_BYTES + 1).to_ne_bytes().to_vec());
+        assert!(read_message(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_incognito_tab_is_never_identified() {
+        let tab: TabMessage = serde_json::from_str(
+            r#"{ "url": "https://example.com/secret", "title": "Secret", "incognito": true }"#,
+        )
+        .unwrap();
+        let heartbeat = heartbeat_for(tab, 1_700_000_000);
+        assert!(heartbeat["data"]["url"].is_null());
+        assert!(heartbeat["data"]["title"].is_null());
+        assert!(!heartbeat.to_string().contains("Secret"));
+        assert_eq!(heartbeat["data"]["incognito"], true);
+        assert_eq!(heartbeat["timestamp"], 1_700_000_000);
+    }
+
+    #[test]
+    fn test_tab_message_is_posted_as_heartbeat() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            let mut authorization = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("Content-Length: ") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                if let Some(value) = line.strip_prefix("Authorization: ") {
+                    authorization = value.trim().to_string();
+                }
+            }
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                .unwrap();
+            (request_line, authorization, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+        });
+
+        let message = br#"{ "url": "https://github.com/", "title": "GitHub", "browser": "chrome", "timestamp": 1700000000 }"#;
+        handle_message(message, addr, "secret").unwrap();
+
+        let (request_line, authorization, heartbeat) = server.join().unwrap();
+        assert_eq!(request_line, "POST /api/v1/heartbeat HTTP/1.1\r\n");
+        assert_eq!(authorization, "Bearer secret");
+        assert_eq!(heartbeat["source"], "browser");
+        assert_eq!(heartbeat["timestamp"], 1_700_000_000);
+        assert_eq!(heartbeat["data"]["url"], "https://github.com/");
+        assert_eq!(heartbeat["data"]["title"], "GitHub");
+    }
+}